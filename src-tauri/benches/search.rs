@@ -0,0 +1,148 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use pocket_syllabus_lib::database::{self, SearchQuery, TimetableQuery};
+use pocket_syllabus_lib::scrape::{Course, Day, Lecturer, Period, Semester, TimeTable};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use tokio::runtime::Runtime;
+
+const COURSES: usize = 30_000;
+
+const DEPARTMENTS: [(&str, &str); 8] = [
+    ("MTH", "数学系"),
+    ("PHY", "物理学系"),
+    ("CHM", "化学系"),
+    ("CSC", "情報工学系"),
+    ("MEC", "機械系"),
+    ("EEE", "電気電子系"),
+    ("ARC", "建築学系"),
+    ("LAH", "文系教養科目"),
+];
+const WORDS: [&str; 10] = [
+    "線形代数",
+    "量子力学",
+    "有機化学",
+    "機械学習",
+    "熱力学",
+    "電磁気学",
+    "建築史",
+    "統計学",
+    "アルゴリズム",
+    "哲学",
+];
+const NAMES: [&str; 10] = [
+    "山田", "佐藤", "鈴木", "高橋", "田中", "伊藤", "渡辺", "中村", "小林", "加藤",
+];
+const DAYS: [fn() -> Day; 5] = [
+    || Day::Monday,
+    || Day::Tuesday,
+    || Day::Wednesday,
+    || Day::Thursday,
+    || Day::Friday,
+];
+const PERIODS: [fn() -> Period; 6] = [
+    || Period::First,
+    || Period::Second,
+    || Period::Third,
+    || Period::Fourth,
+    || Period::Fifth,
+    || Period::Sixth,
+];
+const SEMESTERS: [fn() -> Semester; 4] = [
+    || Semester::First,
+    || Semester::Second,
+    || Semester::Third,
+    || Semester::Fourth,
+];
+
+// deterministic spread of departments, levels, slots and lecturers over n courses
+fn course(n: usize) -> Course {
+    let (prefix, department) = DEPARTMENTS[n % DEPARTMENTS.len()];
+
+    Course {
+        university: "東京工業大学".to_string(),
+        title: format!("{}{}", WORDS[n / 7 % WORDS.len()], n),
+        department: department.to_string(),
+        lecturer: (0..1 + n % 3)
+            .map(|i| Lecturer {
+                name: format!("{} {}", NAMES[(n + i * 3) % NAMES.len()], i),
+                url: "".to_string(),
+            })
+            .collect(),
+        time_table: (0..1 + n % 2)
+            .map(|i| TimeTable {
+                day: DAYS[(n + i) % DAYS.len()](),
+                period: PERIODS[n / 5 % PERIODS.len()](),
+                room: format!("W{}", n % 900),
+            })
+            .collect(),
+        code: format!("{}.{}{}{:02}", prefix, 'A', 1 + n % 6, n % 100),
+        credit: 1 + (n % 4) as i32,
+        year: 2020 + (n % 5) as i32,
+        semester: vec![SEMESTERS[n % SEMESTERS.len()]()],
+        ..Default::default()
+    }
+}
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    database::migrate(&pool).await.unwrap();
+
+    for n in 0..COURSES {
+        database::insert_course(&pool, &course(n)).await.unwrap();
+    }
+
+    pool
+}
+
+type NamedQuery = (&'static str, fn() -> SearchQuery);
+
+fn search(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let pool = runtime.block_on(seeded_pool());
+
+    let queries: [NamedQuery; 6] = [
+        ("everything", SearchQuery::default),
+        ("department", || SearchQuery {
+            department: vec!["情報工学系".to_string()],
+            ..Default::default()
+        }),
+        ("title", || SearchQuery {
+            title: vec!["機械学習".to_string()],
+            ..Default::default()
+        }),
+        ("text", || SearchQuery {
+            text: "機械学習".to_string(),
+            ..Default::default()
+        }),
+        ("lecturer", || SearchQuery {
+            lecturer: vec!["山田".to_string()],
+            ..Default::default()
+        }),
+        ("grade, slot and quarter", || SearchQuery {
+            grade: vec!["200".to_string(), "300".to_string()],
+            quarter: vec![Semester::First],
+            timetable: vec![TimetableQuery {
+                day: Day::Monday,
+                period: Period::First,
+            }],
+            ..Default::default()
+        }),
+    ];
+
+    let mut group = c.benchmark_group(format!("search_courses/{} courses", COURSES));
+    group.sample_size(20);
+    for (name, query) in queries {
+        group.bench_function(name, |b| {
+            b.to_async(&runtime)
+                .iter(|| database::search_courses(&pool, query()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, search);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS archived_pages(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    url TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    etag TEXT,
+    last_modified TEXT,
+    path TEXT NOT NULL
+);
@@ -0,0 +1,18 @@
+-- Syllabus text searchable with MATCH, one row per course with rowid = courses.id.
+-- The trigram tokenizer needs no word segmentation, which Japanese text lacks.
+CREATE VIRTUAL TABLE IF NOT EXISTS course_texts USING fts5(
+    title, abstract, goal, keywords, schedule, textbook,
+    tokenize = 'trigram'
+);
+
+INSERT INTO course_texts (rowid, title, abstract, goal, keywords, schedule, textbook)
+SELECT
+    c.id, c.title, COALESCE(c.abstract, ''), COALESCE(c.goal, ''),
+    COALESCE((SELECT group_concat(keyword, ' ') FROM keywords WHERE course_id = c.id), ''),
+    COALESCE((SELECT group_concat(plan || ' ' || assignment, ' ') FROM schedules WHERE course_id = c.id), ''),
+    COALESCE(c.textbook, '') || ' ' || COALESCE(c.reference_book, '')
+FROM courses AS c;
+
+CREATE TRIGGER IF NOT EXISTS courses_delete_text AFTER DELETE ON courses BEGIN
+    DELETE FROM course_texts WHERE rowid = old.id;
+END;
@@ -0,0 +1,13 @@
+-- Copies of the searchable text in the form produced by normalize.rs, filled in by
+-- insert_course and, for rows that predate this migration, by database::migrate.
+ALTER TABLE courses ADD COLUMN title_normalized TEXT;
+ALTER TABLE lecturers ADD COLUMN name_normalized TEXT;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS course_texts_normalized USING fts5(
+    title, abstract, goal, keywords, schedule, textbook,
+    tokenize = 'trigram'
+);
+
+CREATE TRIGGER IF NOT EXISTS courses_delete_text_normalized AFTER DELETE ON courses BEGIN
+    DELETE FROM course_texts_normalized WHERE rowid = old.id;
+END;
@@ -0,0 +1,18 @@
+-- User-editable rules that hide courses from search results; see database/rules.rs.
+-- The seeded ones keep the old behaviour of hiding courses whose lecturer is a
+-- placeholder, but can now be edited or switched off. Placeholders are whole names,
+-- so real names with 教員 in them are left alone.
+CREATE TABLE IF NOT EXISTS search_rules(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    name TEXT NOT NULL,
+    action TEXT NOT NULL,
+    field TEXT NOT NULL,
+    match_kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT 1
+);
+
+INSERT INTO search_rules (name, action, field, match_kind, pattern)
+VALUES ('担当教員が各教員の講義', 'Exclude', 'Lecturer', 'Equals', '各教員'),
+    ('担当教員が未定の講義', 'Exclude', 'Lecturer', 'Equals', '未定'),
+    ('担当教員が教員未定の講義', 'Exclude', 'Lecturer', 'Equals', '教員未定');
@@ -0,0 +1,124 @@
+-- Child rows now go away with their course, and a course is stored once per
+-- code, year and title. SQLite cannot add ON DELETE CASCADE to an existing foreign
+-- key, so each child table is rebuilt; rows whose course no longer exists are
+-- dropped on the way.
+CREATE TABLE lecturers_new(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    course_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    url TEXT,
+    name_normalized TEXT,
+    FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE
+);
+INSERT INTO lecturers_new (id, course_id, name, url, name_normalized)
+SELECT id, course_id, name, url, name_normalized FROM lecturers
+WHERE course_id IN (SELECT id FROM courses);
+DROP TABLE lecturers;
+ALTER TABLE lecturers_new RENAME TO lecturers;
+
+CREATE TABLE timetables_new(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    course_id INTEGER NOT NULL,
+    day INTEGER NOT NULL,
+    periods INTEGER NOT NULL,
+    room TEXT NOT NULL,
+    FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE
+);
+INSERT INTO timetables_new (id, course_id, day, periods, room)
+SELECT id, course_id, day, periods, room FROM timetables
+WHERE course_id IN (SELECT id FROM courses);
+DROP TABLE timetables;
+ALTER TABLE timetables_new RENAME TO timetables;
+
+CREATE TABLE semesters_new(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    course_id INTEGER NOT NULL,
+    semester INTEGER NOT NULL,
+    FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE
+);
+INSERT INTO semesters_new (id, course_id, semester)
+SELECT id, course_id, semester FROM semesters
+WHERE course_id IN (SELECT id FROM courses);
+DROP TABLE semesters;
+ALTER TABLE semesters_new RENAME TO semesters;
+
+CREATE TABLE keywords_new(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    course_id INTEGER NOT NULL,
+    keyword TEXT NOT NULL,
+    FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE
+);
+INSERT INTO keywords_new (id, course_id, keyword)
+SELECT id, course_id, keyword FROM keywords
+WHERE course_id IN (SELECT id FROM courses);
+DROP TABLE keywords;
+ALTER TABLE keywords_new RENAME TO keywords;
+
+CREATE TABLE competencies_new(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    course_id INTEGER NOT NULL,
+    competency TEXT NOT NULL,
+    FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE
+);
+INSERT INTO competencies_new (id, course_id, competency)
+SELECT id, course_id, competency FROM competencies
+WHERE course_id IN (SELECT id FROM courses);
+DROP TABLE competencies;
+ALTER TABLE competencies_new RENAME TO competencies;
+
+CREATE TABLE related_courses_new(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    course_id INTEGER NOT NULL,
+    related_course_code TEXT NOT NULL,
+    FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE
+);
+INSERT INTO related_courses_new (id, course_id, related_course_code)
+SELECT id, course_id, related_course_code FROM related_courses
+WHERE course_id IN (SELECT id FROM courses);
+DROP TABLE related_courses;
+ALTER TABLE related_courses_new RENAME TO related_courses;
+
+CREATE TABLE schedules_new(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    course_id INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    plan TEXT NOT NULL,
+    assignment TEXT NOT NULL,
+    FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE
+);
+INSERT INTO schedules_new (id, course_id, count, plan, assignment)
+SELECT id, course_id, count, plan, assignment FROM schedules
+WHERE course_id IN (SELECT id FROM courses);
+DROP TABLE schedules;
+ALTER TABLE schedules_new RENAME TO schedules;
+
+-- Of courses stored more than once, the latest copy is kept; the cascades and the
+-- course_texts triggers remove the rest.
+DELETE FROM courses WHERE id NOT IN (
+    SELECT MAX(id) FROM courses GROUP BY code, year, title
+);
+CREATE UNIQUE INDEX IF NOT EXISTS courses_code_year_title ON courses(code, year, title);
+
+-- Every child table is read by course_id.
+CREATE INDEX IF NOT EXISTS lecturers_course_id ON lecturers(course_id);
+CREATE INDEX IF NOT EXISTS timetables_course_id ON timetables(course_id);
+CREATE INDEX IF NOT EXISTS semesters_course_id ON semesters(course_id);
+CREATE INDEX IF NOT EXISTS keywords_course_id ON keywords(course_id);
+CREATE INDEX IF NOT EXISTS competencies_course_id ON competencies(course_id);
+CREATE INDEX IF NOT EXISTS related_courses_course_id ON related_courses(course_id);
+CREATE INDEX IF NOT EXISTS schedules_course_id ON schedules(course_id);
+
+-- Columns SearchQuery filters, sorts or counts facets on.
+CREATE INDEX IF NOT EXISTS courses_university ON courses(university);
+CREATE INDEX IF NOT EXISTS courses_department ON courses(department);
+CREATE INDEX IF NOT EXISTS courses_year ON courses(year);
+CREATE INDEX IF NOT EXISTS courses_credit ON courses(credit);
+CREATE INDEX IF NOT EXISTS courses_language ON courses(language);
+CREATE INDEX IF NOT EXISTS courses_lecture_type ON courses(lecture_type);
+CREATE INDEX IF NOT EXISTS courses_sylbs_update ON courses(sylbs_update);
+CREATE INDEX IF NOT EXISTS timetables_day_periods ON timetables(day, periods);
+CREATE INDEX IF NOT EXISTS semesters_semester ON semesters(semester);
+CREATE INDEX IF NOT EXISTS keywords_keyword ON keywords(keyword);
+CREATE INDEX IF NOT EXISTS competencies_competency ON competencies(competency);
+
+CREATE INDEX IF NOT EXISTS archived_pages_url ON archived_pages(url, fetched_at);
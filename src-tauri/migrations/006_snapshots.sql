@@ -0,0 +1,12 @@
+-- Snapshots are copies of the crawled courses that can be shipped with the app or
+-- shared; see database/snapshot.rs. A snapshot file holds one row describing
+-- itself, with imported_at NULL. An app database keeps one row per snapshot merged
+-- into it.
+CREATE TABLE IF NOT EXISTS snapshots(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    source TEXT NOT NULL,
+    year INTEGER,
+    created_at INTEGER NOT NULL,
+    schema_version INTEGER NOT NULL,
+    imported_at INTEGER
+);
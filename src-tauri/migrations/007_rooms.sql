@@ -0,0 +1,21 @@
+-- Rooms parsed out of timetables.room, which can name several rooms at once; see
+-- database/rooms.rs. Filled in by insert_course and, for rows that predate this
+-- migration, by database::migrate. A room stays once known, even after the last
+-- course using it is gone.
+CREATE TABLE IF NOT EXISTS rooms(
+    id INTEGER NOT NULL PRIMARY KEY ,
+    name TEXT NOT NULL UNIQUE,
+    building TEXT,
+    campus TEXT
+);
+
+CREATE TABLE IF NOT EXISTS timetable_rooms(
+    timetable_id INTEGER NOT NULL,
+    room_id INTEGER NOT NULL,
+    PRIMARY KEY (timetable_id, room_id),
+    FOREIGN KEY (timetable_id) REFERENCES timetables(id) ON DELETE CASCADE,
+    FOREIGN KEY (room_id) REFERENCES rooms(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS timetable_rooms_room_id ON timetable_rooms(room_id);
+CREATE INDEX IF NOT EXISTS rooms_building ON rooms(campus, building);
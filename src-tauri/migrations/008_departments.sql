@@ -0,0 +1,104 @@
+-- Schools and the departments and graduate courses under them; see
+-- database/departments.rs. Seeded with the tree the frontend used to hardcode,
+-- then kept up by crawled list pages, whose department links carry the GakubuCD of
+-- the school and the GakkaCD of the department. A department no list page has
+-- placed yet is put under a school by its course codes, with guessed set.
+CREATE TABLE IF NOT EXISTS departments(
+    id INTEGER NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    parent_id INTEGER,
+    -- GakubuCD of a school, GakkaCD of a department
+    ocw_code INTEGER,
+    guessed INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (parent_id) REFERENCES departments(id) ON DELETE CASCADE
+);
+
+-- one name per parent: graduate courses such as エネルギーコース belong to
+-- several schools
+CREATE UNIQUE INDEX IF NOT EXISTS departments_parent_name
+    ON departments(IFNULL(parent_id, 0), name);
+CREATE INDEX IF NOT EXISTS departments_name ON departments(name);
+
+INSERT INTO departments (id, name, parent_id, ocw_code) VALUES
+    (1, '理学院', NULL, 1),
+    (2, '工学院', NULL, 2),
+    (3, '物質理工学院', NULL, 3),
+    (4, '情報理工学院', NULL, 4),
+    (5, '生命理工学院', NULL, 5),
+    (6, '環境・社会理工学院', NULL, 6),
+    (7, '工学院，物質理工学院，環境・社会理工学院共通科目', NULL, 11),
+    (8, '教養科目群', NULL, 7),
+    (9, '初年次専門科目', NULL, 10);
+
+INSERT INTO departments (name, parent_id) VALUES
+    ('数学系', 1),
+    ('物理学系', 1),
+    ('化学系', 1),
+    ('地球惑星科学系', 1),
+    ('数学コース', 1),
+    ('物理学コース', 1),
+    ('化学コース', 1),
+    ('エネルギーコース', 1),
+    ('エネルギー・情報コース', 1),
+    ('地球惑星科学コース', 1),
+    ('地球生命コース', 1),
+    ('機械系', 2),
+    ('システム制御系', 2),
+    ('電気電子系', 2),
+    ('情報通信系', 2),
+    ('経営工学系', 2),
+    ('機械コース', 2),
+    ('エネルギーコース', 2),
+    ('エネルギー・情報コース', 2),
+    ('エンジニアリングデザインコース', 2),
+    ('ライフエンジニアリングコース', 2),
+    ('原子核工学コース', 2),
+    ('システム制御コース', 2),
+    ('電気電子コース', 2),
+    ('情報通信コース', 2),
+    ('経営工学コース', 2),
+    ('材料系', 3),
+    ('応用化学系', 3),
+    ('材料コース', 3),
+    ('応用化学コース', 3),
+    ('エネルギーコース', 3),
+    ('エネルギー・情報コース', 3),
+    ('ライフエンジニアリングコース', 3),
+    ('原子核工学コース', 3),
+    ('地球生命コース', 3),
+    ('数理・計算科学系', 4),
+    ('情報工学系', 4),
+    ('数理・計算科学コース', 4),
+    ('情報工学コース', 4),
+    ('知能情報コース', 4),
+    ('エネルギー・情報コース', 4),
+    ('ライフエンジニアリングコース', 4),
+    ('生命理工学系', 5),
+    ('生命理工学コース', 5),
+    ('ライフエンジニアリングコース', 5),
+    ('地球生命コース', 5),
+    ('建築学系', 6),
+    ('土木・環境工学系', 6),
+    ('融合理工学系', 6),
+    ('建築学コース', 6),
+    ('土木工学コース', 6),
+    ('融合理工学コース', 6),
+    ('エンジニアリングデザインコース', 6),
+    ('都市・環境学コース', 6),
+    ('地球環境共創コース', 6),
+    ('エネルギーコース', 6),
+    ('エネルギー・情報コース', 6),
+    ('原子核工学コース', 6),
+    ('社会・人間科学コース', 6),
+    ('イノベーション科学コース', 6),
+    ('技術経営専門職学位課程', 6),
+    ('工学院，物質理工学院，環境・社会理工学院共通科目', 7),
+    ('文系教養科目', 8),
+    ('英語科目', 8),
+    ('第二外国語科目', 8),
+    ('日本語・日本文化科目', 8),
+    ('教職科目', 8),
+    ('アントレプレナーシップ科目', 8),
+    ('広域教養科目', 8),
+    ('理工系教養科目', 8),
+    ('キャリア科目', 8);
@@ -0,0 +1,209 @@
+use crate::database::{self, ArchivedPageRow, DbResult};
+use crate::error::AppError;
+use crate::scrape::{html_to_course, html_to_course_abstracts};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const LIST_PAGE: &str = "list";
+pub const DETAIL_PAGE: &str = "detail";
+
+// Every downloaded page is kept gzip-compressed under `dir`, one directory per url
+// and one file per fetch time, so the parser can be rerun without hitting OCW again.
+pub struct Archive {
+    dir: PathBuf,
+}
+
+pub struct Page {
+    pub body: String,
+    // false when the server answered 304 and the archived copy was used
+    pub modified: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReparseReport {
+    pub list_pages: usize,
+    pub detail_pages: usize,
+    pub courses: usize,
+    pub failed: Vec<String>,
+}
+
+impl Archive {
+    pub fn new(dir: PathBuf) -> Self {
+        Archive { dir }
+    }
+
+    // GET with If-None-Match / If-Modified-Since taken from the latest archived copy
+    pub async fn fetch(
+        &self,
+        pool: &SqlitePool,
+        client: &reqwest::Client,
+        url: &str,
+        kind: &str,
+    ) -> DbResult<Page> {
+        let latest = database::latest_archived_page(pool, url).await?;
+
+        let mut request = client.get(url);
+        if let Some(page) = &latest {
+            if let Some(etag) = &page.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &page.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            // a 304 has no body, so without an archived copy there is nothing to parse
+            let page = latest.ok_or_else(|| {
+                AppError::Network(format!("{} answered 304 with no archived copy", url))
+            })?;
+            return Ok(Page {
+                body: self.read(&page.path)?,
+                modified: false,
+            });
+        }
+
+        let response = response.error_for_status()?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.text().await?;
+
+        let fetched_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        let path = self.write(url, fetched_at, &body)?;
+
+        database::insert_archived_page(
+            pool,
+            &ArchivedPageRow {
+                id: 0,
+                url: url.to_string(),
+                kind: kind.to_string(),
+                fetched_at,
+                etag,
+                last_modified,
+                path,
+            },
+        )
+        .await?;
+
+        Ok(Page {
+            body,
+            modified: true,
+        })
+    }
+
+    // Parses the latest copy of every archived page again and replaces the courses
+    // in the database. Detail pages get their sylbs_update from the archived list rows.
+    pub async fn reparse(
+        &self,
+        pool: &SqlitePool,
+        base_url: &str,
+        progress: impl Fn(String),
+    ) -> DbResult<ReparseReport> {
+        let mut report = ReparseReport::default();
+        let mut sylbs_updates = HashMap::new();
+
+        for page in database::latest_archived_pages(pool, LIST_PAGE).await? {
+            let html = self.read(&page.path)?;
+            match html_to_course_abstracts(&html, base_url) {
+                Ok(courses) => {
+                    database::record_departments(pool, &courses).await?;
+                    for course in courses {
+                        sylbs_updates.insert(course.title.url, course.sylbs_update);
+                    }
+                }
+                Err(e) => report.failed.push(format!("{}: {}", page.url, e)),
+            }
+            report.list_pages += 1;
+        }
+
+        let pages = database::latest_archived_pages(pool, DETAIL_PAGE).await?;
+        let length = pages.len();
+
+        for page in pages {
+            report.detail_pages += 1;
+            progress(format!(
+                "Reparsing: {}/{} pages: {}",
+                report.detail_pages, length, page.url
+            ));
+
+            let html = self.read(&page.path)?;
+            let mut detail = match html_to_course(&html, base_url) {
+                Ok(detail) => detail,
+                Err(e) => {
+                    report.failed.push(format!("{}: {}", page.url, e));
+                    continue;
+                }
+            };
+            detail.sylbs_update = sylbs_updates.get(&page.url).cloned().unwrap_or_default();
+            detail.url = page.url;
+
+            database::replace_course(pool, &detail).await?;
+            report.courses += 1;
+        }
+
+        Ok(report)
+    }
+
+    // Removes every archived file; the rows go with database::reset_crawl_data.
+    pub fn clear(&self) -> DbResult<()> {
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, url: &str, fetched_at: i64, body: &str) -> DbResult<String> {
+        let path = Path::new(&url_to_dir_name(url)).join(format!("{}.html.gz", fetched_at));
+        let full_path = self.dir.join(&path);
+        std::fs::create_dir_all(full_path.parent().unwrap())?;
+
+        let mut encoder =
+            GzEncoder::new(std::fs::File::create(&full_path)?, Compression::default());
+        encoder.write_all(body.as_bytes())?;
+        encoder.finish()?;
+
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    fn read(&self, path: &str) -> DbResult<String> {
+        let mut decoder = GzDecoder::new(std::fs::File::open(self.dir.join(path))?);
+        let mut body = String::new();
+        decoder.read_to_string(&mut body)?;
+
+        Ok(body)
+    }
+}
+
+// https://www.ocw.titech.ac.jp/index.php?module=General&GakubuCD=1 -> www_ocw_titech_ac_jp_index_php_module_General_GakubuCD_1
+fn url_to_dir_name(url: &str) -> String {
+    let url = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+
+    url.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,814 @@
+use crate::archive::Archive;
+use crate::crawl::Crawler;
+use crate::database::{
+    self, Campus, CourseListItem, DbResult, Direction, ExportFormat, GraphFormat, GridAxis, Room,
+    SearchQuery, SnapshotMeta, SortKey, SortOrder, TimetableGrid,
+};
+use crate::error::AppError;
+use crate::import::{self, ImportReport};
+use crate::query;
+use crate::scrape::{Day, Period, Semester};
+use crate::settings::Settings;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+const CAMPUSES: [&str; 4] = ["Ookayama", "Suzukakedai", "Tamachi", "Online"];
+
+// The subcommands. They are parsed before the app is built, so they run without
+// the webview toolkit, which needs a display on Linux.
+pub fn command() -> Command {
+    let campus = || {
+        Arg::new("campus")
+            .long("campus")
+            .value_parser(CAMPUSES)
+            .help("Only rooms on this campus")
+    };
+    let year = || {
+        Arg::new("year")
+            .short('y')
+            .long("year")
+            .help("Academic year (default: the latest crawled)")
+    };
+    let output = || {
+        Arg::new("output")
+            .short('o')
+            .long("output")
+            .help("Output file; prints to stdout when omitted")
+    };
+    let file = || Arg::new("file").required(true).help("Snapshot file");
+
+    Command::new("pocket-syllabus")
+        .about("Tokyo Tech OCW syllabus browser. Runs headless when a subcommand is given.")
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand(
+            Command::new("fetch").about("Crawl OCW into the database").arg(
+                Arg::new("department")
+                    .short('d')
+                    .long("department")
+                    .action(ArgAction::Append)
+                    .help("GakubuCD of a department to crawl; crawls every department when omitted"),
+            ),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Search courses, e.g. search lecturer:山田 quarter:3Q-4Q -title:演習 credit>=2")
+                .arg(
+                    Arg::new("query")
+                        .num_args(1..)
+                        .required(true)
+                        .help("Search terms; words without a field look through the syllabus text"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["table", "json"])
+                        .help("Output format (default: table)"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .short('n')
+                        .long("limit")
+                        .help("Print at most this many courses"),
+                )
+                .arg(
+                    Arg::new("offset")
+                        .long("offset")
+                        .help("Skip this many courses first"),
+                )
+                .arg(
+                    Arg::new("sort")
+                        .short('s')
+                        .long("sort")
+                        .value_parser(["code", "title", "credit", "department", "updated"])
+                        .help("Sort key (default: database order)"),
+                )
+                .arg(
+                    Arg::new("desc")
+                        .long("desc")
+                        .action(ArgAction::SetTrue)
+                        .help("Sort in descending order"),
+                ),
+        )
+        .subcommand(
+            Command::new("show").about("Print one course as JSON").arg(
+                Arg::new("code")
+                    .required(true)
+                    .help("Course code, e.g. MTH.A201"),
+            ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export every course, or the ones a search finds, in full")
+                .arg(
+                    Arg::new("query")
+                        .num_args(1..)
+                        .help("Search terms as for search; exports every course when omitted"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["json", "ndjson", "csv"])
+                        .help("Output format (default: json)"),
+                )
+                .arg(output()),
+        )
+        .subcommand(
+            Command::new("graph")
+                .about("Print the prerequisite and related-course graph")
+                .arg(
+                    Arg::new("code")
+                        .help("Course to take the graph around; prints the whole graph when omitted"),
+                )
+                .arg(
+                    Arg::new("ancestors")
+                        .long("ancestors")
+                        .action(ArgAction::SetTrue)
+                        .help("Only the courses to take before"),
+                )
+                .arg(
+                    Arg::new("descendants")
+                        .long("descendants")
+                        .action(ArgAction::SetTrue)
+                        .help("Only the courses to take after"),
+                )
+                .arg(
+                    Arg::new("depth")
+                        .short('d')
+                        .long("depth")
+                        .help("Links to follow from the course (default: 2)"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["dot", "json"])
+                        .help("Output format (default: dot)"),
+                )
+                .arg(output()),
+        )
+        .subcommand(
+            Command::new("grid")
+                .about("Print the courses a search finds by day and period, e.g. grid department:数学系 quarter:3Q")
+                .arg(
+                    Arg::new("query")
+                        .num_args(1..)
+                        .help("Search terms; takes every course when omitted"),
+                )
+                .arg(
+                    Arg::new("by")
+                        .short('b')
+                        .long("by")
+                        .value_parser(["department", "lecturer", "room"])
+                        .help("One grid per department, lecturer or room (default: department)"),
+                )
+                .arg(
+                    Arg::new("select")
+                        .long("select")
+                        .action(ArgAction::Append)
+                        .help("Course code taken; courses meeting at the same time are marked"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["text", "json"])
+                        .help("Output format (default: text)"),
+                ),
+        )
+        .subcommand(
+            Command::new("room")
+                .about("Look up rooms and what meets in them")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list")
+                        .about("Print every known room with its building and campus")
+                        .arg(campus()),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Print the weekly occupancy of a room per quarter")
+                        .arg(
+                            Arg::new("name")
+                                .required(true)
+                                .help("Room, e.g. W8E-101"),
+                        )
+                        .arg(year()),
+                )
+                .subcommand(
+                    Command::new("empty")
+                        .about("Print the rooms no course uses in a slot")
+                        .arg(
+                            Arg::new("slot")
+                                .required(true)
+                                .help("Day and periods, e.g. 月1-2"),
+                        )
+                        .arg(
+                            Arg::new("quarter")
+                                .required(true)
+                                .help("Quarter, e.g. 3Q"),
+                        )
+                        .arg(year())
+                        .arg(campus()),
+                ),
+        )
+        .subcommand(
+            Command::new("db")
+                .about("Database maintenance")
+                .subcommand_required(true)
+                .subcommand(Command::new("stats").about(
+                    "Print row counts per table, courses per department and year, and the database size",
+                ))
+                .subcommand(
+                    Command::new("check")
+                        .about("Run the SQLite integrity check and look for orphaned rows"),
+                )
+                .subcommand(
+                    Command::new("compact")
+                        .about("Checkpoint the write-ahead log and vacuum the database"),
+                )
+                .subcommand(
+                    Command::new("reset")
+                        .about("Delete the crawled courses, keeping search rules and the page archive")
+                        .arg(
+                            Arg::new("archive")
+                                .long("archive")
+                                .action(ArgAction::SetTrue)
+                                .help("Delete the page archive as well"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import saved OCW list and detail pages, or a JSON export, into the database")
+                .arg(Arg::new("dir").required(true).help(
+                    "Directory containing the saved .html files, or a .json file written by export",
+                )),
+        )
+        .subcommand(
+            Command::new("snapshot")
+                .about("Create, merge or inspect database snapshots")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("create")
+                        .about("Write the crawled courses to a snapshot file")
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .help("Snapshot file (default: database.db)"),
+                        )
+                        .arg(Arg::new("source").long("source").help(
+                            "Where the courses came from (default: the base URL in the settings)",
+                        )),
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("Merge a snapshot into the database, keeping user data")
+                        .arg(file()),
+                )
+                .subcommand(
+                    Command::new("info")
+                        .about("Print the metadata of a snapshot file")
+                        .arg(file()),
+                ),
+        )
+}
+
+// Runs the subcommand given on the command line, if any.
+// Returns the exit code when the app should quit instead of opening the window.
+pub async fn run(
+    pool: &SqlitePool,
+    archive: &Archive,
+    settings: &Settings,
+    matches: &ArgMatches,
+) -> Option<i32> {
+    let (name, matches) = matches.subcommand()?;
+
+    let result = match (name, matches.subcommand()) {
+        ("fetch", _) => fetch(pool, archive, settings, matches).await,
+        ("search", _) => search(pool, matches).await,
+        ("show", _) => show(pool, matches).await,
+        ("export", _) => export(pool, matches).await,
+        ("graph", _) => graph(pool, matches).await,
+        ("grid", _) => grid(pool, matches).await,
+        ("room", Some(("list", matches))) => list_rooms(pool, matches).await,
+        ("room", Some(("show", matches))) => show_room(pool, matches).await,
+        ("room", Some(("empty", matches))) => empty_rooms(pool, matches).await,
+        ("db", Some(("stats", _))) => stats(pool).await,
+        ("db", Some(("check", _))) => check(pool).await,
+        ("db", Some(("compact", _))) => compact(pool).await,
+        ("db", Some(("reset", matches))) => reset(pool, archive, matches).await,
+        ("import", _) => import(pool, settings, matches).await,
+        ("snapshot", Some(("create", matches))) => create_snapshot(pool, settings, matches).await,
+        ("snapshot", Some(("import", matches))) => import_snapshot(pool, matches).await,
+        ("snapshot", Some(("info", matches))) => snapshot_info(matches).await,
+        _ => return None,
+    };
+
+    match result {
+        Ok(code) => Some(code),
+        Err(e) => {
+            eprintln!("{} failed: {}", name, e);
+            Some(1)
+        }
+    }
+}
+
+fn arg_str<'a>(matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    matches.get_one::<String>(name).map(String::as_str)
+}
+
+fn arg_strs<'a>(matches: &'a ArgMatches, name: &str) -> Vec<&'a str> {
+    matches
+        .get_many::<String>(name)
+        .map(|values| values.map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn arg_flag(matches: &ArgMatches, name: &str) -> bool {
+    matches.get_flag(name)
+}
+
+async fn fetch(
+    pool: &SqlitePool,
+    archive: &Archive,
+    settings: &Settings,
+    matches: &ArgMatches,
+) -> DbResult<i32> {
+    let departments = match arg_strs(matches, "department") {
+        departments if departments.is_empty() => settings.gakubu_cds(),
+        departments => departments
+            .iter()
+            .map(|department| department.parse())
+            .collect::<Result<Vec<u32>, _>>()?,
+    };
+
+    let crawler = Crawler::new(pool, archive, settings, |status: String| {
+        println!("{}", status)
+    })?;
+    crawler.crawl_departments(&departments).await?;
+
+    Ok(0)
+}
+
+async fn search(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let parsed = query::parse(&arg_strs(matches, "query").join(" "))?;
+    let query = SearchQuery {
+        offset: arg_str(matches, "offset").map_or(Ok(0), str::parse)?,
+        limit: arg_str(matches, "limit").map(str::parse).transpose()?,
+        sort: match arg_str(matches, "sort") {
+            Some("code") => Some(SortKey::Code),
+            Some("title") => Some(SortKey::Title),
+            Some("credit") => Some(SortKey::Credit),
+            Some("department") => Some(SortKey::Department),
+            Some("updated") => Some(SortKey::LastUpdated),
+            _ => None,
+        },
+        order: if arg_flag(matches, "desc") {
+            SortOrder::Desc
+        } else {
+            SortOrder::Asc
+        },
+        ..parsed
+    };
+
+    let response = database::search_courses(pool, query).await?;
+
+    match arg_str(matches, "format") {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&response.items)?),
+        _ => {
+            print_table(&response.items);
+            println!("{} of {} courses", response.items.len(), response.total);
+        }
+    }
+
+    Ok(0)
+}
+
+async fn show(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let code = arg_str(matches, "code").unwrap_or_default();
+
+    match database::get_course_id_by_code(pool, code).await? {
+        Some(id) => {
+            let course = database::get_course(pool, id).await?;
+            println!("{}", serde_json::to_string_pretty(&course)?);
+            Ok(0)
+        }
+        None => {
+            eprintln!("course not found: {}", code);
+            Ok(1)
+        }
+    }
+}
+
+async fn export(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let format = match arg_str(matches, "format") {
+        Some(name) => ExportFormat::from_name(name)
+            .ok_or_else(|| AppError::Parse(format!("unknown format: {}", name)))?,
+        None => ExportFormat::Json,
+    };
+    let query = match arg_strs(matches, "query") {
+        terms if terms.is_empty() => None,
+        terms => Some(query::parse(&terms.join(" "))?),
+    };
+
+    let output = database::export_courses(pool, query, format).await?;
+    match arg_str(matches, "output") {
+        Some(path) => std::fs::write(path, output)?,
+        None => print!("{}", output),
+    }
+
+    Ok(0)
+}
+
+async fn graph(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let format = match arg_str(matches, "format") {
+        Some(name) => GraphFormat::from_name(name)
+            .ok_or_else(|| AppError::Parse(format!("unknown format: {}", name)))?,
+        None => GraphFormat::Dot,
+    };
+    let direction = match (
+        arg_flag(matches, "ancestors"),
+        arg_flag(matches, "descendants"),
+    ) {
+        (true, false) => Direction::Ancestors,
+        (false, true) => Direction::Descendants,
+        _ => Direction::Both,
+    };
+    let depth = arg_str(matches, "depth").map_or(Ok(2), str::parse)?;
+
+    let output =
+        database::export_course_graph(pool, arg_str(matches, "code"), direction, depth, format)
+            .await?;
+    match arg_str(matches, "output") {
+        Some(path) => std::fs::write(path, output)?,
+        None => print!("{}", output),
+    }
+
+    Ok(0)
+}
+
+async fn grid(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let axis = match arg_str(matches, "by") {
+        Some(name) => GridAxis::from_name(name)
+            .ok_or_else(|| AppError::Parse(format!("unknown axis: {}", name)))?,
+        None => GridAxis::Department,
+    };
+    let query = match arg_strs(matches, "query") {
+        terms if terms.is_empty() => SearchQuery::default(),
+        terms => query::parse(&terms.join(" "))?,
+    };
+    let mut selected = Vec::new();
+    for code in arg_strs(matches, "select") {
+        let id = database::get_course_id_by_code(pool, code)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("course {}", code)))?;
+        selected.push(id);
+    }
+
+    let grid = database::get_timetable_grid(pool, &query, axis, &selected).await?;
+    match arg_str(matches, "format") {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&grid)?),
+        _ => print_grid(&grid),
+    }
+
+    Ok(0)
+}
+
+// One line per course and slot, Monday first, with clashes marked by "!"
+fn print_grid(grid: &TimetableGrid) {
+    for group in &grid.groups {
+        println!(
+            "{}",
+            if group.name.is_empty() {
+                "-"
+            } else {
+                &group.name
+            }
+        );
+        for day in [1, 2, 3, 4, 5, 6, 0] {
+            let label = "日月火水木金土".chars().nth(day).unwrap_or_default();
+            for (period, cell) in group.cells[day].iter().enumerate() {
+                for course in &cell.courses {
+                    println!(
+                        "  {}{}-{}{}  {}  {}",
+                        label,
+                        period * 2 + 1,
+                        period * 2 + 2,
+                        if cell.conflicts.contains(&course.id) {
+                            " !"
+                        } else {
+                            "  "
+                        },
+                        course.code,
+                        course.title
+                    );
+                }
+            }
+        }
+        for course in &group.unscheduled {
+            println!("  -         {}  {}", course.code, course.title);
+        }
+    }
+}
+
+fn arg_campus(matches: &ArgMatches) -> DbResult<Option<Campus>> {
+    arg_str(matches, "campus")
+        .map(|name| {
+            Campus::from_name(name)
+                .ok_or_else(|| AppError::Parse(format!("unknown campus: {}", name)))
+        })
+        .transpose()
+}
+
+fn arg_year(matches: &ArgMatches) -> DbResult<Option<i32>> {
+    Ok(arg_str(matches, "year").map(str::parse).transpose()?)
+}
+
+async fn list_rooms(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    print_rooms(&database::get_rooms(pool, arg_campus(matches)?).await?);
+
+    Ok(0)
+}
+
+async fn show_room(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let name = arg_str(matches, "name").unwrap_or_default();
+    let occupancy = database::get_room_occupancy(pool, name, arg_year(matches)?).await?;
+
+    print_rooms(std::slice::from_ref(&occupancy.room));
+    println!("{}", occupancy.year);
+    for slot in &occupancy.slots {
+        for course in &slot.courses {
+            println!(
+                "{}  {}  {}  {}",
+                quarter_label(&slot.quarter),
+                slot_label(&slot.day, &slot.period),
+                course.code,
+                course.title
+            );
+        }
+    }
+
+    Ok(0)
+}
+
+async fn empty_rooms(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let slot = arg_str(matches, "slot").unwrap_or_default();
+    let slot = query::parse_slot(slot)
+        .ok_or_else(|| AppError::Parse(format!("`{}` is not a slot like 月1-2", slot)))?;
+    let quarter = arg_str(matches, "quarter").unwrap_or_default();
+    let quarter = query::parse_quarter(quarter)
+        .ok_or_else(|| AppError::Parse(format!("`{}` is not a quarter like 3Q", quarter)))?;
+
+    let rooms = database::find_empty_rooms(
+        pool,
+        &slot.day,
+        &slot.period,
+        &quarter,
+        arg_year(matches)?,
+        arg_campus(matches)?,
+    )
+    .await?;
+    print_rooms(&rooms);
+
+    Ok(0)
+}
+
+fn print_rooms(rooms: &[Room]) {
+    for room in rooms {
+        println!(
+            "{:<16} {:<8} {}",
+            room.name,
+            room.building.as_deref().unwrap_or("-"),
+            room.campus.map_or("-", |campus| campus.name())
+        );
+    }
+}
+
+fn quarter_label(quarter: &Semester) -> &'static str {
+    match quarter {
+        Semester::First => "1Q",
+        Semester::Second => "2Q",
+        Semester::Third => "3Q",
+        Semester::Fourth => "4Q",
+    }
+}
+
+// 月1-2, as in the course list
+fn slot_label(day: &Day, period: &Period) -> String {
+    let day = match day {
+        Day::Monday => '月',
+        Day::Tuesday => '火',
+        Day::Wednesday => '水',
+        Day::Thursday => '木',
+        Day::Friday => '金',
+        Day::Saturday => '土',
+        Day::Sunday => '日',
+    };
+    let first = match period {
+        Period::First => 1,
+        Period::Second => 3,
+        Period::Third => 5,
+        Period::Fourth => 7,
+        Period::Fifth => 9,
+        Period::Sixth => 11,
+    };
+
+    format!("{}{}-{}", day, first, first + 1)
+}
+
+async fn stats(pool: &SqlitePool) -> DbResult<i32> {
+    let stats = database::get_database_stats(pool).await?;
+
+    for table in &stats.tables {
+        println!("{:<16} {}", table.table, table.count);
+    }
+    println!();
+    for courses in &stats.courses {
+        let padding = 16usize.saturating_sub(display_width(&courses.department));
+        println!(
+            "{}{} {}  {}",
+            courses.department,
+            " ".repeat(padding),
+            courses.year,
+            courses.count
+        );
+    }
+    println!();
+    println!("size:        {} bytes", stats.size);
+    println!("free:        {} bytes", stats.free);
+
+    Ok(0)
+}
+
+async fn check(pool: &SqlitePool) -> DbResult<i32> {
+    let report = database::check_database(pool).await?;
+
+    for problem in &report.problems {
+        println!("{}", problem);
+    }
+    for orphans in &report.orphans {
+        println!("orphaned rows in {}: {}", orphans.table, orphans.count);
+    }
+    if report.is_ok() {
+        println!("ok");
+    }
+
+    Ok(if report.is_ok() { 0 } else { 1 })
+}
+
+async fn compact(pool: &SqlitePool) -> DbResult<i32> {
+    let report = database::compact_database(pool).await?;
+    println!("before:      {} bytes", report.size_before);
+    println!("after:       {} bytes", report.size_after);
+
+    Ok(0)
+}
+
+async fn reset(pool: &SqlitePool, archive: &Archive, matches: &ArgMatches) -> DbResult<i32> {
+    let include_archive = arg_flag(matches, "archive");
+
+    let report = database::reset_crawl_data(pool, include_archive).await?;
+    if include_archive {
+        archive.clear()?;
+    }
+    println!("courses:        {}", report.courses);
+    println!("archived pages: {}", report.archived_pages);
+
+    Ok(0)
+}
+
+async fn import(pool: &SqlitePool, settings: &Settings, matches: &ArgMatches) -> DbResult<i32> {
+    let path = Path::new(arg_str(matches, "dir").unwrap_or("."));
+
+    // a file written by `export`
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        let count = database::import_courses(pool, &std::fs::read_to_string(path)?).await?;
+        println!("imported:     {}", count);
+        return Ok(0);
+    }
+
+    let report = import::import_html(pool, path, &settings.base_url, |status| {
+        println!("{}", status)
+    })
+    .await?;
+    print_import_report(&report);
+
+    Ok(if report.failed.is_empty() { 0 } else { 1 })
+}
+
+async fn create_snapshot(
+    pool: &SqlitePool,
+    settings: &Settings,
+    matches: &ArgMatches,
+) -> DbResult<i32> {
+    let path = Path::new(arg_str(matches, "output").unwrap_or("database.db"));
+    let source = arg_str(matches, "source").unwrap_or(&settings.base_url);
+
+    let meta = database::create_snapshot(pool, path, source).await?;
+    print_snapshot_meta(&meta);
+
+    Ok(0)
+}
+
+async fn import_snapshot(pool: &SqlitePool, matches: &ArgMatches) -> DbResult<i32> {
+    let path = Path::new(arg_str(matches, "file").unwrap_or_default());
+
+    let report = database::import_snapshot(pool, path).await?;
+    print_snapshot_meta(&report.snapshot);
+    println!("added:        {}", report.added);
+    println!("updated:      {}", report.updated);
+    println!("unchanged:    {}", report.unchanged);
+
+    Ok(0)
+}
+
+async fn snapshot_info(matches: &ArgMatches) -> DbResult<i32> {
+    let path = Path::new(arg_str(matches, "file").unwrap_or_default());
+
+    print_snapshot_meta(&database::get_snapshot_meta(path).await?);
+
+    Ok(0)
+}
+
+fn print_snapshot_meta(meta: &SnapshotMeta) {
+    println!("source:       {}", meta.source);
+    println!(
+        "year:         {}",
+        meta.year.map_or("-".to_string(), |year| year.to_string())
+    );
+    println!("created at:   {}", meta.created_at);
+    println!("schema:       {}", meta.schema_version);
+}
+
+fn print_import_report(report: &ImportReport) {
+    println!("list pages:   {}", report.list_pages);
+    println!("detail pages: {}", report.detail_pages);
+    println!("imported:     {}", report.imported);
+    println!("up to date:   {}", report.up_to_date);
+    for (label, files) in [
+        ("unmatched", &report.unmatched),
+        ("skipped", &report.skipped),
+        ("failed", &report.failed),
+    ] {
+        println!("{:<13} {}", format!("{}:", label), files.len());
+        for file in files {
+            println!("  {}", file);
+        }
+    }
+}
+
+// full-width characters take two columns in a terminal
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+fn print_table(courses: &[CourseListItem]) {
+    let header = [
+        "code",
+        "title",
+        "lecturer",
+        "timetable",
+        "semester",
+        "department",
+        "credit",
+    ]
+    .map(String::from);
+    let rows = courses
+        .iter()
+        .map(|course| {
+            [
+                course.code.clone(),
+                course.title.clone(),
+                course.lecturer.clone(),
+                course.timetable.clone(),
+                course.semester.clone(),
+                course.department.clone(),
+                course.credit.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = header.clone().map(|cell| display_width(&cell));
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - display_width(cell))))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::archive::{Archive, DETAIL_PAGE, LIST_PAGE};
+use crate::database::{self, DbResult};
+use crate::error::AppError;
+use crate::scrape::{html_to_course, html_to_course_abstracts};
+use crate::settings::Settings;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Deserialize)]
+pub enum RefreshTarget {
+    Id(i32),
+    Code(String),
+    Url(String),
+}
+
+// Set by the cancel_fetch command; a running crawl stops before its next page.
+#[derive(Debug, Default)]
+pub struct CancelFlag(AtomicBool);
+
+impl CancelFlag {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct Crawler<'a, F: Fn(String)> {
+    pool: &'a SqlitePool,
+    archive: &'a Archive,
+    settings: &'a Settings,
+    client: reqwest::Client,
+    progress: F,
+    cancel: Option<&'a CancelFlag>,
+}
+
+impl<'a, F: Fn(String)> Crawler<'a, F> {
+    pub fn new(
+        pool: &'a SqlitePool,
+        archive: &'a Archive,
+        settings: &'a Settings,
+        progress: F,
+    ) -> DbResult<Self> {
+        Ok(Crawler {
+            pool,
+            archive,
+            settings,
+            client: settings.client()?,
+            progress,
+            cancel: None,
+        })
+    }
+
+    pub fn with_cancel(mut self, cancel: &'a CancelFlag) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn check_cancelled(&self) -> DbResult<()> {
+        match self.cancel {
+            Some(cancel) if cancel.is_cancelled() => Err(AppError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn crawl_departments(&self, gakubu_cds: &[u32]) -> DbResult<()> {
+        database::record_schools(self.pool, &self.settings.departments).await?;
+        for gakubu_cd in gakubu_cds {
+            let url = self.settings.department_url(*gakubu_cd);
+            match self.crawl_list(&url).await {
+                Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                Err(e) => (self.progress)(format!("Skipped {}: {}", url, e)),
+                Ok(()) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    // Fetches one list page and every course on it whose sylbs_update changed
+    pub async fn crawl_list(&self, url: &str) -> DbResult<()> {
+        self.check_cancelled()?;
+        (self.progress)("Fetching Main".to_string());
+        let page = self
+            .archive
+            .fetch(self.pool, &self.client, url, LIST_PAGE)
+            .await?;
+        tokio::time::sleep(self.settings.delay()).await;
+
+        if !page.modified {
+            (self.progress)("Main not modified".to_string());
+        }
+
+        let courses = match html_to_course_abstracts(page.body.as_ref(), &self.settings.base_url) {
+            Ok(courses) => courses,
+            Err(e) => {
+                (self.progress)(format!("Skipped {}: {}", url, e));
+                return Ok(());
+            }
+        };
+
+        database::record_departments(self.pool, &courses).await?;
+
+        let length = courses.len();
+
+        (self.progress)(format!("Left: {} courses", length));
+
+        for (now, course) in (1..).zip(courses) {
+            self.check_cancelled()?;
+
+            (self.progress)(format!(
+                "Scraping: {}/{} courses: {} {}",
+                now, length, course.department, course.title.title
+            ));
+
+            if course.code.is_empty() {
+                continue;
+            }
+
+            let check = database::check_sylbs_update(
+                self.pool,
+                &course.code,
+                course.year,
+                &course.title.title,
+                &course.sylbs_update,
+            )
+            .await?;
+
+            if check {
+                continue;
+            }
+
+            let result = self
+                .fetch_course(&course.title.url, &course.sylbs_update)
+                .await;
+            tokio::time::sleep(self.settings.delay()).await;
+
+            match result {
+                Ok(_) => (self.progress)(format!(
+                    "Finished: {}/{} courses: {}",
+                    now, length, course.title.title
+                )),
+                Err(e) => (self.progress)(format!("Skipped: {}/{} courses: {}", now, length, e)),
+            }
+        }
+
+        (self.progress)("finish insert".to_string());
+
+        Ok(())
+    }
+
+    // Downloads one course again and replaces it in the database, whether or not
+    // its sylbs_update changed. Returns the id of the refreshed course.
+    pub async fn refresh_course(&self, target: &RefreshTarget) -> DbResult<i64> {
+        let source = match target {
+            RefreshTarget::Id(id) => database::get_course_source_by_id(self.pool, *id).await?,
+            RefreshTarget::Code(code) => {
+                database::get_course_source_by_code(self.pool, code).await?
+            }
+            RefreshTarget::Url(url) => Some(
+                database::get_course_source_by_url(self.pool, url)
+                    .await?
+                    .unwrap_or((url.clone(), "".to_string())),
+            ),
+        };
+
+        let (url, sylbs_update) = match source {
+            Some(source) => source,
+            None => return Err(AppError::NotFound(format!("course {:?}", target))),
+        };
+
+        (self.progress)(format!("Refreshing: {}", url));
+        let id = self.fetch_course(&url, &sylbs_update).await?;
+        (self.progress)(format!("Refreshed: {}", url));
+
+        Ok(id)
+    }
+
+    async fn fetch_course(&self, url: &str, sylbs_update: &str) -> DbResult<i64> {
+        let page = self
+            .archive
+            .fetch(self.pool, &self.client, url, DETAIL_PAGE)
+            .await?;
+
+        let mut detail = html_to_course(page.body.as_ref(), &self.settings.base_url)?;
+        detail.url = url.to_string();
+        detail.sylbs_update = sylbs_update.to_string();
+
+        database::replace_course(self.pool, &detail).await
+    }
+}
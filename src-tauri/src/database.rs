@@ -1,11 +1,54 @@
-use crate::scrape::{Day, Period, Semester};
+use crate::error::AppError;
+use crate::normalize::normalize;
+use crate::scrape::{CourseDetail, Day, LecturePlan, Lecturer, Period, Semester, TimeTable};
 use crate::Course;
 use serde::{Deserialize, Serialize};
-use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
-use sqlx::{FromRow, Row, SqlitePool};
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous,
+};
+use sqlx::{FromRow, QueryBuilder, Row, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::HashMap;
 use std::str::FromStr;
 
-type DbResult<T> = Result<T, Box<dyn std::error::Error>>;
+mod departments;
+mod export;
+mod graph;
+mod grid;
+mod maintenance;
+mod rooms;
+mod rules;
+mod search;
+mod snapshot;
+
+pub use departments::{get_department_tree, record_departments, record_schools, DepartmentNode};
+pub use export::{export_courses, import_courses, ExportFormat};
+pub use graph::{
+    course_codes, export_course_graph, get_course_graph, get_course_relations, CourseGraph,
+    Direction, GraphEdge, GraphFormat, GraphNode, Relation,
+};
+pub use grid::{get_timetable_grid, GridAxis, GridCell, GridGroup, TimetableGrid};
+pub use maintenance::{
+    check_database, compact_database, find_orphans, get_database_stats, reset_crawl_data,
+    CompactReport, DatabaseStats, DepartmentYearCount, IntegrityReport, ResetReport, TableCount,
+};
+pub use rooms::{
+    find_empty_rooms, get_room_occupancy, get_rooms, parse_rooms, Campus, Room, RoomCourse,
+    RoomOccupancy, RoomSlot,
+};
+pub use rules::{
+    add_search_rule, delete_search_rule, get_search_rules, update_search_rule, HiddenCount,
+    RuleAction, RuleField, RuleMatch, SearchRule,
+};
+pub use search::{
+    get_filter_options, search_courses, CourseListItem, FacetCount, Facets, FilterOptions,
+    SearchQuery, SearchResponse, SortKey, SortOrder, TimetableMode, TimetableQuery,
+};
+pub use snapshot::{
+    create_snapshot, get_snapshot_meta, import_snapshot, seed_from_snapshot, SnapshotMeta,
+    SnapshotReport,
+};
+
+pub type DbResult<T> = Result<T, AppError>;
 
 pub async fn create_sqlite_pool(database_path: &str) -> DbResult<SqlitePool> {
     if !std::path::Path::new(database_path).exists() {
@@ -26,13 +69,101 @@ pub async fn create_sqlite_pool(database_path: &str) -> DbResult<SqlitePool> {
 
 pub async fn migrate(pool: &SqlitePool) -> DbResult<()> {
     sqlx::migrate!("./migrations").run(pool).await?;
+    fill_normalized(pool).await?;
+    rooms::fill_rooms(pool).await?;
+    departments::fill_departments(pool).await?;
 
     Ok(())
 }
 
-pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
+// Rows stored before the normalized copies existed get them here.
+async fn fill_normalized(pool: &SqlitePool) -> DbResult<()> {
     let mut tx = pool.begin().await?;
 
+    let courses = sqlx::query("SELECT id, title FROM courses WHERE title_normalized IS NULL")
+        .fetch_all(&mut *tx)
+        .await?;
+    for row in courses {
+        sqlx::query("UPDATE courses SET title_normalized = ? WHERE id = ?")
+            .bind(normalize(row.try_get("title")?))
+            .bind(row.try_get::<i64, _>("id")?)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let lecturers = sqlx::query("SELECT id, name FROM lecturers WHERE name_normalized IS NULL")
+        .fetch_all(&mut *tx)
+        .await?;
+    for row in lecturers {
+        sqlx::query("UPDATE lecturers SET name_normalized = ? WHERE id = ?")
+            .bind(normalize(row.try_get("name")?))
+            .bind(row.try_get::<i64, _>("id")?)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let texts = sqlx::query(
+        "SELECT rowid FROM course_texts
+        WHERE rowid NOT IN (SELECT rowid FROM course_texts_normalized)",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in texts {
+        index_normalized_text(&mut tx, row.try_get("rowid")?).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Copies the course_texts row of one course into course_texts_normalized.
+async fn index_normalized_text(tx: &mut SqliteConnection, id: i64) -> DbResult<()> {
+    let row = sqlx::query(
+        "SELECT title, abstract, goal, keywords, schedule, textbook FROM course_texts WHERE rowid = ?",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let mut insert = sqlx::query(
+        "INSERT INTO course_texts_normalized (rowid, title, abstract, goal, keywords, schedule, textbook)
+        VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(id);
+    for column in 0..6 {
+        insert = insert.bind(normalize(row.try_get(column)?));
+    }
+    insert.execute(&mut *tx).await?;
+
+    Ok(())
+}
+
+// Copies the searchable text of one course, child rows included, into course_texts.
+// Same columns as the backfill in migrations/002_fulltext.sql.
+const INDEX_COURSE_TEXT: &str =
+    "INSERT INTO course_texts (rowid, title, abstract, goal, keywords, schedule, textbook)
+    SELECT
+        c.id, c.title, COALESCE(c.abstract, ''), COALESCE(c.goal, ''),
+        COALESCE((SELECT group_concat(keyword, ' ') FROM keywords WHERE course_id = c.id), ''),
+        COALESCE((SELECT group_concat(plan || ' ' || assignment, ' ') FROM schedules WHERE course_id = c.id), ''),
+        COALESCE(c.textbook, '') || ' ' || COALESCE(c.reference_book, '')
+    FROM courses AS c WHERE c.id = ?";
+
+pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<i64> {
+    let mut tx = pool.begin().await?;
+    let id = insert_course_in(&mut tx, course).await?;
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+// Inserts the course with its child rows and text index on `conn`, for callers
+// that store it as part of a larger transaction.
+pub(super) async fn insert_course_in(
+    conn: &mut SqliteConnection,
+    course: &Course,
+) -> DbResult<i64> {
     sqlx::query(
         "INSERT INTO courses (
                     university, 
@@ -57,8 +188,9 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
                     prerequisite,
                     contact,
                     office_hour,
-                    note
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    note,
+                    title_normalized
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(&course.university)
     .bind(&course.title)
@@ -83,31 +215,35 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
     .bind(&course.course_detail.contact)
     .bind(&course.course_detail.office_hour)
     .bind(&course.course_detail.note)
-    .execute(&mut *tx)
+    .bind(normalize(&course.title))
+    .execute(&mut *conn)
     .await?;
 
     let last_id = sqlx::query("SELECT last_insert_rowid() as id")
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut *conn)
         .await?
         .try_get::<i64, _>("id")?;
+    departments::place_department(conn, &course.department).await?;
 
     for teacher in &course.lecturer {
         sqlx::query(
             "INSERT INTO lecturers (
                 course_id,
                 name,
-                url
-            ) VALUES (?, ?, ?)",
+                url,
+                name_normalized
+            ) VALUES (?, ?, ?, ?)",
         )
         .bind(last_id)
         .bind(&teacher.name)
         .bind(&teacher.url)
-        .execute(&mut *tx)
+        .bind(normalize(&teacher.name))
+        .execute(&mut *conn)
         .await?;
     }
 
     for timetable in &course.time_table {
-        sqlx::query(
+        let timetable_id = sqlx::query(
             "INSERT INTO timetables (
                 course_id,
                 day,
@@ -116,26 +252,13 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
             ) VALUES (?, ?, ?, ?)",
         )
         .bind(last_id)
-        .bind(match &timetable.day {
-            Day::Sunday => 0,
-            Day::Monday => 1,
-            Day::Tuesday => 2,
-            Day::Wednesday => 3,
-            Day::Thursday => 4,
-            Day::Friday => 5,
-            Day::Saturday => 6,
-        })
-        .bind(match &timetable.period {
-            Period::First => 1,
-            Period::Second => 2,
-            Period::Third => 3,
-            Period::Fourth => 4,
-            Period::Fifth => 5,
-            Period::Sixth => 6,
-        })
+        .bind(day_number(&timetable.day))
+        .bind(period_number(&timetable.period))
         .bind(&timetable.room)
-        .execute(&mut *tx)
-        .await?;
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+        rooms::link_rooms(conn, timetable_id, &timetable.room).await?;
     }
 
     for sem in &course.semester {
@@ -146,13 +269,8 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
             ) VALUES (?, ?)",
         )
         .bind(last_id)
-        .bind(match &sem {
-            Semester::First => 1,
-            Semester::Second => 2,
-            Semester::Third => 3,
-            Semester::Fourth => 4,
-        })
-        .execute(&mut *tx)
+        .bind(semester_number(sem))
+        .execute(&mut *conn)
         .await?;
     }
 
@@ -165,7 +283,7 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
         )
         .bind(last_id)
         .bind(&key)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
     }
 
@@ -178,7 +296,7 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
         )
         .bind(last_id)
         .bind(&competency)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
     }
 
@@ -195,14 +313,10 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
         .bind(&schedule.count)
         .bind(&schedule.plan)
         .bind(&schedule.assignment)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
     }
 
-    tx.commit().await?;
-
-    let mut tx = pool.begin().await?;
-
     for related in &course.course_detail.related_course {
         let related_code = related.chars().take(8).collect::<String>();
 
@@ -214,82 +328,208 @@ pub async fn insert_course(pool: &SqlitePool, course: &Course) -> DbResult<()> {
         )
         .bind(last_id)
         .bind(related_code)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
     }
 
-    tx.commit().await?;
+    // indexed in the same transaction, so a stored course is always searchable
+    sqlx::query(INDEX_COURSE_TEXT)
+        .bind(last_id)
+        .execute(&mut *conn)
+        .await?;
+    index_normalized_text(conn, last_id).await?;
 
-    Ok(())
+    Ok(last_id)
 }
 
+// Whether the course of this code, year and title is stored with `sylbs_update`.
+// A list row whose year is not known is never taken as up to date.
 pub async fn check_sylbs_update(
     pool: &SqlitePool,
     code: &str,
+    year: Option<i32>,
     title: &str,
     sylbs_update: &str,
 ) -> DbResult<bool> {
+    let stored: Option<String> = sqlx::query_scalar(
+        "SELECT sylbs_update FROM courses WHERE code = ? AND year = ? AND title = ?",
+    )
+    .bind(code)
+    .bind(year)
+    .bind(title)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(stored.as_deref() == Some(sylbs_update))
+}
+
+pub async fn replace_course(pool: &SqlitePool, course: &Course) -> DbResult<i64> {
     let mut tx = pool.begin().await?;
+    let id = replace_course_in(&mut tx, course).await?;
+    tx.commit().await?;
 
-    let row = sqlx::query("SELECT sylbs_update FROM courses WHERE code = ? AND title = ?")
-        .bind(code)
-        .bind(title)
-        .fetch_optional(&mut *tx)
+    Ok(id)
+}
+
+// Stores the course in place of the one with the same code, year and title, whose
+// child rows follow through ON DELETE CASCADE. Other years of the course stay.
+pub(super) async fn replace_course_in(
+    conn: &mut SqliteConnection,
+    course: &Course,
+) -> DbResult<i64> {
+    sqlx::query("DELETE FROM courses WHERE code = ? AND year = ? AND title = ?")
+        .bind(&course.code)
+        .bind(course.year)
+        .bind(&course.title)
+        .execute(&mut *conn)
         .await?;
 
-    let result = match row {
-        Some(row) => {
-            let db_sylbs_update: String = row.try_get("sylbs_update")?;
-            db_sylbs_update == sylbs_update
-        }
-        None => false,
-    };
+    insert_course_in(conn, course).await
+}
 
-    tx.commit().await?;
+// (url, sylbs_update) of a stored course, used to download it again
+pub async fn get_course_source_by_id(
+    pool: &SqlitePool,
+    id: i32,
+) -> DbResult<Option<(String, String)>> {
+    let source = sqlx::query_as("SELECT url, sylbs_update FROM courses WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
 
-    Ok(result)
+    Ok(source)
 }
 
-#[derive(Debug, Deserialize)]
-pub struct SearchQuery {
-    pub university: Vec<String>,
-    pub department: Vec<String>,
-    pub year: Vec<String>,
-    pub title: Vec<String>,
-    pub lecturer: Vec<String>,
-    pub grade: Vec<String>,
-    pub quarter: Vec<Semester>,
-    pub timetable: Vec<TimetableQuery>,
+pub async fn get_course_source_by_code(
+    pool: &SqlitePool,
+    code: &str,
+) -> DbResult<Option<(String, String)>> {
+    let source = sqlx::query_as(
+        "SELECT url, sylbs_update FROM courses WHERE code = ? ORDER BY year DESC, id DESC LIMIT 1",
+    )
+    .bind(code)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(source)
 }
 
-#[derive(Debug, Deserialize)]
-pub struct TimetableQuery {
-    pub day: Day,
-    pub period: Period,
+pub async fn get_course_source_by_url(
+    pool: &SqlitePool,
+    url: &str,
+) -> DbResult<Option<(String, String)>> {
+    let source = sqlx::query_as(
+        "SELECT url, sylbs_update FROM courses WHERE url = ? ORDER BY id DESC LIMIT 1",
+    )
+    .bind(url)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(source)
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct CourseListItem {
-    id: i32,
-    university: String,
-    code: String,
-    title: String,
-    lecturer: String,
-    timetable: String,
-    semester: String,
-    department: String,
-    credit: i32,
+pub async fn get_course_id_by_code(pool: &SqlitePool, code: &str) -> DbResult<Option<i32>> {
+    let id = sqlx::query_scalar(
+        "SELECT id FROM courses WHERE code = ? ORDER BY year DESC, id DESC LIMIT 1",
+    )
+    .bind(code)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn get_course_ids(pool: &SqlitePool) -> DbResult<Vec<i32>> {
+    let ids = sqlx::query_scalar("SELECT id FROM courses ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(ids)
+}
+
+// (table, row count) of every table in the schema
+pub async fn count_rows(pool: &SqlitePool) -> DbResult<Vec<(String, i64)>> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '_sqlx_%' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut counts = Vec::with_capacity(tables.len());
+    for table in tables {
+        let count = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"{}\"", table))
+            .fetch_one(pool)
+            .await?;
+        counts.push((table, count));
+    }
+
+    Ok(counts)
 }
 
 #[derive(FromRow, Debug)]
-struct CourseListItemRow {
-    id: i32,
-    university: String,
-    code: String,
-    title: String,
-    department: String,
-    credit: i32,
-    year: i32,
+pub struct ArchivedPageRow {
+    pub id: i64,
+    pub url: String,
+    pub kind: String,
+    pub fetched_at: i64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub path: String,
+}
+
+pub async fn insert_archived_page(pool: &SqlitePool, page: &ArchivedPageRow) -> DbResult<()> {
+    sqlx::query(
+        "INSERT INTO archived_pages (
+                url,
+                kind,
+                fetched_at,
+                etag,
+                last_modified,
+                path
+            ) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&page.url)
+    .bind(&page.kind)
+    .bind(page.fetched_at)
+    .bind(&page.etag)
+    .bind(&page.last_modified)
+    .bind(&page.path)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn latest_archived_page(
+    pool: &SqlitePool,
+    url: &str,
+) -> DbResult<Option<ArchivedPageRow>> {
+    let page = sqlx::query_as::<_, ArchivedPageRow>(
+        "SELECT * FROM archived_pages WHERE url = ? ORDER BY fetched_at DESC, id DESC LIMIT 1",
+    )
+    .bind(url)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(page)
+}
+
+// latest version of every archived page of the given kind
+pub async fn latest_archived_pages(
+    pool: &SqlitePool,
+    kind: &str,
+) -> DbResult<Vec<ArchivedPageRow>> {
+    let pages = sqlx::query_as::<_, ArchivedPageRow>(
+        "SELECT * FROM archived_pages AS a WHERE kind = ? AND id = (
+                SELECT b.id FROM archived_pages AS b WHERE b.url = a.url
+                ORDER BY b.fetched_at DESC, b.id DESC LIMIT 1
+            ) ORDER BY id",
+    )
+    .bind(kind)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(pages)
 }
 
 #[derive(FromRow)]
@@ -316,330 +556,76 @@ struct SemesterRow {
     semester: i32,
 }
 
-pub async fn search_courses(pool: &SqlitePool, search_query: SearchQuery) -> Vec<CourseListItem> {
-    let mut tx = pool.begin().await.unwrap();
-
-    let query = "SELECT id, university, code, title, department, credit, year FROM courses ";
-    let mut constraints = Vec::new();
-
-    if !search_query.university.is_empty() {
-        constraints.push(format!(
-            "university IN ({})",
-            search_query
-                .university
-                .iter()
-                .map(|s| format!("'{}'", s))
-                .collect::<Vec<String>>()
-                .join(",")
-        ));
-    }
-
-    if !search_query.department.is_empty() {
-        constraints.push(format!(
-            "department IN ({})",
-            search_query
-                .department
-                .iter()
-                .map(|s| format!("'{}'", s))
-                .collect::<Vec<String>>()
-                .join(",")
-        ));
-    }
-
-    if !search_query.year.is_empty() {
-        constraints.push(format!(
-            "year IN ({})",
-            search_query
-                .year
-                .iter()
-                .map(|s| format!("'{}'", s))
-                .collect::<Vec<String>>()
-                .join(",")
-        ));
-    }
-
-    let query = format!(
-        "{}{}{}",
-        query,
-        if !constraints.is_empty() {
-            "WHERE "
-        } else {
-            ""
-        },
-        constraints.join(" AND ")
-    );
-
-    println!("query: {}", query);
-
-    let mut rows = sqlx::query_as::<_, CourseListItemRow>(&query)
-        .fetch_all(&mut *tx)
-        .await
-        .unwrap();
-
-    tx.commit().await.unwrap();
-
-    // grade
-    if !search_query.grade.is_empty() {
-        let grades = search_query
-            .grade
-            .iter()
-            .map(|s| match s.as_str() {
-                "100" => '1',
-                "200" => '2',
-                "300" => '3',
-                "400" => '4',
-                "500" => '5',
-                "600" => '6',
-                _ => '0',
-            })
-            .collect::<Vec<char>>();
-
-        rows.retain(|row| {
-            grades
-                .iter()
-                .any(|grade| row.code.chars().nth(5).unwrap() == *grade)
-        });
-    }
-
-    // timetable
-    if !search_query.timetable.is_empty() {
-        let timetable_query = format!(
-            "SELECT * FROM timetables WHERE day IN ({}) AND periods IN ({})",
-            search_query
-                .timetable
-                .iter()
-                .map(|t| format!(
-                    "{}",
-                    match t.day {
-                        Day::Monday => {
-                            1
-                        }
-                        Day::Tuesday => {
-                            2
-                        }
-                        Day::Wednesday => {
-                            3
-                        }
-                        Day::Thursday => {
-                            4
-                        }
-                        Day::Friday => {
-                            5
-                        }
-                        Day::Saturday => {
-                            6
-                        }
-                        Day::Sunday => {
-                            0
-                        }
-                    }
-                ))
-                .collect::<Vec<String>>()
-                .join(","),
-            search_query
-                .timetable
-                .iter()
-                .map(|t| format!(
-                    "{}",
-                    match t.period {
-                        Period::First => {
-                            1
-                        }
-                        Period::Second => {
-                            2
-                        }
-                        Period::Third => {
-                            3
-                        }
-                        Period::Fourth => {
-                            4
-                        }
-                        Period::Fifth => {
-                            5
-                        }
-                        Period::Sixth => {
-                            6
-                        }
-                    }
-                ))
-                .collect::<Vec<String>>()
-                .join(",")
-        );
-
-        let mut tx = pool.begin().await.unwrap();
-
-        let timetable_ids = sqlx::query_as::<_, TimetableRow>(&timetable_query)
-            .fetch_all(&mut *tx)
-            .await
-            .unwrap();
-
-        tx.commit().await.unwrap();
-
-        rows.retain(|row| {
-            timetable_ids
-                .iter()
-                .any(|timetable_id| timetable_id.course_id == row.id)
-        });
-    }
-
-    // semester
-    if !search_query.quarter.is_empty() {
-        let semester_query = format!(
-            "SELECT * FROM semesters WHERE semester IN ({})",
-            search_query
-                .quarter
-                .iter()
-                .map(|s| format!(
-                    "{}",
-                    match s {
-                        Semester::First => {
-                            1
-                        }
-                        Semester::Second => {
-                            2
-                        }
-                        Semester::Third => {
-                            3
-                        }
-                        Semester::Fourth => {
-                            4
-                        }
-                    }
-                ))
-                .collect::<Vec<String>>()
-                .join(",")
-        );
-
-        let mut tx = pool.begin().await.unwrap();
-
-        let semester_ids = sqlx::query_as::<_, SemesterRow>(&semester_query)
-            .fetch_all(&mut *tx)
-            .await
-            .unwrap();
-
-        tx.commit().await.unwrap();
-
-        rows.retain(|row| {
-            semester_ids
-                .iter()
-                .any(|semester_id| semester_id.course_id == row.id)
-        });
+fn day_number(day: &Day) -> i32 {
+    match day {
+        Day::Sunday => 0,
+        Day::Monday => 1,
+        Day::Tuesday => 2,
+        Day::Wednesday => 3,
+        Day::Thursday => 4,
+        Day::Friday => 5,
+        Day::Saturday => 6,
     }
+}
 
-    let mut results = Vec::with_capacity(rows.len());
-
-    for row in rows {
-        let mut tx = pool.begin().await.unwrap();
-
-        let lecturers =
-            sqlx::query_as::<_, LecturerRow>("SELECT * FROM lecturers WHERE course_id = ?")
-                .bind(row.id)
-                .fetch_all(&mut *tx)
-                .await
-                .unwrap();
-
-        let timetables =
-            sqlx::query_as::<_, TimetableRow>("SELECT * FROM timetables WHERE course_id = ?")
-                .bind(row.id)
-                .fetch_all(&mut *tx)
-                .await
-                .unwrap();
-
-        let semesters =
-            sqlx::query_as::<_, SemesterRow>("SELECT * FROM semesters WHERE course_id = ?")
-                .bind(row.id)
-                .fetch_all(&mut *tx)
-                .await
-                .unwrap();
-
-        tx.commit().await.unwrap();
-
-        let lectures = lecturers
-            .iter()
-            .map(|lecturer| lecturer.name.clone())
-            .collect::<Vec<String>>()
-            .join(", ");
-
-        let timetables = timetables
-            .iter()
-            .map(|timetable| {
-                format!(
-                    "{}{}",
-                    match timetable.day {
-                        0 => "日",
-                        1 => "月",
-                        2 => "火",
-                        3 => "水",
-                        4 => "木",
-                        5 => "金",
-                        6 => "土",
-                        _ => "",
-                    },
-                    match timetable.periods {
-                        1 => "1-2",
-                        2 => "3-4",
-                        3 => "5-6",
-                        4 => "7-8",
-                        5 => "9-10",
-                        6 => "11-12",
-                        _ => "",
-                    }
-                )
-            })
-            .collect::<Vec<String>>()
-            .join(", ");
-
-        let semesters = semesters
-            .iter()
-            .map(|semester| {
-                (match semester.semester {
-                    1 => "1Q",
-                    2 => "2Q",
-                    3 => "3Q",
-                    4 => "4Q",
-                    _ => "",
-                })
-                .to_string()
-            })
-            .collect::<Vec<String>>()
-            .join(", ");
-
-        results.push(CourseListItem {
-            id: row.id,
-            university: row.university,
-            code: row.code,
-            title: row.title,
-            lecturer: lectures,
-            timetable: timetables,
-            semester: semesters,
-            department: row.department,
-            credit: row.credit,
-        });
+fn period_number(period: &Period) -> i32 {
+    match period {
+        Period::First => 1,
+        Period::Second => 2,
+        Period::Third => 3,
+        Period::Fourth => 4,
+        Period::Fifth => 5,
+        Period::Sixth => 6,
     }
+}
 
-    // title
-    if !search_query.title.is_empty() {
-        results.retain(|result| {
-            search_query
-                .title
-                .iter()
-                .any(|title| result.title.contains(title))
-        });
+fn semester_number(semester: &Semester) -> i32 {
+    match semester {
+        Semester::First => 1,
+        Semester::Second => 2,
+        Semester::Third => 3,
+        Semester::Fourth => 4,
     }
+}
 
-    // lecturer
-    if !search_query.lecturer.is_empty() {
-        results.retain(|result| {
-            search_query
-                .lecturer
-                .iter()
-                .any(|lecturer| result.lecturer.contains(lecturer))
-        });
-    }
+fn day_from_number(day: i32) -> DbResult<Day> {
+    Ok(match day {
+        0 => Day::Sunday,
+        1 => Day::Monday,
+        2 => Day::Tuesday,
+        3 => Day::Wednesday,
+        4 => Day::Thursday,
+        5 => Day::Friday,
+        6 => Day::Saturday,
+        _ => return Err(AppError::Parse(format!("day {} out of range", day))),
+    })
+}
 
-    results.retain(|result| !result.lecturer.contains("教員"));
+fn period_from_number(period: i32) -> DbResult<Period> {
+    Ok(match period {
+        1 => Period::First,
+        2 => Period::Second,
+        3 => Period::Third,
+        4 => Period::Fourth,
+        5 => Period::Fifth,
+        6 => Period::Sixth,
+        _ => return Err(AppError::Parse(format!("period {} out of range", period))),
+    })
+}
 
-    results
+fn semester_from_number(semester: i32) -> DbResult<Semester> {
+    Ok(match semester {
+        1 => Semester::First,
+        2 => Semester::Second,
+        3 => Semester::Third,
+        4 => Semester::Fourth,
+        _ => {
+            return Err(AppError::Parse(format!(
+                "quarter {} out of range",
+                semester
+            )))
+        }
+    })
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -709,6 +695,80 @@ pub struct ScheduleResponse {
     assignment: String,
 }
 
+// Turns an exported course back into one insert_course can store; the ids are
+// left behind, the database hands out new ones.
+impl TryFrom<CourseResponse> for Course {
+    type Error = AppError;
+
+    fn try_from(course: CourseResponse) -> DbResult<Course> {
+        let detail = course.course_detail;
+
+        Ok(Course {
+            university: course.university,
+            title: course.title,
+            english_title: course.english_title,
+            department: course.department,
+            lecturer: course
+                .lecturer
+                .into_iter()
+                .map(|lecturer| Lecturer {
+                    name: lecturer.name,
+                    url: lecturer.url,
+                })
+                .collect(),
+            lecture_type: course.lecture_type,
+            time_table: course
+                .timetable
+                .into_iter()
+                .map(|timetable| {
+                    Ok(TimeTable {
+                        day: day_from_number(timetable.day_of_week)?,
+                        period: period_from_number(timetable.period)?,
+                        room: timetable.room,
+                    })
+                })
+                .collect::<DbResult<_>>()?,
+            code: course.code,
+            credit: course.credit,
+            year: course.year,
+            semester: course
+                .semester
+                .into_iter()
+                .map(semester_from_number)
+                .collect::<DbResult<_>>()?,
+            language: course.language,
+            url: course.url,
+            sylbs_update: course.sylbs_update,
+            course_detail: CourseDetail {
+                abst: detail.abst,
+                goal: detail.goal,
+                experience: detail.experience,
+                keyword: detail.keyword,
+                competencies: detail.competencies,
+                flow: detail.flow,
+                schedule: detail
+                    .schedule
+                    .into_iter()
+                    .map(|schedule| LecturePlan {
+                        count: schedule.count,
+                        plan: schedule.plan,
+                        assignment: schedule.assignment,
+                    })
+                    .collect(),
+                out_of_class: detail.out_of_class,
+                textbook: detail.textbook,
+                reference_book: detail.reference_book,
+                assessment: detail.assessment,
+                related_course: detail.related_course,
+                prerequisite: detail.prerequisite,
+                contact: detail.contact,
+                office_hour: detail.office_hour,
+                note: detail.note,
+            },
+        })
+    }
+}
+
 #[derive(FromRow)]
 struct CourseRow {
     id: i32,
@@ -767,126 +827,184 @@ struct RelatedCourseRow {
     related_course_code: String,
 }
 
-pub async fn get_course(pool: &SqlitePool, id: i32) -> CourseResponse {
-    let mut tx = pool.begin().await.unwrap();
+pub async fn get_course(pool: &SqlitePool, id: i32) -> DbResult<CourseResponse> {
+    get_courses(pool, &[id])
+        .await?
+        .pop()
+        .ok_or_else(|| AppError::NotFound(format!("course {}", id)))
+}
 
-    let course = sqlx::query_as::<_, CourseRow>("SELECT * FROM courses WHERE id = ?")
-        .bind(id)
-        .fetch_one(&mut *tx)
-        .await
-        .unwrap();
+// Rows of `sql`, which ends in the course id column to match, for `ids`. The ids
+// are bound a few hundred at a time to stay under SQLite's limit on parameters.
+pub(super) async fn fetch_for_courses<T>(
+    conn: &mut SqliteConnection,
+    sql: &str,
+    ids: &[i32],
+) -> DbResult<Vec<T>>
+where
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
+{
+    let mut rows = Vec::new();
+    for chunk in ids.chunks(500) {
+        let mut query = QueryBuilder::<Sqlite>::new(sql);
+        query.push(" IN (");
+        let mut separated = query.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(")");
+        rows.extend(query.build_query_as::<T>().fetch_all(&mut *conn).await?);
+    }
 
-    let lecturers = sqlx::query_as::<_, LecturerRow>("SELECT * FROM lecturers WHERE course_id = ?")
-        .bind(id)
-        .fetch_all(&mut *tx)
-        .await
-        .unwrap();
+    Ok(rows)
+}
+
+// The rows of a child table for `ids` by course id, each in the order it was
+// inserted. `key` gives the (course_id, id) of a row.
+async fn fetch_children<T>(
+    conn: &mut SqliteConnection,
+    table: &str,
+    ids: &[i32],
+    key: fn(&T) -> (i32, i32),
+) -> DbResult<HashMap<i32, Vec<T>>>
+where
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
+{
+    let sql = format!("SELECT * FROM {} WHERE course_id", table);
+    let mut rows: Vec<T> = fetch_for_courses(conn, &sql, ids).await?;
+    rows.sort_by_key(|row| key(row).1);
+
+    let mut children: HashMap<i32, Vec<T>> = HashMap::new();
+    for row in rows {
+        children.entry(key(&row).0).or_default().push(row);
+    }
 
-    let timetables =
-        sqlx::query_as::<_, TimetableRow>("SELECT * FROM timetables WHERE course_id = ?")
-            .bind(id)
-            .fetch_all(&mut *tx)
-            .await
-            .unwrap();
+    Ok(children)
+}
 
-    let semesters = sqlx::query_as::<_, SemesterRow>("SELECT * FROM semesters WHERE course_id = ?")
-        .bind(id)
-        .fetch_all(&mut *tx)
-        .await
-        .unwrap();
+// The courses `ids` in full and in the same order, with one query per table
+// rather than per course. Ids that are not stored are left out.
+pub async fn get_courses(pool: &SqlitePool, ids: &[i32]) -> DbResult<Vec<CourseResponse>> {
+    let mut tx = pool.begin().await?;
 
-    let keywords = sqlx::query_as::<_, KeywordRow>("SELECT * FROM keywords WHERE course_id = ?")
-        .bind(id)
-        .fetch_all(&mut *tx)
-        .await
-        .unwrap();
+    let mut courses: HashMap<i32, CourseRow> =
+        fetch_for_courses::<CourseRow>(&mut tx, "SELECT * FROM courses WHERE id", ids)
+            .await?
+            .into_iter()
+            .map(|course| (course.id, course))
+            .collect();
+    let mut lecturers = fetch_children(&mut tx, "lecturers", ids, |row: &LecturerRow| {
+        (row.course_id, row.id)
+    })
+    .await?;
+    let mut timetables = fetch_children(&mut tx, "timetables", ids, |row: &TimetableRow| {
+        (row.course_id, row.id)
+    })
+    .await?;
+    let mut semesters = fetch_children(&mut tx, "semesters", ids, |row: &SemesterRow| {
+        (row.course_id, row.id)
+    })
+    .await?;
+    let mut keywords = fetch_children(&mut tx, "keywords", ids, |row: &KeywordRow| {
+        (row.course_id, row.id)
+    })
+    .await?;
+    let mut competencies = fetch_children(&mut tx, "competencies", ids, |row: &CompetencyRow| {
+        (row.course_id, row.id)
+    })
+    .await?;
+    let mut schedules = fetch_children(&mut tx, "schedules", ids, |row: &ScheduleRow| {
+        (row.course_id, row.id)
+    })
+    .await?;
+    let mut related_courses =
+        fetch_children(&mut tx, "related_courses", ids, |row: &RelatedCourseRow| {
+            (row.course_id, row.id)
+        })
+        .await?;
 
-    let competencies =
-        sqlx::query_as::<_, CompetencyRow>("SELECT * FROM competencies WHERE course_id = ?")
-            .bind(id)
-            .fetch_all(&mut *tx)
-            .await
-            .unwrap();
+    tx.commit().await?;
 
-    let schedules = sqlx::query_as::<_, ScheduleRow>("SELECT * FROM schedules WHERE course_id = ?")
-        .bind(id)
-        .fetch_all(&mut *tx)
-        .await
-        .unwrap();
-
-    let related_courses =
-        sqlx::query_as::<_, RelatedCourseRow>("SELECT * FROM related_courses WHERE course_id = ?")
-            .bind(id)
-            .fetch_all(&mut *tx)
-            .await
-            .unwrap();
-
-    tx.commit().await.unwrap();
-
-    CourseResponse {
-        id: course.id,
-        university: course.university,
-        title: course.title,
-        english_title: course.english_title,
-        department: course.department,
-        lecturer: lecturers
-            .iter()
-            .map(|lecturer| LecturerResponse {
-                id: lecturer.id,
-                name: lecturer.name.clone(),
-                url: lecturer.url.clone(),
-            })
-            .collect(),
-        lecture_type: course.lecture_type,
-        timetable: timetables
-            .iter()
-            .map(|timetable| TimetableResponse {
-                course_id: timetable.course_id,
-                day_of_week: timetable.day,
-                period: timetable.periods,
-                room: timetable.room.clone(),
-            })
-            .collect(),
-        code: course.code,
-        credit: course.credit,
-        year: course.year,
-        semester: semesters.iter().map(|semester| semester.semester).collect(),
-        language: course.language,
-        course_detail: CourseDetailResponse {
-            abst: course.r#abstract,
-            goal: course.goal,
-            experience: course.experience,
-            keyword: keywords
+    let mut responses = Vec::with_capacity(ids.len());
+    for id in ids {
+        let Some(course) = courses.remove(id) else {
+            continue;
+        };
+        let lecturers = lecturers.remove(id).unwrap_or_default();
+        let timetables = timetables.remove(id).unwrap_or_default();
+        let semesters = semesters.remove(id).unwrap_or_default();
+        let keywords = keywords.remove(id).unwrap_or_default();
+        let competencies = competencies.remove(id).unwrap_or_default();
+        let schedules = schedules.remove(id).unwrap_or_default();
+        let related_courses = related_courses.remove(id).unwrap_or_default();
+
+        responses.push(CourseResponse {
+            id: course.id,
+            university: course.university,
+            title: course.title,
+            english_title: course.english_title,
+            department: course.department,
+            lecturer: lecturers
                 .iter()
-                .map(|keyword| keyword.keyword.clone())
-                .collect(),
-            competencies: competencies
-                .iter()
-                .map(|competency| competency.competency.clone())
-                .collect(),
-            flow: course.flow,
-            schedule: schedules
-                .iter()
-                .map(|schedule| ScheduleResponse {
-                    count: schedule.count,
-                    plan: schedule.plan.clone(),
-                    assignment: schedule.assignment.clone(),
+                .map(|lecturer| LecturerResponse {
+                    id: lecturer.id,
+                    name: lecturer.name.clone(),
+                    url: lecturer.url.clone(),
                 })
                 .collect(),
-            out_of_class: course.out_of_class,
-            textbook: course.textbook,
-            reference_book: course.reference_book,
-            assessment: course.assessment,
-            related_course: related_courses
+            lecture_type: course.lecture_type,
+            timetable: timetables
                 .iter()
-                .map(|related_course| related_course.related_course_code.clone())
+                .map(|timetable| TimetableResponse {
+                    course_id: timetable.course_id,
+                    day_of_week: timetable.day,
+                    period: timetable.periods,
+                    room: timetable.room.clone(),
+                })
                 .collect(),
-            prerequisite: course.prerequisite,
-            contact: course.contact,
-            office_hour: course.office_hour,
-            note: course.note,
-        },
-        url: course.url,
-        sylbs_update: course.sylbs_update,
+            code: course.code,
+            credit: course.credit,
+            year: course.year,
+            semester: semesters.iter().map(|semester| semester.semester).collect(),
+            language: course.language,
+            course_detail: CourseDetailResponse {
+                abst: course.r#abstract,
+                goal: course.goal,
+                experience: course.experience,
+                keyword: keywords
+                    .iter()
+                    .map(|keyword| keyword.keyword.clone())
+                    .collect(),
+                competencies: competencies
+                    .iter()
+                    .map(|competency| competency.competency.clone())
+                    .collect(),
+                flow: course.flow,
+                schedule: schedules
+                    .iter()
+                    .map(|schedule| ScheduleResponse {
+                        count: schedule.count,
+                        plan: schedule.plan.clone(),
+                        assignment: schedule.assignment.clone(),
+                    })
+                    .collect(),
+                out_of_class: course.out_of_class,
+                textbook: course.textbook,
+                reference_book: course.reference_book,
+                assessment: course.assessment,
+                related_course: related_courses
+                    .iter()
+                    .map(|related_course| related_course.related_course_code.clone())
+                    .collect(),
+                prerequisite: course.prerequisite,
+                contact: course.contact,
+                office_hour: course.office_hour,
+                note: course.note,
+            },
+            url: course.url,
+            sylbs_update: course.sylbs_update,
+        });
     }
+
+    Ok(responses)
 }
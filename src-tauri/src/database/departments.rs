@@ -0,0 +1,192 @@
+use super::DbResult;
+use crate::scrape::CourseAbstract;
+use crate::settings::CrawlTarget;
+use serde::Serialize;
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepartmentNode {
+    pub id: i64,
+    pub name: String,
+    // GakubuCD of a school, GakkaCD of a department; None until a list page names it
+    pub ocw_code: Option<i64>,
+    // placed by its course codes rather than by a list page
+    pub guessed: bool,
+    pub children: Vec<DepartmentNode>,
+}
+
+#[derive(FromRow)]
+struct DepartmentRow {
+    id: i64,
+    name: String,
+    parent_id: Option<i64>,
+    ocw_code: Option<i64>,
+    guessed: bool,
+}
+
+// Makes the schools of the crawl targets known by their GakubuCD, so the list
+// pages of a school added to the settings can place its departments.
+pub async fn record_schools(pool: &SqlitePool, targets: &[CrawlTarget]) -> DbResult<()> {
+    let mut tx = pool.begin().await?;
+
+    for target in targets {
+        sqlx::query("INSERT OR IGNORE INTO departments (name, parent_id) VALUES (?, NULL)")
+            .bind(&target.name)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE departments SET ocw_code = ?, guessed = 0
+            WHERE parent_id IS NULL AND name = ?",
+        )
+        .bind(target.gakubu_cd)
+        .bind(&target.name)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Places the departments of a list page's rows under the school of their GakubuCD.
+// A guess made from course codes gives way to the list page; rows of a school
+// that is not known yet are left to be guessed when their courses are stored.
+pub async fn record_departments(pool: &SqlitePool, courses: &[CourseAbstract]) -> DbResult<()> {
+    let mut tx = pool.begin().await?;
+
+    for course in courses {
+        let Some(gakubu_cd) = course.gakubu_cd else {
+            continue;
+        };
+        if course.department.is_empty() {
+            continue;
+        }
+        let school: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM departments WHERE parent_id IS NULL AND ocw_code = ?",
+        )
+        .bind(gakubu_cd)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(school) = school else {
+            continue;
+        };
+
+        sqlx::query("DELETE FROM departments WHERE name = ? AND guessed = 1")
+            .bind(&course.department)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT OR IGNORE INTO departments (name, parent_id) VALUES (?, ?)")
+            .bind(&course.department)
+            .bind(school)
+            .execute(&mut *tx)
+            .await?;
+        if course.gakka_cd.is_some() {
+            sqlx::query("UPDATE departments SET ocw_code = ? WHERE parent_id = ? AND name = ?")
+                .bind(course.gakka_cd)
+                .bind(school)
+                .bind(&course.department)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Gives a department no list page has placed a school: the one whose departments
+// have the most courses sharing a code prefix (MTH. of MTH.A201) with it. Without
+// any, it becomes a top-level department of its own.
+pub(super) async fn place_department(tx: &mut SqliteConnection, department: &str) -> DbResult<()> {
+    if department.is_empty() {
+        return Ok(());
+    }
+    let known: Option<i64> = sqlx::query_scalar("SELECT id FROM departments WHERE name = ?")
+        .bind(department)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if known.is_some() {
+        return Ok(());
+    }
+
+    let school: Option<i64> = sqlx::query_scalar(
+        "SELECT d.parent_id
+        FROM courses AS c
+        JOIN departments AS d ON d.name = c.department AND d.parent_id IS NOT NULL
+        WHERE c.department != ?1
+        AND substr(c.code, 1, 4) IN (SELECT substr(code, 1, 4) FROM courses WHERE department = ?1)
+        GROUP BY d.parent_id
+        ORDER BY COUNT(*) DESC, d.parent_id
+        LIMIT 1",
+    )
+    .bind(department)
+    .fetch_optional(&mut *tx)
+    .await?;
+    sqlx::query("INSERT INTO departments (name, parent_id, guessed) VALUES (?, ?, 1)")
+        .bind(department)
+        .bind(school)
+        .execute(&mut *tx)
+        .await?;
+
+    Ok(())
+}
+
+// Departments of courses stored before the departments table existed get their
+// place here.
+pub(super) async fn fill_departments(pool: &SqlitePool) -> DbResult<()> {
+    let mut tx = pool.begin().await?;
+
+    let departments: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT department FROM courses
+        WHERE department NOT IN (SELECT name FROM departments)
+        ORDER BY department",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for department in departments {
+        place_department(&mut tx, &department).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Schools with their departments and graduate courses, in the order they became
+// known. A graduate course run by several schools appears under each of them.
+pub async fn get_department_tree(pool: &SqlitePool) -> DbResult<Vec<DepartmentNode>> {
+    let rows: Vec<DepartmentRow> = sqlx::query_as(
+        "SELECT id, name, parent_id, ocw_code, guessed FROM departments ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut children: HashMap<Option<i64>, Vec<DepartmentRow>> = HashMap::new();
+    for row in rows {
+        children.entry(row.parent_id).or_default().push(row);
+    }
+
+    fn build(
+        parent: Option<i64>,
+        children: &mut HashMap<Option<i64>, Vec<DepartmentRow>>,
+    ) -> Vec<DepartmentNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|row| DepartmentNode {
+                children: build(Some(row.id), children),
+                id: row.id,
+                name: row.name,
+                ocw_code: row.ocw_code,
+                guessed: row.guessed,
+            })
+            .collect()
+    }
+
+    Ok(build(None, &mut children))
+}
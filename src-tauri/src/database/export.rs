@@ -0,0 +1,215 @@
+use super::{get_course_ids, get_courses, search, CourseResponse, DbResult, SearchQuery};
+use crate::Course;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ExportFormat {
+    // one array of CourseResponse, the format import_courses reads back
+    Json,
+    // one CourseResponse per line
+    Ndjson,
+    // one row per course with lists joined, for spreadsheets
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name {
+            "json" => Some(ExportFormat::Json),
+            "ndjson" => Some(ExportFormat::Ndjson),
+            "csv" => Some(ExportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+// Every course, or the ones `query` finds in the order it sorts them, in full.
+pub async fn export_courses(
+    pool: &SqlitePool,
+    query: Option<SearchQuery>,
+    format: ExportFormat,
+) -> DbResult<String> {
+    let ids = match query {
+        // without the count and facets of a search, and without its paging
+        Some(query) => search::find_courses(pool, &query)
+            .await?
+            .into_iter()
+            .map(|course| course.id)
+            .collect(),
+        None => get_course_ids(pool).await?,
+    };
+
+    let courses = get_courses(pool, &ids).await?;
+
+    let output = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&courses)?,
+        ExportFormat::Ndjson => {
+            let mut output = String::new();
+            for course in &courses {
+                output.push_str(&serde_json::to_string(course)?);
+                output.push('\n');
+            }
+            output
+        }
+        ExportFormat::Csv => to_csv(&courses),
+    };
+
+    Ok(output)
+}
+
+// Stores the courses of a JSON export, replacing stored courses with the same code,
+// year and title. The file goes in whole or not at all. Returns how many were
+// stored.
+pub async fn import_courses(pool: &SqlitePool, json: &str) -> DbResult<usize> {
+    let courses: Vec<CourseResponse> = serde_json::from_str(json)?;
+    let courses = courses
+        .into_iter()
+        .map(Course::try_from)
+        .collect::<DbResult<Vec<_>>>()?;
+
+    let mut tx = pool.begin().await?;
+    for course in &courses {
+        super::replace_course_in(&mut tx, course).await?;
+    }
+    tx.commit().await?;
+
+    Ok(courses.len())
+}
+
+const CSV_HEADER: [&str; 31] = [
+    "id",
+    "university",
+    "code",
+    "title",
+    "englishTitle",
+    "department",
+    "lecturer",
+    "lectureType",
+    "timetable",
+    "credit",
+    "year",
+    "semester",
+    "language",
+    "url",
+    "sylbsUpdate",
+    "abst",
+    "goal",
+    "experience",
+    "keyword",
+    "competencies",
+    "flow",
+    "schedule",
+    "outOfClass",
+    "textbook",
+    "referenceBook",
+    "assessment",
+    "relatedCourse",
+    "prerequisite",
+    "contact",
+    "officeHour",
+    "note",
+];
+
+// The columns are the CourseResponse fields with courseDetail flattened. Lists are
+// joined with "; ", timetables and quarters read like the course list (月1-2 W521,
+// 3Q), and the file starts with a BOM so Excel reads it as UTF-8.
+fn to_csv(courses: &[CourseResponse]) -> String {
+    let mut output = String::from("\u{feff}");
+    push_csv_row(&mut output, CSV_HEADER.iter().map(|name| name.to_string()));
+
+    for course in courses {
+        let detail = &course.course_detail;
+        push_csv_row(
+            &mut output,
+            [
+                course.id.to_string(),
+                course.university.clone(),
+                course.code.clone(),
+                course.title.clone(),
+                course.english_title.clone(),
+                course.department.clone(),
+                course
+                    .lecturer
+                    .iter()
+                    .map(|lecturer| lecturer.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                course.lecture_type.clone(),
+                course
+                    .timetable
+                    .iter()
+                    .map(|timetable| {
+                        format!(
+                            "{}{}-{} {}",
+                            "日月火水木金土"
+                                .chars()
+                                .nth(timetable.day_of_week as usize)
+                                .unwrap_or('?'),
+                            timetable.period * 2 - 1,
+                            timetable.period * 2,
+                            timetable.room
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                course.credit.to_string(),
+                course.year.to_string(),
+                course
+                    .semester
+                    .iter()
+                    .map(|semester| format!("{}Q", semester))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                course.language.clone(),
+                course.url.clone(),
+                course.sylbs_update.clone(),
+                detail.abst.clone(),
+                detail.goal.clone(),
+                detail.experience.to_string(),
+                detail.keyword.join("; "),
+                detail.competencies.join("; "),
+                detail.flow.clone(),
+                detail
+                    .schedule
+                    .iter()
+                    .map(|schedule| {
+                        format!(
+                            "{}. {} / {}",
+                            schedule.count, schedule.plan, schedule.assignment
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                detail.out_of_class.clone(),
+                detail.textbook.clone(),
+                detail.reference_book.clone(),
+                detail.assessment.clone(),
+                detail.related_course.join("; "),
+                detail.prerequisite.clone(),
+                detail.contact.clone(),
+                detail.office_hour.clone(),
+                detail.note.clone(),
+            ],
+        );
+    }
+
+    output
+}
+
+// Appends one RFC 4180 row: fields with a comma, quote or line break are quoted.
+fn push_csv_row(output: &mut String, fields: impl IntoIterator<Item = String>) {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            output.push('"');
+            output.push_str(&field.replace('"', "\"\""));
+            output.push('"');
+        } else {
+            output.push_str(&field);
+        }
+    }
+    output.push_str("\r\n");
+}
@@ -0,0 +1,318 @@
+use super::DbResult;
+use crate::error::AppError;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Relation {
+    // the later course names the earlier one in its prerequisite text
+    Prerequisite,
+    // one of the two lists the other under related courses
+    Related,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Direction {
+    // the courses to take before
+    Ancestors,
+    // the courses to take after
+    Descendants,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum GraphFormat {
+    // Graphviz, for `dot -Tsvg`
+    Dot,
+    Json,
+}
+
+impl GraphFormat {
+    pub fn from_name(name: &str) -> Option<GraphFormat> {
+        match name {
+            "dot" => Some(GraphFormat::Dot),
+            "json" => Some(GraphFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    pub code: String,
+    // the latest course with the code; None when it is only mentioned by others
+    pub id: Option<i32>,
+    // empty when id is None
+    pub title: String,
+    // steps from the course the graph was taken around, None for the whole graph
+    pub depth: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+    // taken before `to`
+    pub from: String,
+    pub to: String,
+    pub relation: Relation,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CourseGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+// MTH.A201; the number's first digit is the level, see SearchQuery::grade. The
+// boundaries are ASCII ones, since Japanese text runs straight on from the code,
+// as in MTH.A201を履修.
+fn course_code_re() -> Regex {
+    Regex::new(r"(?-u:\b)[A-Z]{3}\.[A-Z]\d{3}(?-u:\b)").unwrap()
+}
+
+// Syllabi type codes in full width as often as not, so NFKC comes first.
+pub fn course_codes(text: &str) -> Vec<String> {
+    let text = text.nfkc().collect::<String>();
+    let mut codes = Vec::new();
+    for code in course_code_re().find_iter(&text) {
+        let code = code.as_str().to_string();
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+
+    codes
+}
+
+// Related courses carry no direction, so the one with the lower number is taken
+// to come first, and the code decides between equal numbers.
+fn related_edge(a: &str, b: &str) -> GraphEdge {
+    let number = |code: &'_ str| code.get(5..).unwrap_or_default().to_string();
+    let (from, to) = if (number(a), a) <= (number(b), b) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+
+    GraphEdge {
+        from: from.to_string(),
+        to: to.to_string(),
+        relation: Relation::Related,
+    }
+}
+
+// Every course code with the links from the latest course of each code. Codes
+// that are only mentioned become nodes without an id or title.
+pub async fn get_course_graph(pool: &SqlitePool) -> DbResult<CourseGraph> {
+    let courses: Vec<(i32, String, String, Option<String>)> = sqlx::query_as(
+        "SELECT id, code, title, prerequisite FROM courses ORDER BY code, year DESC, id DESC",
+    )
+    .fetch_all(pool)
+    .await?;
+    let related: Vec<(i32, String)> =
+        sqlx::query_as("SELECT course_id, related_course_code FROM related_courses ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+
+    let mut nodes = BTreeMap::new();
+    let mut edges = BTreeSet::new();
+    let mut latest = BTreeMap::new();
+
+    for (id, code, title, prerequisite) in courses {
+        if nodes.contains_key(&code) {
+            continue;
+        }
+        for from in course_codes(prerequisite.as_deref().unwrap_or_default()) {
+            if from != code {
+                edges.insert(GraphEdge {
+                    from,
+                    to: code.clone(),
+                    relation: Relation::Prerequisite,
+                });
+            }
+        }
+        latest.insert(id, code.clone());
+        nodes.insert(
+            code.clone(),
+            GraphNode {
+                code,
+                id: Some(id),
+                title,
+                depth: None,
+            },
+        );
+    }
+
+    for (course_id, related_code) in related {
+        let Some(code) = latest.get(&course_id) else {
+            continue;
+        };
+        for other in course_codes(&related_code) {
+            if &other != code {
+                edges.insert(related_edge(code, &other));
+            }
+        }
+    }
+
+    for edge in &edges {
+        for code in [&edge.from, &edge.to] {
+            if !nodes.contains_key(code) {
+                nodes.insert(
+                    code.clone(),
+                    GraphNode {
+                        code: code.clone(),
+                        id: None,
+                        title: String::new(),
+                        depth: None,
+                    },
+                );
+            }
+        }
+    }
+
+    Ok(CourseGraph {
+        nodes: nodes.into_values().collect(),
+        edges: edges.into_iter().collect(),
+    })
+}
+
+impl CourseGraph {
+    // The courses up to `depth` links before and/or after `code`, with the links
+    // followed to reach them. A course both before and after, as with a cycle of
+    // related courses, is kept at the depth it was first reached.
+    pub fn relations(&self, code: &str, direction: Direction, depth: u32) -> DbResult<CourseGraph> {
+        if !self.nodes.iter().any(|node| node.code == code) {
+            return Err(AppError::NotFound(format!("course {}", code)));
+        }
+
+        let mut depths = BTreeMap::from([(code, 0)]);
+        let mut edges = BTreeSet::new();
+        let ways: &[bool] = match direction {
+            Direction::Ancestors => &[true],
+            Direction::Descendants => &[false],
+            Direction::Both => &[true, false],
+        };
+
+        for &backwards in ways {
+            let mut seen = BTreeMap::from([(code, 0)]);
+            let mut queue = VecDeque::from([code]);
+            while let Some(current) = queue.pop_front() {
+                let current_depth = seen[current];
+                if current_depth == depth {
+                    continue;
+                }
+                for edge in &self.edges {
+                    let (near, far) = if backwards {
+                        (&edge.to, &edge.from)
+                    } else {
+                        (&edge.from, &edge.to)
+                    };
+                    if near.as_str() != current {
+                        continue;
+                    }
+                    edges.insert(edge.clone());
+                    if !seen.contains_key(far.as_str()) {
+                        seen.insert(far.as_str(), current_depth + 1);
+                        queue.push_back(far.as_str());
+                    }
+                }
+            }
+            for (reached, steps) in seen {
+                depths.entry(reached).or_insert(steps);
+            }
+        }
+
+        Ok(CourseGraph {
+            nodes: self
+                .nodes
+                .iter()
+                .filter_map(|node| {
+                    depths.get(node.code.as_str()).map(|&depth| GraphNode {
+                        depth: Some(depth),
+                        ..node.clone()
+                    })
+                })
+                .collect(),
+            edges: edges.into_iter().collect(),
+        })
+    }
+
+    // Related links are dashed, and courses missing from the database are grey.
+    pub fn to_dot(&self) -> String {
+        fn escape(text: &str) -> String {
+            text.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        let mut output =
+            String::from("digraph courses {\n    rankdir=LR;\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let label = if node.title.is_empty() {
+                escape(&node.code)
+            } else {
+                format!("{}\\n{}", escape(&node.code), escape(&node.title))
+            };
+            output.push_str(&format!(
+                "    \"{}\" [label=\"{}\"{}];\n",
+                escape(&node.code),
+                label,
+                if node.id.is_none() {
+                    ", color=grey"
+                } else {
+                    ""
+                }
+            ));
+        }
+        for edge in &self.edges {
+            output.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                escape(&edge.from),
+                escape(&edge.to),
+                if edge.relation == Relation::Related {
+                    " [style=dashed]"
+                } else {
+                    ""
+                }
+            ));
+        }
+        output.push_str("}\n");
+
+        output
+    }
+}
+
+pub async fn get_course_relations(
+    pool: &SqlitePool,
+    code: &str,
+    direction: Direction,
+    depth: u32,
+) -> DbResult<CourseGraph> {
+    get_course_graph(pool)
+        .await?
+        .relations(code, direction, depth)
+}
+
+// The whole graph, or the part around `code` when given.
+pub async fn export_course_graph(
+    pool: &SqlitePool,
+    code: Option<&str>,
+    direction: Direction,
+    depth: u32,
+    format: GraphFormat,
+) -> DbResult<String> {
+    let graph = match code {
+        Some(code) => get_course_relations(pool, code, direction, depth).await?,
+        None => get_course_graph(pool).await?,
+    };
+
+    let output = match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Json => serde_json::to_string_pretty(&graph)?,
+    };
+
+    Ok(output)
+}
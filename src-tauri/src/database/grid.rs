@@ -0,0 +1,263 @@
+use super::search::{self, CourseListItem, SearchQuery};
+use super::{fetch_for_courses, DbResult};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
+
+// Sunday to Saturday, numbered as in timetables.day
+const DAYS: usize = 7;
+// 1-2 to 11-12, numbered from 1 in timetables.periods
+const PERIODS: usize = 6;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum GridAxis {
+    // one grid per department, as a department office sees its week
+    #[default]
+    Department,
+    // one per lecturer; a course with several lecturers is in each of their grids
+    Lecturer,
+    // one per room, each session only in the rooms it meets in
+    Room,
+}
+
+impl GridAxis {
+    pub fn from_name(name: &str) -> Option<GridAxis> {
+        match name {
+            "department" => Some(GridAxis::Department),
+            "lecturer" => Some(GridAxis::Lecturer),
+            "room" => Some(GridAxis::Room),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GridCell {
+    pub courses: Vec<CourseListItem>,
+    // the courses here that meet at the same time as another chosen course
+    pub conflicts: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GridGroup {
+    // the department, lecturer or room; empty for courses without one
+    pub name: String,
+    // cells[day][period], the day from Sunday and the period from 1-2
+    pub cells: Vec<Vec<GridCell>>,
+    // courses without a session, such as intensive ones
+    pub unscheduled: Vec<CourseListItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimetableGrid {
+    pub axis: GridAxis,
+    // by name, with the courses that have none last
+    pub groups: Vec<GridGroup>,
+}
+
+impl GridGroup {
+    fn new(name: String) -> Self {
+        GridGroup {
+            name,
+            cells: vec![vec![GridCell::default(); PERIODS]; DAYS],
+            unscheduled: Vec::new(),
+        }
+    }
+}
+
+// A session of a course: (timetable id, day, periods)
+type Session = (i64, i32, i32);
+
+// Year and quarters of the courses, to tell whether two of them meet at the same time.
+struct Terms {
+    years: HashMap<i32, i32>,
+    quarters: HashMap<i32, Vec<i32>>,
+}
+
+impl Terms {
+    // Courses of the same year overlap when they share a quarter. One without any
+    // quarter is taken to overlap every other, as nothing says it does not.
+    fn overlap(&self, a: i32, b: i32) -> bool {
+        if self.years.get(&a) != self.years.get(&b) {
+            return false;
+        }
+        match (self.quarters.get(&a), self.quarters.get(&b)) {
+            (Some(a), Some(b)) => a.iter().any(|quarter| b.contains(quarter)),
+            _ => true,
+        }
+    }
+}
+
+// The courses `search_query` finds laid out by day and period, one grid per value
+// of `axis`. A course in a cell is listed under conflicts when one of `selected`
+// other than itself meets in the same slot, year and quarter; the selected courses
+// count whether or not the query finds them.
+pub async fn get_timetable_grid(
+    pool: &SqlitePool,
+    search_query: &SearchQuery,
+    axis: GridAxis,
+    selected: &[i32],
+) -> DbResult<TimetableGrid> {
+    let items = search::find_courses(pool, search_query).await?;
+
+    let mut ids: Vec<i32> = items.iter().map(|course| course.id).collect();
+    for id in selected {
+        if !ids.contains(id) {
+            ids.push(*id);
+        }
+    }
+
+    let mut conn = pool.acquire().await?;
+    let mut sessions: HashMap<i32, Vec<Session>> = HashMap::new();
+    let rows: Vec<(i64, i32, i32, i32)> = fetch_for_courses(
+        &mut conn,
+        "SELECT id, course_id, day, periods FROM timetables WHERE course_id",
+        &ids,
+    )
+    .await?;
+    for (id, course_id, day, periods) in rows {
+        sessions
+            .entry(course_id)
+            .or_default()
+            .push((id, day, periods));
+    }
+
+    let mut terms = Terms {
+        years: HashMap::new(),
+        quarters: HashMap::new(),
+    };
+    let rows: Vec<(i32, i32, Option<i32>)> = fetch_for_courses(
+        &mut conn,
+        "SELECT c.id, c.year, s.semester
+        FROM courses AS c LEFT JOIN semesters AS s ON s.course_id = c.id
+        WHERE c.id",
+        &ids,
+    )
+    .await?;
+    for (id, year, quarter) in rows {
+        terms.years.insert(id, year);
+        if let Some(quarter) = quarter {
+            terms.quarters.entry(id).or_default().push(quarter);
+        }
+    }
+
+    // names per course for Department and Lecturer, per session for Room
+    let mut names: HashMap<i64, Vec<String>> = HashMap::new();
+    match axis {
+        GridAxis::Department => {
+            for course in &items {
+                names.insert(course.id.into(), vec![course.department.clone()]);
+            }
+        }
+        GridAxis::Lecturer => {
+            let rows: Vec<(i32, String)> = fetch_for_courses(
+                &mut conn,
+                "SELECT course_id, name FROM lecturers WHERE course_id",
+                &ids,
+            )
+            .await?;
+            for (course_id, name) in rows {
+                names.entry(course_id.into()).or_default().push(name);
+            }
+        }
+        GridAxis::Room => {
+            let rows: Vec<(i64, String)> = fetch_for_courses(
+                &mut conn,
+                "SELECT tr.timetable_id, r.name
+                FROM timetable_rooms AS tr
+                JOIN rooms AS r ON r.id = tr.room_id
+                JOIN timetables AS t ON t.id = tr.timetable_id
+                WHERE t.course_id",
+                &ids,
+            )
+            .await?;
+            for (timetable_id, name) in rows {
+                names.entry(timetable_id).or_default().push(name);
+            }
+        }
+    }
+    let names_of = |key: i64| {
+        names
+            .get(&key)
+            .filter(|names| !names.is_empty())
+            .cloned()
+            .unwrap_or_else(|| vec![String::new()])
+    };
+
+    let chosen: Vec<(i32, i32, i32)> = selected
+        .iter()
+        .flat_map(|id| {
+            sessions
+                .get(id)
+                .into_iter()
+                .flatten()
+                .map(move |&(_, day, periods)| (*id, day, periods))
+        })
+        .collect();
+
+    let mut groups: BTreeMap<String, GridGroup> = BTreeMap::new();
+    for course in &items {
+        let course_sessions = sessions
+            .get(&course.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if course_sessions.is_empty() {
+            // without a session there is no room either
+            let names = match axis {
+                GridAxis::Room => vec![String::new()],
+                GridAxis::Department | GridAxis::Lecturer => names_of(course.id.into()),
+            };
+            for name in names {
+                let group = groups
+                    .entry(name.clone())
+                    .or_insert_with(|| GridGroup::new(name));
+                group.unscheduled.push(course.clone());
+            }
+            continue;
+        }
+
+        for &(timetable_id, day, periods) in course_sessions {
+            let (Ok(day_index), Ok(period_index)) =
+                (usize::try_from(day), usize::try_from(periods - 1))
+            else {
+                continue;
+            };
+            if day_index >= DAYS || period_index >= PERIODS {
+                continue;
+            }
+            let conflict = chosen.iter().any(|&(other, other_day, other_periods)| {
+                other != course.id
+                    && other_day == day
+                    && other_periods == periods
+                    && terms.overlap(other, course.id)
+            });
+            let key = match axis {
+                GridAxis::Room => timetable_id,
+                GridAxis::Department | GridAxis::Lecturer => course.id.into(),
+            };
+
+            for name in names_of(key) {
+                let group = groups
+                    .entry(name.clone())
+                    .or_insert_with(|| GridGroup::new(name));
+                let cell = &mut group.cells[day_index][period_index];
+                if !cell.courses.iter().any(|other| other.id == course.id) {
+                    cell.courses.push(course.clone());
+                }
+                if conflict && !cell.conflicts.contains(&course.id) {
+                    cell.conflicts.push(course.id);
+                }
+            }
+        }
+    }
+
+    let (unnamed, mut groups): (Vec<GridGroup>, Vec<GridGroup>) = groups
+        .into_values()
+        .partition(|group| group.name.is_empty());
+    groups.extend(unnamed);
+
+    Ok(TimetableGrid { axis, groups })
+}
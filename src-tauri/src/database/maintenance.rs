@@ -0,0 +1,190 @@
+use super::{count_rows, DbResult};
+use serde::Serialize;
+use sqlx::{FromRow, SqlitePool};
+
+// The tables holding one course's lists, each with course_id pointing at courses.
+const CHILD_TABLES: [&str; 7] = [
+    "lecturers",
+    "timetables",
+    "semesters",
+    "keywords",
+    "competencies",
+    "related_courses",
+    "schedules",
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableCount {
+    pub table: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DepartmentYearCount {
+    pub department: String,
+    pub year: i32,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStats {
+    pub tables: Vec<TableCount>,
+    pub courses: Vec<DepartmentYearCount>,
+    // bytes in the main database file, including free pages
+    pub size: i64,
+    // bytes in free pages, which compact_database gives back
+    pub free: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    // the lines PRAGMA integrity_check printed; empty when it answered ok
+    pub problems: Vec<String>,
+    // child rows whose course no longer exists, per table with any
+    pub orphans: Vec<TableCount>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty() && self.orphans.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactReport {
+    pub size_before: i64,
+    pub size_after: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetReport {
+    pub courses: u64,
+    pub archived_pages: u64,
+}
+
+async fn database_size(pool: &SqlitePool) -> DbResult<(i64, i64)> {
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+        .fetch_one(pool)
+        .await?;
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count")
+        .fetch_one(pool)
+        .await?;
+    let free_pages: i64 = sqlx::query_scalar("PRAGMA freelist_count")
+        .fetch_one(pool)
+        .await?;
+
+    Ok((page_size * page_count, page_size * free_pages))
+}
+
+pub async fn get_database_stats(pool: &SqlitePool) -> DbResult<DatabaseStats> {
+    let tables = count_rows(pool)
+        .await?
+        .into_iter()
+        .map(|(table, count)| TableCount { table, count })
+        .collect();
+    let courses = sqlx::query_as(
+        "SELECT department, year, COUNT(*) AS count FROM courses
+        GROUP BY department, year ORDER BY department, year",
+    )
+    .fetch_all(pool)
+    .await?;
+    let (size, free) = database_size(pool).await?;
+
+    Ok(DatabaseStats {
+        tables,
+        courses,
+        size,
+        free,
+    })
+}
+
+// Child rows can lose their course when foreign keys were off, as they were for
+// databases written before 005_constraints.sql or by other SQLite clients.
+pub async fn find_orphans(pool: &SqlitePool) -> DbResult<Vec<TableCount>> {
+    let mut orphans = Vec::new();
+    for table in CHILD_TABLES {
+        let count = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {} WHERE course_id NOT IN (SELECT id FROM courses)",
+            table
+        ))
+        .fetch_one(pool)
+        .await?;
+        if count > 0 {
+            orphans.push(TableCount {
+                table: table.to_string(),
+                count,
+            });
+        }
+    }
+
+    Ok(orphans)
+}
+
+pub async fn check_database(pool: &SqlitePool) -> DbResult<IntegrityReport> {
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(IntegrityReport {
+        problems: problems.into_iter().filter(|line| line != "ok").collect(),
+        orphans: find_orphans(pool).await?,
+    })
+}
+
+// Moves the write-ahead log into the database file and rebuilds it without free
+// pages. VACUUM itself goes through the log, hence the second checkpoint.
+pub async fn compact_database(pool: &SqlitePool) -> DbResult<CompactReport> {
+    let (size_before, _) = database_size(pool).await?;
+
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+    sqlx::query("VACUUM").execute(pool).await?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+
+    let (size_after, _) = database_size(pool).await?;
+
+    Ok(CompactReport {
+        size_before,
+        size_after,
+    })
+}
+
+// Deletes every course with its lists and the record of imported snapshots, so the
+// next fetch starts from scratch. Search rules are kept. The archived pages are
+// kept too unless `archive` is set, so reparse_archive can rebuild the courses
+// without hitting OCW; their files are the caller's to remove.
+pub async fn reset_crawl_data(pool: &SqlitePool, archive: bool) -> DbResult<ResetReport> {
+    let mut tx = pool.begin().await?;
+
+    let courses = sqlx::query("DELETE FROM courses")
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    sqlx::query("DELETE FROM snapshots")
+        .execute(&mut *tx)
+        .await?;
+    let archived_pages = if archive {
+        sqlx::query("DELETE FROM archived_pages")
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+    } else {
+        0
+    };
+
+    tx.commit().await?;
+
+    Ok(ResetReport {
+        courses,
+        archived_pages,
+    })
+}
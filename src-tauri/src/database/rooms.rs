@@ -0,0 +1,353 @@
+use super::{
+    day_from_number, day_number, period_from_number, period_number, semester_from_number,
+    semester_number, DbResult,
+};
+use crate::error::AppError;
+use crate::scrape::{Day, Period, Semester};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Campus {
+    Ookayama,
+    Suzukakedai,
+    Tamachi,
+    // Zoom and other rooms that are not rooms
+    Online,
+}
+
+impl Campus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Campus::Ookayama => "Ookayama",
+            Campus::Suzukakedai => "Suzukakedai",
+            Campus::Tamachi => "Tamachi",
+            Campus::Online => "Online",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Campus> {
+        match name {
+            "Ookayama" => Some(Campus::Ookayama),
+            "Suzukakedai" => Some(Campus::Suzukakedai),
+            "Tamachi" => Some(Campus::Tamachi),
+            "Online" => Some(Campus::Online),
+            _ => None,
+        }
+    }
+
+    // by the leading letters of a building code
+    fn from_building(building: &str) -> Option<Campus> {
+        let letters = building
+            .chars()
+            .take_while(|c| c.is_ascii_uppercase())
+            .collect::<String>();
+        match letters.as_str() {
+            "W" | "WL" | "S" | "SL" | "I" | "H" | "M" | "E" | "N" => Some(Campus::Ookayama),
+            "G" | "J" | "B" | "R" => Some(Campus::Suzukakedai),
+            "CIC" => Some(Campus::Tamachi),
+            _ => None,
+        }
+    }
+
+    // by a place name written next to the rooms
+    fn from_text(text: &str) -> Option<Campus> {
+        let lower = text.to_lowercase();
+        if ["zoom", "online", "オンライン", "遠隔"]
+            .iter()
+            .any(|word| lower.contains(word))
+        {
+            Some(Campus::Online)
+        } else if text.contains("すずかけ台") {
+            Some(Campus::Suzukakedai)
+        } else if text.contains("田町") {
+            Some(Campus::Tamachi)
+        } else if text.contains("大岡山") || text.contains("石川台") {
+            Some(Campus::Ookayama)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Room {
+    pub name: String,
+    pub building: Option<String>,
+    pub campus: Option<Campus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomCourse {
+    pub id: i32,
+    pub code: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSlot {
+    pub quarter: Semester,
+    pub day: Day,
+    pub period: Period,
+    // more than one when courses share the room, or the same course is listed twice
+    pub courses: Vec<RoomCourse>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomOccupancy {
+    pub room: Room,
+    pub year: i32,
+    // only the used slots, by quarter, day and period
+    pub slots: Vec<RoomSlot>,
+}
+
+// W8E-101 and S2-203 with the building before the dash; W631 and S221 with the
+// building number as the first digit; H121 in the Main Building, which has none.
+fn room_code_re() -> Regex {
+    Regex::new(
+        r"(?P<dashed>[A-Z]{1,3}\d{0,2}[A-Z]?)-[A-Z]?\d{1,4}|(?P<main>H)\d{3}|(?P<letters>[A-Z]{1,2})(?P<number>\d)\d{2,3}",
+    )
+    .unwrap()
+}
+
+// Splits a timetables.room string into rooms. Room codes are picked out wherever
+// they appear, so "W631，W641" and "すずかけ台 J221" both work; anything else is kept
+// whole per comma-separated part, with at most a campus guessed from the text.
+pub fn parse_rooms(room: &str) -> Vec<Room> {
+    let room = room.nfkc().collect::<String>();
+    let hint = Campus::from_text(&room);
+
+    let mut rooms = Vec::new();
+    for caps in room_code_re().captures_iter(&room) {
+        let building = match (caps.name("dashed"), caps.name("main")) {
+            (Some(dashed), _) => dashed.as_str().to_string(),
+            (None, Some(main)) => main.as_str().to_string(),
+            (None, None) => format!("{}{}", &caps["letters"], &caps["number"]),
+        };
+        // a place name decides, except that "Zoom / W631" still has a real room
+        let campus = match hint {
+            None | Some(Campus::Online) => Campus::from_building(&building).or(hint),
+            _ => hint,
+        };
+        push_room(
+            &mut rooms,
+            Room {
+                name: caps[0].to_string(),
+                building: Some(building),
+                campus,
+            },
+        );
+    }
+
+    if rooms.is_empty() {
+        for part in room.split([',', '、', '/', ';']) {
+            let part = part.trim();
+            if !part.is_empty() {
+                push_room(
+                    &mut rooms,
+                    Room {
+                        name: part.to_string(),
+                        building: None,
+                        campus: Campus::from_text(part).or(hint),
+                    },
+                );
+            }
+        }
+    }
+
+    rooms
+}
+
+fn push_room(rooms: &mut Vec<Room>, room: Room) {
+    if !rooms.iter().any(|other| other.name == room.name) {
+        rooms.push(room);
+    }
+}
+
+// Records the rooms of one timetables row.
+pub(super) async fn link_rooms(
+    tx: &mut SqliteConnection,
+    timetable_id: i64,
+    room: &str,
+) -> DbResult<()> {
+    for room in parse_rooms(room) {
+        sqlx::query("INSERT OR IGNORE INTO rooms (name, building, campus) VALUES (?, ?, ?)")
+            .bind(&room.name)
+            .bind(&room.building)
+            .bind(room.campus.map(|campus| campus.name()))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO timetable_rooms (timetable_id, room_id)
+            SELECT ?, id FROM rooms WHERE name = ?",
+        )
+        .bind(timetable_id)
+        .bind(&room.name)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Timetables stored before the rooms table existed get their rooms here.
+pub(super) async fn fill_rooms(pool: &SqlitePool) -> DbResult<()> {
+    let mut tx = pool.begin().await?;
+
+    let timetables: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT id, room FROM timetables
+        WHERE room != '' AND id NOT IN (SELECT timetable_id FROM timetable_rooms)",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for (id, room) in timetables {
+        link_rooms(&mut tx, id, &room).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+fn room_from_row(row: &sqlx::sqlite::SqliteRow) -> DbResult<Room> {
+    Ok(Room {
+        name: row.try_get("name")?,
+        building: row.try_get("building")?,
+        campus: row
+            .try_get::<Option<String>, _>("campus")?
+            .as_deref()
+            .and_then(Campus::from_name),
+    })
+}
+
+// Every known room, by campus and building.
+pub async fn get_rooms(pool: &SqlitePool, campus: Option<Campus>) -> DbResult<Vec<Room>> {
+    let rows = sqlx::query(
+        "SELECT name, building, campus FROM rooms
+        WHERE ?1 IS NULL OR campus = ?1
+        ORDER BY campus IS NULL, campus, building IS NULL, building, name",
+    )
+    .bind(campus.map(|campus| campus.name()))
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(room_from_row).collect()
+}
+
+async fn latest_year(pool: &SqlitePool) -> DbResult<i32> {
+    let year: Option<i32> = sqlx::query_scalar("SELECT MAX(year) FROM courses")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(year.unwrap_or_default())
+}
+
+// What meets in room `name` during `year`, the latest year when None. Courses
+// without a quarter, such as intensive ones, have no place in the week and are left
+// out.
+pub async fn get_room_occupancy(
+    pool: &SqlitePool,
+    name: &str,
+    year: Option<i32>,
+) -> DbResult<RoomOccupancy> {
+    let row = sqlx::query("SELECT name, building, campus FROM rooms WHERE name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("room {}", name)))?;
+    let room = room_from_row(&row)?;
+    let year = match year {
+        Some(year) => year,
+        None => latest_year(pool).await?,
+    };
+
+    let rows = sqlx::query(
+        "SELECT s.semester, t.day, t.periods, c.id, c.code, c.title
+        FROM rooms AS r
+        JOIN timetable_rooms AS tr ON tr.room_id = r.id
+        JOIN timetables AS t ON t.id = tr.timetable_id
+        JOIN courses AS c ON c.id = t.course_id
+        JOIN semesters AS s ON s.course_id = c.id
+        WHERE r.name = ? AND c.year = ?
+        ORDER BY s.semester, t.day, t.periods, c.code, c.id",
+    )
+    .bind(name)
+    .bind(year)
+    .fetch_all(pool)
+    .await?;
+
+    let mut slots: Vec<RoomSlot> = Vec::new();
+    let mut last = None;
+    for row in rows {
+        let key: (i32, i32, i32) = (
+            row.try_get("semester")?,
+            row.try_get("day")?,
+            row.try_get("periods")?,
+        );
+        if last != Some(key) {
+            slots.push(RoomSlot {
+                quarter: semester_from_number(key.0)?,
+                day: day_from_number(key.1)?,
+                period: period_from_number(key.2)?,
+                courses: Vec::new(),
+            });
+            last = Some(key);
+        }
+        if let Some(slot) = slots.last_mut() {
+            slot.courses.push(RoomCourse {
+                id: row.try_get("id")?,
+                code: row.try_get("code")?,
+                title: row.try_get("title")?,
+            });
+        }
+    }
+
+    Ok(RoomOccupancy { room, year, slots })
+}
+
+// The known rooms no course of `year` (the latest when None) uses at `day` and
+// `period` in `quarter`, optionally on one campus. Online rooms are never empty, and
+// neither are rooms without a building, which are free text such as 未定.
+pub async fn find_empty_rooms(
+    pool: &SqlitePool,
+    day: &Day,
+    period: &Period,
+    quarter: &Semester,
+    year: Option<i32>,
+    campus: Option<Campus>,
+) -> DbResult<Vec<Room>> {
+    let year = match year {
+        Some(year) => year,
+        None => latest_year(pool).await?,
+    };
+
+    let rows = sqlx::query(
+        "SELECT name, building, campus FROM rooms
+        WHERE (?1 IS NULL OR campus = ?1) AND campus IS NOT 'Online'
+        AND building IS NOT NULL
+        AND id NOT IN (
+            SELECT tr.room_id
+            FROM timetable_rooms AS tr
+            JOIN timetables AS t ON t.id = tr.timetable_id
+            JOIN courses AS c ON c.id = t.course_id
+            JOIN semesters AS s ON s.course_id = c.id
+            WHERE t.day = ?2 AND t.periods = ?3 AND s.semester = ?4 AND c.year = ?5
+        )
+        ORDER BY campus IS NULL, campus, building, name",
+    )
+    .bind(campus.map(|campus| campus.name()))
+    .bind(day_number(day))
+    .bind(period_number(period))
+    .bind(semester_number(quarter))
+    .bind(year)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(room_from_row).collect()
+}
@@ -0,0 +1,206 @@
+use super::DbResult;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqlitePool};
+
+// A predicate on one field of a CourseListItem. Courses matching an enabled Exclude
+// rule are left out of search results unless they also match an enabled Include rule,
+// so an Include rule can bring back e.g. one department an Exclude rule would hide.
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct SearchRule {
+    // ignored by add_search_rule
+    #[serde(default)]
+    pub id: i64,
+    pub name: String,
+    pub action: RuleAction,
+    pub field: RuleField,
+    pub match_kind: RuleMatch,
+    pub pattern: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, sqlx::Type)]
+pub enum RuleAction {
+    Include,
+    Exclude,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, sqlx::Type)]
+pub enum RuleField {
+    University,
+    Code,
+    Title,
+    Lecturer,
+    Timetable,
+    Semester,
+    Department,
+    Credit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, sqlx::Type)]
+pub enum RuleMatch {
+    Contains,
+    Equals,
+    // SQLite GLOB, e.g. LAH.* for every course code starting with LAH.
+    Glob,
+}
+
+// How many courses a search left out because of one Exclude rule. A course matching
+// several rules is counted under each of them.
+#[derive(Debug, Serialize)]
+pub struct HiddenCount {
+    pub rule_id: i64,
+    pub name: String,
+    pub count: i64,
+}
+
+impl SearchRule {
+    // Appends the rule as a condition on the course `c`. Lecturers, timetables and
+    // semesters match when any one of the course's rows does, in the same form as
+    // the CourseListItem shows them, e.g. 月1-2 and 3Q.
+    pub(super) fn push_condition<'a>(&'a self, query: &mut QueryBuilder<'a, Sqlite>) {
+        let (table, column) = match self.field {
+            RuleField::University => (None, "c.university"),
+            RuleField::Code => (None, "c.code"),
+            RuleField::Title => (None, "c.title"),
+            RuleField::Department => (None, "c.department"),
+            RuleField::Credit => (None, "CAST(c.credit AS TEXT)"),
+            RuleField::Lecturer => (Some("lecturers"), "name"),
+            RuleField::Timetable => (
+                Some("timetables"),
+                "substr('日月火水木金土', day + 1, 1) || (periods * 2 - 1) || '-' || (periods * 2)",
+            ),
+            RuleField::Semester => (Some("semesters"), "semester || 'Q'"),
+        };
+
+        if let Some(table) = table {
+            query.push(format!(
+                "EXISTS (SELECT 1 FROM {} WHERE course_id = c.id AND ",
+                table
+            ));
+        }
+        match self.match_kind {
+            RuleMatch::Contains => {
+                query.push(format!("instr({}, ", column));
+                query.push_bind(self.pattern.as_str());
+                query.push(") > 0");
+            }
+            RuleMatch::Equals => {
+                query.push(format!("{} = ", column));
+                query.push_bind(self.pattern.as_str());
+            }
+            RuleMatch::Glob => {
+                query.push(format!("{} GLOB ", column));
+                query.push_bind(self.pattern.as_str());
+            }
+        }
+        if table.is_some() {
+            query.push(")");
+        }
+    }
+}
+
+// Appends ` AND (NOT (exclude OR ...) OR include OR ...)` for the enabled rules.
+pub(super) fn push_rules<'a>(query: &mut QueryBuilder<'a, Sqlite>, rules: &'a [SearchRule]) {
+    let excludes: Vec<&SearchRule> = rules
+        .iter()
+        .filter(|rule| rule.action == RuleAction::Exclude)
+        .collect();
+    if excludes.is_empty() {
+        return;
+    }
+
+    query.push(" AND (NOT (");
+    for (i, rule) in excludes.into_iter().enumerate() {
+        if i > 0 {
+            query.push(" OR ");
+        }
+        rule.push_condition(query);
+    }
+    query.push(")");
+    push_includes(query, rules, " OR ");
+    query.push(")");
+}
+
+// Appends `{prefix}include OR ...` for the Include rules, if there are any.
+pub(super) fn push_includes<'a>(
+    query: &mut QueryBuilder<'a, Sqlite>,
+    rules: &'a [SearchRule],
+    prefix: &str,
+) {
+    for (i, rule) in rules
+        .iter()
+        .filter(|rule| rule.action == RuleAction::Include)
+        .enumerate()
+    {
+        query.push(if i == 0 { prefix } else { " OR " });
+        rule.push_condition(query);
+    }
+}
+
+pub async fn get_search_rules(pool: &SqlitePool) -> DbResult<Vec<SearchRule>> {
+    let rules = sqlx::query_as("SELECT * FROM search_rules ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rules)
+}
+
+pub(super) async fn get_enabled_search_rules(pool: &SqlitePool) -> DbResult<Vec<SearchRule>> {
+    let rules = sqlx::query_as("SELECT * FROM search_rules WHERE enabled ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rules)
+}
+
+pub async fn add_search_rule(pool: &SqlitePool, rule: &SearchRule) -> DbResult<i64> {
+    let result = sqlx::query(
+        "INSERT INTO search_rules (name, action, field, match_kind, pattern, enabled)
+        VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&rule.name)
+    .bind(rule.action)
+    .bind(rule.field)
+    .bind(rule.match_kind)
+    .bind(&rule.pattern)
+    .bind(rule.enabled)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn update_search_rule(pool: &SqlitePool, rule: &SearchRule) -> DbResult<()> {
+    let result = sqlx::query(
+        "UPDATE search_rules
+        SET name = ?, action = ?, field = ?, match_kind = ?, pattern = ?, enabled = ?
+        WHERE id = ?",
+    )
+    .bind(&rule.name)
+    .bind(rule.action)
+    .bind(rule.field)
+    .bind(rule.match_kind)
+    .bind(&rule.pattern)
+    .bind(rule.enabled)
+    .bind(rule.id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("search rule {}", rule.id)));
+    }
+    Ok(())
+}
+
+pub async fn delete_search_rule(pool: &SqlitePool, id: i64) -> DbResult<()> {
+    let result = sqlx::query("DELETE FROM search_rules WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("search rule {}", id)));
+    }
+    Ok(())
+}
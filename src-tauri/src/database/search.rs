@@ -0,0 +1,762 @@
+use super::rules::{self, HiddenCount, RuleAction, SearchRule};
+use super::{day_number, period_number, semester_number, DbResult};
+use crate::normalize::normalize;
+use crate::scrape::{Day, Period, Semester};
+use serde::{Deserialize, Serialize};
+use sqlx::{Encode, FromRow, QueryBuilder, Sqlite, SqlitePool, Type};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SearchQuery {
+    pub university: Vec<String>,
+    pub department: Vec<String>,
+    pub year: Vec<String>,
+    pub title: Vec<String>,
+    pub lecturer: Vec<String>,
+    pub grade: Vec<String>,
+    pub quarter: Vec<Semester>,
+    pub timetable: Vec<TimetableQuery>,
+    #[serde(default)]
+    pub timetable_mode: TimetableMode,
+    // free text looked up in the syllabus body: abstract, goals, keywords, lecture plan, textbooks;
+    // words in double quotes are looked up together as one phrase
+    #[serde(default)]
+    pub text: String,
+    // exact values as get_filter_options lists them; a course matches any of them
+    #[serde(default)]
+    pub language: Vec<String>,
+    #[serde(default)]
+    pub credit: Vec<i32>,
+    #[serde(default)]
+    pub lecture_type: Vec<String>,
+    #[serde(default)]
+    pub keyword: Vec<String>,
+    #[serde(default)]
+    pub competency: Vec<String>,
+    #[serde(default)]
+    pub credit_min: Option<i32>,
+    #[serde(default)]
+    pub credit_max: Option<i32>,
+    // a course has to match at least one query of any_of and none of none_of;
+    // only their filters count, not their paging or sort
+    #[serde(default)]
+    pub any_of: Vec<SearchQuery>,
+    #[serde(default)]
+    pub none_of: Vec<SearchQuery>,
+    // skip the search rules, to see everything they hide
+    #[serde(default)]
+    pub ignore_rules: bool,
+    // page of the results to return; no limit returns every match
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    // None sorts by relevance for a text search and in database order otherwise
+    #[serde(default)]
+    pub sort: Option<SortKey>,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TimetableQuery {
+    pub day: Day,
+    pub period: Period,
+}
+
+// How the (day, period) slots of `timetable` select courses.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum TimetableMode {
+    // at least one session is in one of the slots
+    #[default]
+    Any,
+    // every session is in one of the slots, e.g. the free slots of a timetable;
+    // courses without any session, such as intensive courses, do not match
+    Within,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum SortKey {
+    Code,
+    Title,
+    Credit,
+    Department,
+    LastUpdated,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct CourseListItem {
+    pub id: i32,
+    pub university: String,
+    pub code: String,
+    pub title: String,
+    pub lecturer: String,
+    pub timetable: String,
+    pub semester: String,
+    pub department: String,
+    pub credit: i32,
+    // where `text` matched, hits wrapped in <mark></mark>; empty without a text query
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub items: Vec<CourseListItem>,
+    // number of matches before offset and limit
+    pub total: i64,
+    pub facets: Facets,
+    // courses left out by each enabled Exclude rule
+    pub hidden: Vec<HiddenCount>,
+}
+
+// How many courses each value of a facet would match, counted under every filter
+// of the query except the facet's own, so choosing one department still shows
+// how many courses the other departments have.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Facets {
+    pub department: Vec<FacetCount>,
+    // "1Q" to "4Q"
+    pub quarter: Vec<FacetCount>,
+    // "月1-2", like the timetable of a CourseListItem
+    pub slot: Vec<FacetCount>,
+    // "100" to "600", like SearchQuery::grade
+    pub grade: Vec<FacetCount>,
+    pub credit: Vec<FacetCount>,
+    pub language: Vec<FacetCount>,
+    pub lecture_type: Vec<FacetCount>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Facet {
+    Department,
+    Quarter,
+    Slot,
+    Grade,
+    Credit,
+    Language,
+    LectureType,
+}
+
+impl Facet {
+    // (join, value, group) of the facet's count query; courses with several
+    // quarters or slots are counted once under each of them.
+    fn columns(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Facet::Department => ("", "c.department", "c.department"),
+            Facet::Quarter => (
+                " JOIN semesters AS fs ON fs.course_id = c.id",
+                "fs.semester || 'Q'",
+                "fs.semester",
+            ),
+            Facet::Slot => (
+                " JOIN timetables AS ft ON ft.course_id = c.id",
+                "substr('日月火水木金土', ft.day + 1, 1) || (ft.periods * 2 - 1) || '-' || (ft.periods * 2)",
+                "ft.day, ft.periods",
+            ),
+            Facet::Grade => ("", "substr(c.code, 6, 1) || '00'", "substr(c.code, 6, 1)"),
+            Facet::Credit => ("", "CAST(c.credit AS TEXT)", "c.credit"),
+            Facet::Language => ("", "COALESCE(c.language, '')", "COALESCE(c.language, '')"),
+            Facet::LectureType => (
+                "",
+                "COALESCE(c.lecture_type, '')",
+                "COALESCE(c.lecture_type, '')",
+            ),
+        }
+    }
+}
+
+// The columns of course_texts_normalized looked through for terms too short for it.
+const TEXT_COLUMNS: [&str; 6] = [
+    "title", "abstract", "goal", "keywords", "schedule", "textbook",
+];
+
+// Lecturers, timetables and semesters are joined as ", "-separated strings,
+// e.g. "月1-2, 木3-4" and "1Q, 2Q", in the order they were inserted.
+const COURSE_LIST_ITEM_COLUMNS: &str = "SELECT
+        c.id, c.university, c.code, c.title, c.department, c.credit,
+        COALESCE(l.lecturer, '') AS lecturer,
+        COALESCE(t.timetable, '') AS timetable,
+        COALESCE(s.semester, '') AS semester";
+
+const COURSE_LIST_ITEM_JOINS: &str = "
+    FROM courses AS c
+    LEFT JOIN (
+        SELECT course_id, group_concat(name, ', ') AS lecturer
+        FROM (SELECT course_id, name FROM lecturers ORDER BY id)
+        GROUP BY course_id
+    ) AS l ON l.course_id = c.id
+    LEFT JOIN (
+        SELECT course_id, group_concat(
+            substr('日月火水木金土', day + 1, 1) || (periods * 2 - 1) || '-' || (periods * 2),
+            ', '
+        ) AS timetable
+        FROM (SELECT course_id, day, periods FROM timetables ORDER BY id)
+        GROUP BY course_id
+    ) AS t ON t.course_id = c.id
+    LEFT JOIN (
+        SELECT course_id, group_concat(semester || 'Q', ', ') AS semester
+        FROM (SELECT course_id, semester FROM semesters ORDER BY id)
+        GROUP BY course_id
+    ) AS s ON s.course_id = c.id";
+
+// The values of a SearchQuery in the form the columns hold them, owned here so
+// the same filters can be bound into both the count and the page query.
+struct Filters<'a> {
+    query: &'a SearchQuery,
+    // the enabled search rules; only applied to the query as a whole
+    rules: &'a [SearchRule],
+    grades: Vec<&'static str>,
+    // (day, periods) as stored in timetables
+    slots: Vec<(i32, i32)>,
+    semesters: Vec<i32>,
+    titles: Vec<String>,
+    lecturers: Vec<String>,
+    text: Option<TextMatch>,
+    short_terms: Vec<String>,
+    any_of: Vec<Filters<'a>>,
+    none_of: Vec<Filters<'a>>,
+}
+
+// The terms of a text search: words split at whitespace, except that a phrase in
+// double quotes stays one term, as in `行列 "固有 値"`. A quote without a partner is
+// part of a word.
+fn text_terms(text: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = text.split('"').collect();
+    if parts.len().is_multiple_of(2) {
+        let last = parts.len() - 2;
+        let start = text.len() - parts[last].len() - parts[last + 1].len() - 1;
+        parts.truncate(last);
+        parts.push(&text[start..]);
+    }
+
+    let mut terms = Vec::new();
+    for (i, part) in parts.into_iter().enumerate() {
+        // odd parts sit between a pair of quotes
+        if i % 2 == 1 {
+            if !part.trim().is_empty() {
+                terms.push(part.trim());
+            }
+        } else {
+            terms.extend(part.split_whitespace());
+        }
+    }
+
+    terms
+}
+
+// Text terms of at least 3 characters go through the trigram index of
+// course_texts_normalized. The snippet comes from the original text in course_texts,
+// with terms that appear there verbatim wrapped in <mark>; it is empty when only the
+// normalized form matched.
+struct TextMatch {
+    normalized: String,
+    original: String,
+}
+
+impl<'a> Filters<'a> {
+    fn new(query: &'a SearchQuery, rules: &'a [SearchRule]) -> Self {
+        let (long, short): (Vec<&str>, Vec<&str>) = text_terms(&query.text)
+            .into_iter()
+            .partition(|term| normalize(term).chars().count() >= 3);
+
+        let phrases = |terms: Vec<String>, operator: &str| {
+            terms
+                .iter()
+                .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+                .collect::<Vec<String>>()
+                .join(operator)
+        };
+
+        Filters {
+            query,
+            rules,
+            // the 5th character of a course code is its level, e.g. MTH.A201 -> 2
+            grades: query
+                .grade
+                .iter()
+                .map(|s| match s.as_str() {
+                    "100" => "1",
+                    "200" => "2",
+                    "300" => "3",
+                    "400" => "4",
+                    "500" => "5",
+                    "600" => "6",
+                    _ => "0",
+                })
+                .collect(),
+            slots: query
+                .timetable
+                .iter()
+                .map(|t| (day_number(&t.day), period_number(&t.period)))
+                .collect(),
+            semesters: query.quarter.iter().map(semester_number).collect(),
+            titles: query.title.iter().map(|title| normalize(title)).collect(),
+            lecturers: query
+                .lecturer
+                .iter()
+                .map(|lecturer| normalize(lecturer))
+                .collect(),
+            text: (!long.is_empty()).then(|| TextMatch {
+                normalized: phrases(long.iter().map(|term| normalize(term)).collect(), " "),
+                original: phrases(long.iter().map(|term| term.to_string()).collect(), " OR "),
+            }),
+            short_terms: short.iter().map(|term| normalize(term)).collect(),
+            any_of: query.any_of.iter().map(|q| Filters::new(q, &[])).collect(),
+            none_of: query.none_of.iter().map(|q| Filters::new(q, &[])).collect(),
+        }
+    }
+
+    // FROM and WHERE of the search. `from` is "FROM courses AS c" plus any joins the
+    // selected columns need; a text search joins `f` (with rank) and, for snippets, `h`.
+    // The filter of `skip`, if any, is left out for counting that facet.
+    fn push_from_where(
+        &'a self,
+        query: &mut QueryBuilder<'a, Sqlite>,
+        from: &str,
+        snippet: bool,
+        skip: Option<Facet>,
+    ) {
+        query.push(from);
+        self.push_text_join(query, snippet);
+        query.push(" WHERE 1 = 1");
+        self.push_conditions(query, skip);
+        rules::push_rules(query, self.rules);
+    }
+
+    // The ids of the courses an any_of or none_of query matches.
+    fn push_subquery(&'a self, query: &mut QueryBuilder<'a, Sqlite>) {
+        query.push("SELECT c.id FROM courses AS c");
+        self.push_text_join(query, false);
+        query.push(" WHERE 1 = 1");
+        self.push_conditions(query, None);
+    }
+
+    fn push_text_join(&'a self, query: &mut QueryBuilder<'a, Sqlite>, snippet: bool) {
+        if let Some(text) = &self.text {
+            query.push(
+                " JOIN (
+                    SELECT rowid, rank FROM course_texts_normalized
+                    WHERE course_texts_normalized MATCH ",
+            );
+            query.push_bind(text.normalized.as_str());
+            query.push(") AS f ON f.rowid = c.id");
+
+            if snippet {
+                query.push(
+                    " LEFT JOIN (
+                        SELECT rowid, snippet(course_texts, -1, '<mark>', '</mark>', '…', 32) AS snippet
+                        FROM course_texts WHERE course_texts MATCH ",
+                );
+                query.push_bind(text.original.as_str());
+                query.push(") AS h ON h.rowid = c.id");
+            }
+        }
+    }
+
+    // The WHERE conditions of the query, each starting with " AND".
+    fn push_conditions(&'a self, query: &mut QueryBuilder<'a, Sqlite>, skip: Option<Facet>) {
+        for term in &self.short_terms {
+            // column by column, so a term cannot match across the end of one and the
+            // start of the next
+            query.push(" AND c.id IN (SELECT rowid FROM course_texts_normalized WHERE ");
+            let mut separated = query.separated(" OR ");
+            for column in TEXT_COLUMNS {
+                separated.push(format!("instr({}, ", column));
+                separated.push_bind_unseparated(term.as_str());
+                separated.push_unseparated(") > 0");
+            }
+            separated.push_unseparated(")");
+        }
+
+        push_in(query, "c.university", &self.query.university);
+        if skip != Some(Facet::Department) {
+            push_departments(query, &self.query.department);
+        }
+        push_in(query, "c.year", &self.query.year);
+        if skip != Some(Facet::Grade) {
+            push_in(query, "substr(c.code, 6, 1)", &self.grades);
+        }
+        push_contains_any(query, "c.title_normalized", &self.titles);
+
+        if !self.slots.is_empty() && skip != Some(Facet::Slot) {
+            match self.query.timetable_mode {
+                TimetableMode::Any => {
+                    query.push(" AND c.id IN (SELECT course_id FROM timetables WHERE ");
+                    push_slots(query, &self.slots, "IN");
+                    query.push(")");
+                }
+                TimetableMode::Within => {
+                    query.push(
+                        " AND c.id IN (SELECT course_id FROM timetables)
+                        AND c.id NOT IN (SELECT course_id FROM timetables WHERE ",
+                    );
+                    push_slots(query, &self.slots, "NOT IN");
+                    query.push(")");
+                }
+            }
+        }
+
+        if !self.semesters.is_empty() && skip != Some(Facet::Quarter) {
+            query.push(" AND c.id IN (SELECT course_id FROM semesters WHERE 1 = 1");
+            push_in(query, "semester", &self.semesters);
+            query.push(")");
+        }
+
+        if !self.lecturers.is_empty() {
+            query.push(" AND c.id IN (SELECT course_id FROM lecturers WHERE 1 = 1");
+            push_contains_any(query, "name_normalized", &self.lecturers);
+            query.push(")");
+        }
+
+        if skip != Some(Facet::Language) {
+            push_in(query, "c.language", &self.query.language);
+        }
+        if skip != Some(Facet::LectureType) {
+            push_in(query, "c.lecture_type", &self.query.lecture_type);
+        }
+
+        if !self.query.keyword.is_empty() {
+            query.push(" AND c.id IN (SELECT course_id FROM keywords WHERE 1 = 1");
+            push_in(query, "keyword", &self.query.keyword);
+            query.push(")");
+        }
+
+        if !self.query.competency.is_empty() {
+            query.push(" AND c.id IN (SELECT course_id FROM competencies WHERE 1 = 1");
+            push_in(query, "competency", &self.query.competency);
+            query.push(")");
+        }
+
+        if skip != Some(Facet::Credit) {
+            push_in(query, "c.credit", &self.query.credit);
+            if let Some(min) = self.query.credit_min {
+                query.push(" AND c.credit >= ");
+                query.push_bind(min);
+            }
+            if let Some(max) = self.query.credit_max {
+                query.push(" AND c.credit <= ");
+                query.push_bind(max);
+            }
+        }
+
+        if !self.any_of.is_empty() {
+            query.push(" AND (");
+            for (i, filters) in self.any_of.iter().enumerate() {
+                query.push(if i == 0 { "c.id IN (" } else { " OR c.id IN (" });
+                filters.push_subquery(query);
+                query.push(")");
+            }
+            query.push(")");
+        }
+
+        for filters in &self.none_of {
+            query.push(" AND c.id NOT IN (");
+            filters.push_subquery(query);
+            query.push(")");
+        }
+    }
+
+    fn push_order_by(&self, query: &mut QueryBuilder<'a, Sqlite>) {
+        let column = match self.query.sort {
+            Some(SortKey::Code) => "c.code",
+            Some(SortKey::Title) => "c.title",
+            Some(SortKey::Credit) => "c.credit",
+            Some(SortKey::Department) => "c.department",
+            // sylbs_update is a yyyy/mm/dd string, so it sorts as text
+            Some(SortKey::LastUpdated) => "c.sylbs_update",
+            None if self.text.is_some() => "f.rank",
+            None => "c.id",
+        };
+        let direction = match self.query.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+
+        query.push(format!(" ORDER BY {} {}, c.id", column, direction));
+    }
+}
+
+// Appends ` AND column IN (?, ?, ...)` with every value bound as a parameter.
+// Nothing is appended for an empty list, which means "no filter".
+fn push_in<'a, T>(query: &mut QueryBuilder<'a, Sqlite>, column: &str, values: &'a [T])
+where
+    &'a T: Encode<'a, Sqlite> + Type<Sqlite> + Send,
+{
+    if values.is_empty() {
+        return;
+    }
+
+    query.push(format!(" AND {} IN (", column));
+    let mut separated = query.separated(", ");
+    for value in values {
+        separated.push_bind(value);
+    }
+    separated.push_unseparated(")");
+}
+
+// Appends the department filter. A course of a chosen department or of any
+// department below it in the departments table matches, so 理学院 takes in 数学系 and
+// 数学コース; names the table does not know still match courses by themselves.
+fn push_departments<'a>(query: &mut QueryBuilder<'a, Sqlite>, names: &'a [String]) {
+    if names.is_empty() {
+        return;
+    }
+
+    query.push(" AND (c.department IN (");
+    let mut separated = query.separated(", ");
+    for name in names {
+        separated.push_bind(name);
+    }
+    query.push(
+        ") OR c.department IN (
+            WITH RECURSIVE below(id, name) AS (
+                SELECT id, name FROM departments WHERE name IN (",
+    );
+    let mut separated = query.separated(", ");
+    for name in names {
+        separated.push_bind(name);
+    }
+    query.push(
+        ")
+                UNION
+                SELECT d.id, d.name FROM departments AS d JOIN below ON d.parent_id = below.id
+            )
+            SELECT name FROM below
+        ))",
+    );
+}
+
+// Appends `(day, periods) IN (VALUES (?, ?), ...)`, so each day is matched with
+// its own period rather than with any of the chosen periods.
+fn push_slots(query: &mut QueryBuilder<'_, Sqlite>, slots: &[(i32, i32)], operator: &str) {
+    query.push(format!("(day, periods) {} (VALUES ", operator));
+    let mut separated = query.separated(", ");
+    for (day, periods) in slots {
+        separated.push("(");
+        separated.push_bind_unseparated(*day);
+        separated.push_unseparated(", ");
+        separated.push_bind_unseparated(*periods);
+        separated.push_unseparated(")");
+    }
+    separated.push_unseparated(")");
+}
+
+// Appends ` AND (instr(column, ?) > 0 OR ...)`, i.e. the column contains any of the values.
+fn push_contains_any<'a>(query: &mut QueryBuilder<'a, Sqlite>, column: &str, values: &'a [String]) {
+    if values.is_empty() {
+        return;
+    }
+
+    query.push(" AND (");
+    let mut separated = query.separated(" OR ");
+    for value in values {
+        separated.push(format!("instr({}, ", column));
+        separated.push_bind_unseparated(value);
+        separated.push_unseparated(") > 0");
+    }
+    separated.push_unseparated(")");
+}
+
+// Counts the courses the query matches but `rule` hides.
+async fn count_hidden(
+    pool: &SqlitePool,
+    filters: &Filters<'_>,
+    rule: &SearchRule,
+) -> DbResult<i64> {
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM courses AS c");
+    filters.push_text_join(&mut query, false);
+    query.push(" WHERE 1 = 1");
+    filters.push_conditions(&mut query, None);
+    query.push(" AND ");
+    rule.push_condition(&mut query);
+    if filters
+        .rules
+        .iter()
+        .any(|rule| rule.action == RuleAction::Include)
+    {
+        query.push(" AND NOT (");
+        rules::push_includes(&mut query, filters.rules, "");
+        query.push(")");
+    }
+
+    let count = query.build_query_scalar::<i64>().fetch_one(pool).await?;
+
+    Ok(count)
+}
+
+async fn count_facet(
+    pool: &SqlitePool,
+    filters: &Filters<'_>,
+    facet: Facet,
+) -> DbResult<Vec<FacetCount>> {
+    let (join, value, group) = facet.columns();
+
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} AS value, COUNT(DISTINCT c.id) AS count",
+        value
+    ));
+    filters.push_from_where(
+        &mut query,
+        &format!(" FROM courses AS c{}", join),
+        false,
+        Some(facet),
+    );
+    query.push(format!(" GROUP BY {} ORDER BY {}", group, group));
+
+    let counts = query.build_query_as::<FacetCount>().fetch_all(pool).await?;
+
+    Ok(counts)
+}
+
+// The page query of a search, before its LIMIT.
+fn items_query<'a>(filters: &'a Filters<'a>) -> QueryBuilder<'a, Sqlite> {
+    let mut query = QueryBuilder::<Sqlite>::new(COURSE_LIST_ITEM_COLUMNS);
+    query.push(if filters.text.is_some() {
+        ", COALESCE(h.snippet, '') AS snippet"
+    } else {
+        ", '' AS snippet"
+    });
+    filters.push_from_where(&mut query, COURSE_LIST_ITEM_JOINS, true, None);
+    filters.push_order_by(&mut query);
+
+    query
+}
+
+// Every course the query matches, in its order, without the paging, total and
+// facets of search_courses.
+pub(super) async fn find_courses(
+    pool: &SqlitePool,
+    search_query: &SearchQuery,
+) -> DbResult<Vec<CourseListItem>> {
+    let rules = if search_query.ignore_rules {
+        Vec::new()
+    } else {
+        rules::get_enabled_search_rules(pool).await?
+    };
+    let filters = Filters::new(search_query, &rules);
+
+    let items = items_query(&filters)
+        .build_query_as::<CourseListItem>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(items)
+}
+
+pub async fn search_courses(
+    pool: &SqlitePool,
+    search_query: SearchQuery,
+) -> DbResult<SearchResponse> {
+    let rules = if search_query.ignore_rules {
+        Vec::new()
+    } else {
+        rules::get_enabled_search_rules(pool).await?
+    };
+    let filters = Filters::new(&search_query, &rules);
+
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*)");
+    filters.push_from_where(&mut count, " FROM courses AS c", false, None);
+    let total = count.build_query_scalar::<i64>().fetch_one(pool).await?;
+
+    let mut query = items_query(&filters);
+    match search_query.limit {
+        Some(limit) => {
+            query.push(" LIMIT ");
+            query.push_bind(limit);
+            query.push(" OFFSET ");
+            query.push_bind(search_query.offset);
+        }
+        None if search_query.offset > 0 => {
+            query.push(" LIMIT -1 OFFSET ");
+            query.push_bind(search_query.offset);
+        }
+        None => {}
+    }
+
+    let items = query
+        .build_query_as::<CourseListItem>()
+        .fetch_all(pool)
+        .await?;
+
+    let facets = Facets {
+        department: count_facet(pool, &filters, Facet::Department).await?,
+        quarter: count_facet(pool, &filters, Facet::Quarter).await?,
+        slot: count_facet(pool, &filters, Facet::Slot).await?,
+        grade: count_facet(pool, &filters, Facet::Grade).await?,
+        credit: count_facet(pool, &filters, Facet::Credit).await?,
+        language: count_facet(pool, &filters, Facet::Language).await?,
+        lecture_type: count_facet(pool, &filters, Facet::LectureType).await?,
+    };
+
+    let mut hidden = Vec::new();
+    for rule in rules
+        .iter()
+        .filter(|rule| rule.action == RuleAction::Exclude)
+    {
+        hidden.push(HiddenCount {
+            rule_id: rule.id,
+            name: rule.name.clone(),
+            count: count_hidden(pool, &filters, rule).await?,
+        });
+    }
+
+    Ok(SearchResponse {
+        items,
+        total,
+        facets,
+        hidden,
+    })
+}
+
+// The distinct values stored for each filter, for the menus of the search form.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterOptions {
+    pub university: Vec<String>,
+    pub department: Vec<String>,
+    pub year: Vec<i32>,
+    pub language: Vec<String>,
+    pub credit: Vec<i32>,
+    pub lecture_type: Vec<String>,
+    pub keyword: Vec<String>,
+    pub competency: Vec<String>,
+}
+
+pub async fn get_filter_options(pool: &SqlitePool) -> DbResult<FilterOptions> {
+    // column and table are constants; empty strings are what a missing field is stored as
+    async fn distinct<T>(pool: &SqlitePool, column: &str, table: &str) -> DbResult<Vec<T>>
+    where
+        T: for<'r> sqlx::Decode<'r, Sqlite> + Type<Sqlite> + Send + Unpin,
+    {
+        let sql = format!(
+            "SELECT DISTINCT {column} FROM {table}
+            WHERE {column} IS NOT NULL AND {column} != ''
+            ORDER BY {column}"
+        );
+        Ok(sqlx::query_scalar(&sql).fetch_all(pool).await?)
+    }
+
+    Ok(FilterOptions {
+        university: distinct(pool, "university", "courses").await?,
+        department: distinct(pool, "department", "courses").await?,
+        year: distinct(pool, "year", "courses").await?,
+        language: distinct(pool, "language", "courses").await?,
+        credit: distinct(pool, "credit", "courses").await?,
+        lecture_type: distinct(pool, "lecture_type", "courses").await?,
+        keyword: distinct(pool, "keyword", "keywords").await?,
+        competency: distinct(pool, "competency", "competencies").await?,
+    })
+}
@@ -0,0 +1,236 @@
+use super::{get_courses, insert_course_in, replace_course_in, DbResult};
+use crate::error::AppError;
+use crate::Course;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{FromRow, SqlitePool};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// What a snapshot file says about itself.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotMeta {
+    // where the courses were crawled from, e.g. the OCW base URL
+    pub source: String,
+    // the latest year of the courses inside; None for an empty snapshot
+    pub year: Option<i32>,
+    // seconds since the Unix epoch
+    pub created_at: i64,
+    // the last migration applied to the snapshot's schema
+    pub schema_version: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotReport {
+    pub snapshot: SnapshotMeta,
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+async fn schema_version(pool: &SqlitePool) -> DbResult<i64> {
+    let version =
+        sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success")
+            .fetch_one(pool)
+            .await?;
+
+    Ok(version)
+}
+
+async fn open(path: &Path, read_only: bool) -> DbResult<SqlitePool> {
+    if !path.exists() {
+        return Err(AppError::NotFound(format!("snapshot {}", path.display())));
+    }
+
+    // a bundled snapshot may sit in a read-only directory, where SQLite cannot
+    // create its journal next to it
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(read_only)
+        .immutable(read_only);
+    let options = if read_only {
+        options
+    } else {
+        options.journal_mode(SqliteJournalMode::Delete)
+    };
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+
+    Ok(pool)
+}
+
+async fn read_meta(snapshot: &SqlitePool, path: &Path) -> DbResult<SnapshotMeta> {
+    let meta = sqlx::query_as(
+        "SELECT source, year, created_at, schema_version FROM snapshots
+        WHERE imported_at IS NULL ORDER BY id DESC LIMIT 1",
+    )
+    .fetch_optional(snapshot)
+    .await?;
+
+    meta.ok_or_else(|| AppError::Parse(format!("{} is not a snapshot", path.display())))
+}
+
+pub async fn get_snapshot_meta(path: &Path) -> DbResult<SnapshotMeta> {
+    let snapshot = open(path, true).await?;
+    let meta = read_meta(&snapshot, path).await;
+    snapshot.close().await;
+
+    meta
+}
+
+// Writes the crawled courses to a new snapshot file at `path`, replacing any file
+// there. Archived pages, search rules and imported snapshots stay behind.
+pub async fn create_snapshot(
+    pool: &SqlitePool,
+    path: &Path,
+    source: &str,
+) -> DbResult<SnapshotMeta> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let target = path
+        .to_str()
+        .ok_or_else(|| AppError::Config(format!("not a UTF-8 path: {}", path.display())))?;
+    sqlx::query("VACUUM INTO ?")
+        .bind(target)
+        .execute(pool)
+        .await?;
+
+    let snapshot = open(path, false).await?;
+    let meta = SnapshotMeta {
+        source: source.to_string(),
+        year: sqlx::query_scalar("SELECT MAX(year) FROM courses")
+            .fetch_one(&snapshot)
+            .await?,
+        created_at: now(),
+        schema_version: schema_version(&snapshot).await?,
+    };
+
+    for table in ["archived_pages", "search_rules", "snapshots"] {
+        sqlx::query(&format!("DELETE FROM {}", table))
+            .execute(&snapshot)
+            .await?;
+    }
+    sqlx::query(
+        "INSERT INTO snapshots (source, year, created_at, schema_version) VALUES (?, ?, ?, ?)",
+    )
+    .bind(&meta.source)
+    .bind(meta.year)
+    .bind(meta.created_at)
+    .bind(meta.schema_version)
+    .execute(&snapshot)
+    .await?;
+    sqlx::query("VACUUM").execute(&snapshot).await?;
+    snapshot.close().await;
+
+    Ok(meta)
+}
+
+// Merges a snapshot into the database in one transaction. A course the database
+// lacks is added, one whose snapshot copy has a later sylbs_update replaces the
+// stored one, and the rest are left as they are. Only courses are read from the
+// snapshot, so user data such as search rules is never touched.
+pub async fn import_snapshot(pool: &SqlitePool, path: &Path) -> DbResult<SnapshotReport> {
+    let snapshot = open(path, true).await?;
+    let result = merge(pool, &snapshot, path).await;
+    snapshot.close().await;
+
+    result
+}
+
+async fn merge(pool: &SqlitePool, snapshot: &SqlitePool, path: &Path) -> DbResult<SnapshotReport> {
+    let meta = read_meta(snapshot, path).await?;
+    let version = schema_version(pool).await?;
+    if meta.schema_version > version {
+        return Err(AppError::Config(format!(
+            "the snapshot has schema version {}, newer than this app's {}",
+            meta.schema_version, version
+        )));
+    }
+
+    let mut report = SnapshotReport {
+        snapshot: meta,
+        added: 0,
+        updated: 0,
+        unchanged: 0,
+    };
+
+    // read a few hundred courses at a time rather than the whole snapshot at once
+    let ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM courses ORDER BY id")
+        .fetch_all(snapshot)
+        .await?;
+    let mut tx = pool.begin().await?;
+    for chunk in ids.chunks(500) {
+        for course in get_courses(snapshot, chunk).await? {
+            let course = Course::try_from(course)?;
+
+            let stored: Option<Option<String>> = sqlx::query_scalar(
+                "SELECT sylbs_update FROM courses WHERE code = ? AND year = ? AND title = ?",
+            )
+            .bind(&course.code)
+            .bind(course.year)
+            .bind(&course.title)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            match stored {
+                None => {
+                    insert_course_in(&mut tx, &course).await?;
+                    report.added += 1;
+                }
+                // the dates are written as 2024/03/14, so they sort as strings
+                Some(stored)
+                    if stored.as_deref().unwrap_or_default() < course.sylbs_update.as_str() =>
+                {
+                    replace_course_in(&mut tx, &course).await?;
+                    report.updated += 1;
+                }
+                Some(_) => report.unchanged += 1,
+            }
+        }
+    }
+
+    sqlx::query(
+        "INSERT INTO snapshots (source, year, created_at, schema_version, imported_at)
+        VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&report.snapshot.source)
+    .bind(report.snapshot.year)
+    .bind(report.snapshot.created_at)
+    .bind(report.snapshot.schema_version)
+    .bind(now())
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(report)
+}
+
+// Fills a database without any courses from the snapshot at `path`, as on the first
+// launch with the snapshot bundled as a resource. Does nothing when the database
+// already has courses or there is no snapshot.
+pub async fn seed_from_snapshot(
+    pool: &SqlitePool,
+    path: &Path,
+) -> DbResult<Option<SnapshotReport>> {
+    let courses: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM courses")
+        .fetch_one(pool)
+        .await?;
+    if courses > 0 || !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(import_snapshot(pool, path).await?))
+}
@@ -0,0 +1,129 @@
+use crate::query::SyntaxError;
+use crate::scrape::ParseError;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+// The error every command and database function returns. The frontend receives it
+// as `{ "kind": "NotFound", "message": "..." }`, where `kind` is the variant name
+// and `message` is the same text Display prints.
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    Database(String),
+    Network(String),
+    // reading or writing a file such as an export, a snapshot or an archived page
+    File(String),
+    Parse(String),
+    // the user stopped a running fetch
+    Cancelled,
+    Config(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "NotFound",
+            AppError::Database(_) => "Database",
+            AppError::Network(_) => "Network",
+            AppError::File(_) => "File",
+            AppError::Parse(_) => "Parse",
+            AppError::Cancelled => "Cancelled",
+            AppError::Config(_) => "Config",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(message) => write!(f, "not found: {}", message),
+            AppError::Database(message) => write!(f, "database error: {}", message),
+            AppError::Network(message) => write!(f, "network error: {}", message),
+            AppError::File(message) => write!(f, "file error: {}", message),
+            AppError::Parse(message) => write!(f, "parse error: {}", message),
+            AppError::Cancelled => write!(f, "cancelled"),
+            AppError::Config(message) => write!(f, "config error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => AppError::NotFound(e.to_string()),
+            e => AppError::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(e: sqlx::migrate::MigrateError) -> Self {
+        AppError::Database(e.to_string())
+    }
+}
+
+// Settings map their own io errors to Config.
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound(e.to_string()),
+            _ => AppError::File(e.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::Network(e.to_string())
+    }
+}
+
+impl From<ParseError> for AppError {
+    fn from(e: ParseError) -> Self {
+        AppError::Parse(e.0)
+    }
+}
+
+impl From<SyntaxError> for AppError {
+    fn from(e: SyntaxError) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for AppError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for AppError {
+    fn from(e: toml::de::Error) -> Self {
+        AppError::Config(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for AppError {
+    fn from(e: toml::ser::Error) -> Self {
+        AppError::Config(e.to_string())
+    }
+}
@@ -0,0 +1,156 @@
+use crate::database::{self, DbResult};
+use crate::scrape::{html_to_course, html_to_course_abstracts};
+use regex::Regex;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub list_pages: usize,
+    pub detail_pages: usize,
+    pub imported: usize,
+    pub up_to_date: usize,
+    // detail pages that matched no list row; imported without sylbs_update
+    pub unmatched: Vec<String>,
+    // files that are neither a list page nor a detail page
+    pub skipped: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+struct ListRow {
+    url: String,
+    code: String,
+    title: String,
+    sylbs_update: String,
+}
+
+// Imports list (T0100) and detail (T0300) pages saved from OCW under `dir`.
+// Details are matched to list rows by the "saved from url" comment browsers add,
+// and by course code (and title when the code is ambiguous) otherwise.
+pub async fn import_html(
+    pool: &SqlitePool,
+    dir: &Path,
+    base_url: &str,
+    progress: impl Fn(String),
+) -> DbResult<ImportReport> {
+    let mut report = ImportReport::default();
+
+    let mut files = Vec::new();
+    collect_html_files(dir, &mut files)?;
+    files.sort();
+
+    let mut rows = Vec::new();
+    let mut details = Vec::new();
+
+    for file in files {
+        let name = file.to_string_lossy().to_string();
+        let html = String::from_utf8_lossy(&std::fs::read(&file)?).to_string();
+
+        if html.contains("ranking-list") {
+            report.list_pages += 1;
+            match html_to_course_abstracts(&html, base_url) {
+                Ok(courses) => {
+                    database::record_departments(pool, &courses).await?;
+                    rows.extend(courses.into_iter().map(|course| ListRow {
+                        url: course.title.url,
+                        code: course.code,
+                        title: course.title.title,
+                        sylbs_update: course.sylbs_update,
+                    }))
+                }
+                Err(e) => report.failed.push(format!("{}: {}", name, e)),
+            }
+        } else if html.contains("gaiyo-data") {
+            report.detail_pages += 1;
+            details.push((name, html));
+        } else {
+            report.skipped.push(name);
+        }
+    }
+
+    let mut by_url = HashMap::new();
+    let mut by_code: HashMap<&str, Vec<&ListRow>> = HashMap::new();
+    for row in &rows {
+        by_url.insert(row.url.as_str(), row);
+        by_code.entry(row.code.as_str()).or_default().push(row);
+    }
+
+    let saved_from_re = Regex::new(r"<!-- saved from url=\(\d+\)(\S+) -->").unwrap();
+    let length = details.len();
+
+    for (now, (name, html)) in details.into_iter().enumerate() {
+        progress(format!("Importing: {}/{} pages: {}", now + 1, length, name));
+
+        let mut detail = match html_to_course(&html, base_url) {
+            Ok(detail) => detail,
+            Err(e) => {
+                report.failed.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        let saved_from = saved_from_re
+            .captures(&html)
+            .map(|caps| caps.get(1).unwrap().as_str().replace("&amp;", "&"));
+
+        let row = saved_from
+            .as_deref()
+            .and_then(|url| by_url.get(url).copied())
+            .or_else(|| match by_code.get(detail.code.as_str()) {
+                Some(candidates) if candidates.len() == 1 => Some(candidates[0]),
+                Some(candidates) => candidates
+                    .iter()
+                    .find(|row| row.title == detail.title)
+                    .copied(),
+                None => None,
+            });
+
+        match row {
+            Some(row) => {
+                detail.url = row.url.clone();
+                detail.sylbs_update = row.sylbs_update.clone();
+
+                if database::check_sylbs_update(
+                    pool,
+                    &detail.code,
+                    Some(detail.year),
+                    &detail.title,
+                    &detail.sylbs_update,
+                )
+                .await?
+                {
+                    report.up_to_date += 1;
+                    continue;
+                }
+            }
+            None => {
+                detail.url = saved_from.unwrap_or_default();
+                report.unmatched.push(name);
+            }
+        }
+
+        database::replace_course(pool, &detail).await?;
+        report.imported += 1;
+    }
+
+    Ok(report)
+}
+
+fn collect_html_files(dir: &Path, files: &mut Vec<PathBuf>) -> DbResult<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_html_files(&path, files)?;
+        } else if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("html" | "htm")
+        ) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -1,16 +1,34 @@
-use crate::scrape::{html_to_course, html_to_course_abstracts};
-use sqlx::sqlite::SqliteError;
+use crate::scrape::{Day, Period, Semester};
+use serde::Serialize;
 use sqlx::SqlitePool;
-use std::process::Command;
-use std::time;
+use std::path::PathBuf;
+use std::sync::RwLock;
 use tauri::async_runtime::block_on;
 use tauri::path::BaseDirectory;
 use tauri::{Emitter, Manager, State};
 
-mod database;
-mod scrape;
-
-use crate::database::{CourseListItem, CourseResponse, SearchQuery};
+pub mod archive;
+mod cli;
+pub mod crawl;
+pub mod database;
+pub mod error;
+pub mod import;
+pub mod normalize;
+pub mod query;
+pub mod scrape;
+pub mod settings;
+
+use crate::archive::{Archive, ReparseReport};
+use crate::crawl::{CancelFlag, Crawler, RefreshTarget};
+use crate::database::{
+    Campus, CompactReport, CourseGraph, CourseResponse, DatabaseStats, DepartmentNode, Direction,
+    ExportFormat, FilterOptions, GraphFormat, GridAxis, IntegrityReport, ResetReport, Room,
+    RoomOccupancy, SearchQuery, SearchResponse, SearchRule, SnapshotMeta, SnapshotReport,
+    TimetableGrid,
+};
+use crate::error::AppError;
+use crate::import::ImportReport;
+use crate::settings::Settings;
 pub use scrape::Course;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -19,206 +37,483 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+// Progress messages are best effort: a closed window must not abort the work itself.
+fn emit_status<S: Serialize + Clone>(app: &tauri::AppHandle, status: S) {
+    let _ = app.emit("fetch_status", status);
+}
+
+fn crawler<'a>(
+    sqlite_pool: &'a SqlitePool,
+    archive: &'a Archive,
+    settings: &'a Settings,
+    app: &'a tauri::AppHandle,
+) -> Result<Crawler<'a, impl Fn(String) + Send + Sync + 'a>, AppError> {
+    Crawler::new(sqlite_pool, archive, settings, move |status: String| {
+        emit_status(app, status);
+    })
+}
+
 #[tauri::command]
-fn fetch_test(sqlite_pool: State<'_, SqlitePool>, app: State<'_, tauri::AppHandle>) {
-    println!("fetch_test");
+async fn fetch(
+    sqlite_pool: State<'_, SqlitePool>,
+    archive: State<'_, Archive>,
+    settings: State<'_, RwLock<Settings>>,
+    cancel: State<'_, CancelFlag>,
+    app: State<'_, tauri::AppHandle>,
+) -> Result<(), AppError> {
+    emit_status(&app, "Start Fetching");
+
+    let settings = settings.read().unwrap().clone();
+    let departments = settings.gakubu_cds();
+    cancel.reset();
+    crawler(&sqlite_pool, &archive, &settings, &app)?
+        .with_cancel(&cancel)
+        .crawl_departments(&departments)
+        .await
+}
 
-    (*app).emit("fetch_status", "test").unwrap();
+#[tauri::command]
+async fn fetch_departments(
+    sqlite_pool: State<'_, SqlitePool>,
+    archive: State<'_, Archive>,
+    settings: State<'_, RwLock<Settings>>,
+    cancel: State<'_, CancelFlag>,
+    app: State<'_, tauri::AppHandle>,
+    departments: Vec<u32>,
+) -> Result<(), AppError> {
+    emit_status(&app, "Start Fetching");
+
+    let settings = settings.read().unwrap().clone();
+    cancel.reset();
+    crawler(&sqlite_pool, &archive, &settings, &app)?
+        .with_cancel(&cancel)
+        .crawl_departments(&departments)
+        .await
+}
 
-    let url =
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=1&lang=JA";
-    let rc = reqwest::blocking::get(url).unwrap();
-    let res = rc.text().unwrap();
+// Stops a running fetch before its next page; the fetch then fails with Cancelled.
+#[tauri::command]
+fn cancel_fetch(cancel: State<'_, CancelFlag>) {
+    cancel.cancel();
+}
 
-    // let res = sample::get_sample_main();
+#[tauri::command]
+async fn refresh_course(
+    sqlite_pool: State<'_, SqlitePool>,
+    archive: State<'_, Archive>,
+    settings: State<'_, RwLock<Settings>>,
+    app: State<'_, tauri::AppHandle>,
+    target: RefreshTarget,
+) -> Result<CourseResponse, AppError> {
+    let settings = settings.read().unwrap().clone();
+    let id = crawler(&sqlite_pool, &archive, &settings, &app)?
+        .refresh_course(&target)
+        .await?;
+
+    database::get_course(&sqlite_pool, id as i32).await
+}
 
-    let courses = html_to_course_abstracts(res.as_ref());
+#[tauri::command]
+async fn reparse_archive(
+    sqlite_pool: State<'_, SqlitePool>,
+    archive: State<'_, Archive>,
+    settings: State<'_, RwLock<Settings>>,
+    app: State<'_, tauri::AppHandle>,
+) -> Result<ReparseReport, AppError> {
+    let base_url = settings.read().unwrap().base_url.clone();
+    let report = archive
+        .reparse(&sqlite_pool, &base_url, |status| emit_status(&app, status))
+        .await?;
 
-    println!("{:?}", courses.len());
-    println!("{:?}", courses[1]);
+    emit_status(&app, "finish reparse");
 
-    //let detail = sample::get_sample_sub();
-    //let detail = html_to_course((&detail).as_ref());
+    Ok(report)
+}
 
-    let check = block_on(database::check_sylbs_update(
-        &*sqlite_pool,
-        &courses[1].code,
-        &courses[1].title.title,
-        &courses[1].sylbs_update,
-    ))
-    .unwrap();
+#[tauri::command]
+async fn import_html(
+    sqlite_pool: State<'_, SqlitePool>,
+    settings: State<'_, RwLock<Settings>>,
+    app: State<'_, tauri::AppHandle>,
+    dir: String,
+) -> Result<ImportReport, AppError> {
+    let base_url = settings.read().unwrap().base_url.clone();
+    let report = import::import_html(
+        &sqlite_pool,
+        std::path::Path::new(&dir),
+        &base_url,
+        |status| emit_status(&app, status),
+    )
+    .await?;
+
+    emit_status(&app, "finish import");
+
+    Ok(report)
+}
 
-    if check {
-        (*app).emit("fetch_status", "already fetched").unwrap();
-        return;
-    }
+#[tauri::command]
+async fn search_courses(
+    sqlite_pool: State<'_, SqlitePool>,
+    search_query: SearchQuery,
+) -> Result<SearchResponse, AppError> {
+    database::search_courses(&*sqlite_pool, search_query).await
+}
 
-    let rc = reqwest::blocking::get(courses[1].title.url.as_str()).unwrap();
-    let mut detail = html_to_course(rc.text().unwrap().as_ref());
-    detail.url = courses[1].title.url.clone();
-    detail.sylbs_update = courses[1].sylbs_update.clone();
+// Writes every course, or the ones search_query finds, to `path`.
+#[tauri::command]
+async fn export_courses(
+    sqlite_pool: State<'_, SqlitePool>,
+    search_query: Option<SearchQuery>,
+    format: ExportFormat,
+    path: String,
+) -> Result<(), AppError> {
+    let output = database::export_courses(&sqlite_pool, search_query, format).await?;
+    std::fs::write(path, output)?;
 
-    println!("{:?}", detail);
+    Ok(())
+}
 
-    (*app).emit("fetch_status", "finish scrape").unwrap();
+// Reads back a JSON file written by export_courses. Returns how many courses it stored.
+#[tauri::command]
+async fn import_courses(
+    sqlite_pool: State<'_, SqlitePool>,
+    path: String,
+) -> Result<usize, AppError> {
+    let json = std::fs::read_to_string(path)?;
 
-    block_on(database::insert_course(&sqlite_pool, &detail)).unwrap();
+    database::import_courses(&sqlite_pool, &json).await
+}
 
-    (*app).emit("fetch_status", "finish insert").unwrap();
+// Merges a newer snapshot into the database, keeping search rules and other user data.
+#[tauri::command]
+async fn import_snapshot(
+    sqlite_pool: State<'_, SqlitePool>,
+    path: String,
+) -> Result<SnapshotReport, AppError> {
+    database::import_snapshot(&sqlite_pool, std::path::Path::new(&path)).await
 }
 
 #[tauri::command]
-async fn fetch(
+async fn create_snapshot(
     sqlite_pool: State<'_, SqlitePool>,
-    app: State<'_, tauri::AppHandle>,
-) -> Result<(), ()> {
-    println!("fetch");
-
-    (*app).emit("fetch_status", "Start Fetching").unwrap();
-
-    let urls = vec![
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=1&lang=JA", //理学院
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=4&lang=JA", // 情報
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=2&lang=JA", //工学院
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=3&lang=JA", // 物質
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=5&lang=JA", // 生命
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=6&lang=JA", // 環社
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=10&lang=JA", // 初年専門
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=11&lang=JA", // 共通
-        "https://www.ocw.titech.ac.jp/index.php?module=General&action=T0100&GakubuCD=7&lang=JA", // 教養
-    ];
-
-    for url in urls {
-        (*app)
-            .emit("fetch_status", "Fetching Main".to_string())
-            .unwrap();
-        let rc = reqwest::get(url).await.unwrap();
-        let res = rc.text().await.unwrap();
-        tokio::time::sleep(time::Duration::from_secs(10)).await;
-
-        let courses = html_to_course_abstracts(res.as_ref());
-
-        let length = courses.len();
-        let mut now = 0;
-
-        (*app)
-            .emit("fetch_status", format!("Left: {} courses", length))
-            .unwrap();
-
-        for course in courses {
-            now += 1;
-            (*app)
-                .emit(
-                    "fetch_status",
-                    format!(
-                        "Scraping: {}/{} courses: {} {}",
-                        now, length, course.department, course.title.title
-                    ),
-                )
-                .unwrap();
-
-            println!(
-                "Scraping: {}/{} courses: {}",
-                now, length, course.title.title
-            );
-
-            if course.code.is_empty() {
-                continue;
-            }
+    settings: State<'_, RwLock<Settings>>,
+    path: String,
+) -> Result<SnapshotMeta, AppError> {
+    let source = settings.read().unwrap().base_url.clone();
 
-            let check = database::check_sylbs_update(
-                &sqlite_pool,
-                &course.code,
-                &course.title.title,
-                &course.sylbs_update,
-            )
-            .await
-            .unwrap();
-
-            if check {
-                continue;
-            }
+    database::create_snapshot(&sqlite_pool, std::path::Path::new(&path), &source).await
+}
 
-            let rc = reqwest::get(course.title.url.as_str()).await.unwrap();
-            let mut detail = html_to_course(rc.text().await.unwrap().as_ref());
-            detail.url = course.title.url;
-            detail.sylbs_update = course.sylbs_update;
-            tokio::time::sleep(time::Duration::from_secs(10)).await;
-
-            database::insert_course(&sqlite_pool, &detail)
-                .await
-                .unwrap();
-
-            (*app)
-                .emit(
-                    "fetch_status",
-                    format!("Finished: {}/{} courses: {}", now, length, detail.title),
-                )
-                .unwrap();
-        }
+// The courses up to `depth` prerequisite or related-course links before or after `code`.
+#[tauri::command]
+async fn get_course_relations(
+    sqlite_pool: State<'_, SqlitePool>,
+    code: String,
+    direction: Direction,
+    depth: u32,
+) -> Result<CourseGraph, AppError> {
+    database::get_course_relations(&sqlite_pool, &code, direction, depth).await
+}
+
+// Writes the whole course graph, or the part around `code`, to `path`.
+#[tauri::command]
+async fn export_course_graph(
+    sqlite_pool: State<'_, SqlitePool>,
+    code: Option<String>,
+    direction: Direction,
+    depth: u32,
+    format: GraphFormat,
+    path: String,
+) -> Result<(), AppError> {
+    let output =
+        database::export_course_graph(&sqlite_pool, code.as_deref(), direction, depth, format)
+            .await?;
+    std::fs::write(path, output)?;
 
-        (*app).emit("fetch_status", "finish insert").unwrap();
-    }
     Ok(())
 }
 
 #[tauri::command]
-async fn search_courses(
+async fn get_rooms(
+    sqlite_pool: State<'_, SqlitePool>,
+    campus: Option<Campus>,
+) -> Result<Vec<Room>, AppError> {
+    database::get_rooms(&sqlite_pool, campus).await
+}
+
+// What meets in the room each quarter; `year` defaults to the latest one.
+#[tauri::command]
+async fn get_room_occupancy(
+    sqlite_pool: State<'_, SqlitePool>,
+    name: String,
+    year: Option<i32>,
+) -> Result<RoomOccupancy, AppError> {
+    database::get_room_occupancy(&sqlite_pool, &name, year).await
+}
+
+#[tauri::command]
+async fn find_empty_rooms(
+    sqlite_pool: State<'_, SqlitePool>,
+    day: Day,
+    period: Period,
+    quarter: Semester,
+    year: Option<i32>,
+    campus: Option<Campus>,
+) -> Result<Vec<Room>, AppError> {
+    database::find_empty_rooms(&sqlite_pool, &day, &period, &quarter, year, campus).await
+}
+
+// The courses a search finds by day and period, one grid per department unless
+// `axis` says lecturer or room. Courses clashing with another of `selected` are
+// marked in their cells.
+#[tauri::command]
+async fn get_timetable_grid(
     sqlite_pool: State<'_, SqlitePool>,
     search_query: SearchQuery,
-) -> Result<Vec<CourseListItem>, ()> {
-    let courses = database::search_courses(&*sqlite_pool, search_query).await;
+    axis: Option<GridAxis>,
+    selected: Option<Vec<i32>>,
+) -> Result<TimetableGrid, AppError> {
+    database::get_timetable_grid(
+        &sqlite_pool,
+        &search_query,
+        axis.unwrap_or_default(),
+        &selected.unwrap_or_default(),
+    )
+    .await
+}
+
+// Schools with their departments; a search for a school finds the courses of
+// every department under it.
+#[tauri::command]
+async fn get_department_tree(
+    sqlite_pool: State<'_, SqlitePool>,
+) -> Result<Vec<DepartmentNode>, AppError> {
+    database::get_department_tree(&sqlite_pool).await
+}
+
+#[tauri::command]
+async fn get_database_stats(sqlite_pool: State<'_, SqlitePool>) -> Result<DatabaseStats, AppError> {
+    database::get_database_stats(&sqlite_pool).await
+}
+
+#[tauri::command]
+async fn check_database(sqlite_pool: State<'_, SqlitePool>) -> Result<IntegrityReport, AppError> {
+    database::check_database(&sqlite_pool).await
+}
+
+#[tauri::command]
+async fn compact_database(sqlite_pool: State<'_, SqlitePool>) -> Result<CompactReport, AppError> {
+    database::compact_database(&sqlite_pool).await
+}
+
+// Deletes the crawled courses, and with `archive` the downloaded pages as well.
+// Search rules and settings are kept.
+#[tauri::command]
+async fn reset_crawl_data(
+    sqlite_pool: State<'_, SqlitePool>,
+    archive: State<'_, Archive>,
+    include_archive: bool,
+) -> Result<ResetReport, AppError> {
+    let report = database::reset_crawl_data(&sqlite_pool, include_archive).await?;
+    if include_archive {
+        archive.clear()?;
+    }
+
+    Ok(report)
+}
+
+#[tauri::command]
+async fn get_filter_options(sqlite_pool: State<'_, SqlitePool>) -> Result<FilterOptions, AppError> {
+    database::get_filter_options(&*sqlite_pool).await
+}
+
+#[tauri::command]
+async fn get_search_rules(sqlite_pool: State<'_, SqlitePool>) -> Result<Vec<SearchRule>, AppError> {
+    database::get_search_rules(&*sqlite_pool).await
+}
+
+#[tauri::command]
+async fn add_search_rule(
+    sqlite_pool: State<'_, SqlitePool>,
+    rule: SearchRule,
+) -> Result<i64, AppError> {
+    database::add_search_rule(&*sqlite_pool, &rule).await
+}
+
+#[tauri::command]
+async fn update_search_rule(
+    sqlite_pool: State<'_, SqlitePool>,
+    rule: SearchRule,
+) -> Result<(), AppError> {
+    database::update_search_rule(&*sqlite_pool, &rule).await
+}
 
-    Ok(courses)
+#[tauri::command]
+async fn delete_search_rule(sqlite_pool: State<'_, SqlitePool>, id: i64) -> Result<(), AppError> {
+    database::delete_search_rule(&*sqlite_pool, id).await
 }
 
 #[tauri::command]
-async fn get_course(sqlite_pool: State<'_, SqlitePool>, id: i32) -> Result<CourseResponse, ()> {
-    let course = database::get_course(&*sqlite_pool, id).await;
+fn parse_query(input: &str) -> Result<SearchQuery, AppError> {
+    Ok(query::parse(input)?)
+}
 
-    Ok(course)
+#[tauri::command]
+async fn get_course(
+    sqlite_pool: State<'_, SqlitePool>,
+    id: i32,
+) -> Result<CourseResponse, AppError> {
+    database::get_course(&*sqlite_pool, id).await
+}
+
+// The app's config and data dirs, named as Tauri's path resolver names them on
+// desktop. The command line needs them before there is an app to ask.
+fn app_dir(base: Option<PathBuf>, identifier: &str) -> Result<PathBuf, AppError> {
+    base.map(|dir| dir.join(identifier))
+        .ok_or_else(|| AppError::Config("no home directory to keep the app data in".to_string()))
+}
+
+fn settings_path(identifier: &str) -> Result<PathBuf, AppError> {
+    Ok(app_dir(dirs::config_dir(), identifier)?.join("settings.toml"))
+}
+
+// Opens the database and page archive, shared by the window and the command line.
+async fn open(identifier: &str, settings: &Settings) -> Result<(SqlitePool, Archive), AppError> {
+    let data_dir = app_dir(dirs::data_dir(), identifier)?;
+    std::fs::create_dir_all(&data_dir)?;
+
+    let db_path = match &settings.database_path {
+        Some(path) => PathBuf::from(path),
+        None => data_dir.join("database.db"),
+    };
+    let sqlite_pool = database::create_sqlite_pool(&db_path.to_string_lossy()).await?;
+    database::migrate(&sqlite_pool).await?;
+
+    Ok((sqlite_pool, Archive::new(data_dir.join("archive"))))
+}
+
+#[tauri::command]
+fn get_settings(settings: State<'_, RwLock<Settings>>) -> Settings {
+    settings.read().unwrap().clone()
+}
+
+#[tauri::command]
+fn update_settings(
+    settings: State<'_, RwLock<Settings>>,
+    app: State<'_, tauri::AppHandle>,
+    mut new_settings: Settings,
+) -> Result<(), AppError> {
+    new_settings.save(&settings_path(&app.config().identifier)?)?;
+    *settings.write().unwrap() = new_settings;
+
+    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let context = tauri::generate_context!();
+    let matches = cli::command().get_matches();
+
+    let identifier = &context.config().identifier;
+
+    // a broken settings.toml is told apart from a broken database
+    let settings = match settings_path(identifier) {
+        Ok(path) => match Settings::load(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("invalid settings in {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        Err(e) => {
+            eprintln!("invalid settings: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let (sqlite_pool, archive) = match block_on(open(identifier, &settings)) {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("failed to open the database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // subcommands run headless and exit before the webview toolkit, which needs a
+    // display on Linux, is started
+    if let Some(code) = block_on(cli::run(&sqlite_pool, &archive, &settings, &matches)) {
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
-        .plugin(tauri_plugin_cli::init())
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             greet,
-            fetch_test,
             fetch,
+            fetch_departments,
+            cancel_fetch,
+            refresh_course,
+            reparse_archive,
+            import_html,
             search_courses,
-            get_course
+            export_courses,
+            import_courses,
+            import_snapshot,
+            create_snapshot,
+            get_course_relations,
+            export_course_graph,
+            get_rooms,
+            get_room_occupancy,
+            find_empty_rooms,
+            get_timetable_grid,
+            get_department_tree,
+            get_database_stats,
+            check_database,
+            compact_database,
+            reset_crawl_data,
+            parse_query,
+            get_filter_options,
+            get_search_rules,
+            add_search_rule,
+            update_search_rule,
+            delete_search_rule,
+            get_course,
+            get_settings,
+            update_settings
         ])
-        .setup(|app| {
-            app.handle().plugin(tauri_plugin_cli::init()).unwrap();
-
-            let app_dir = app.path().app_data_dir().unwrap();
-
-            std::fs::create_dir_all(&app_dir).expect("Failed to create app data directory");
-
-            let db_path = app_dir.join("database.db");
-            let db_path = db_path.to_str().unwrap();
-
-            // let db_path = app
-            //     .path()
-            //     .resolve("database.db", BaseDirectory::Resource)
-            //     .unwrap();
-            // let db_path = db_path.as_path().to_str().unwrap();
-            // let db_path = if db_path.starts_with("\\\\?\\") {
-            //     &db_path[4..]
-            // } else {
-            //     db_path
-            // };
-            println!("db_path: {}", db_path);
-            let sqlite_pool = block_on(database::create_sqlite_pool(db_path)).unwrap();
-            block_on(database::migrate(&sqlite_pool)).unwrap();
+        .setup(move |app| {
+            // the snapshot bundled as database.db fills the database on first launch,
+            // in the background so the window opens at once; without it the app
+            // still starts, just empty
+            if let Ok(snapshot) = app.path().resolve("database.db", BaseDirectory::Resource) {
+                let pool = sqlite_pool.clone();
+                let app_handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    match database::seed_from_snapshot(&pool, &snapshot).await {
+                        Ok(Some(report)) => {
+                            println!(
+                                "seeded {} courses from {}",
+                                report.added, report.snapshot.source
+                            );
+                            let _ = app_handle.emit("database_seeded", report);
+                        }
+                        Ok(None) => {}
+                        Err(e) => eprintln!("failed to seed the database: {}", e),
+                    }
+                });
+            }
 
             app.manage(sqlite_pool);
+            app.manage(archive);
+            app.manage(RwLock::new(settings));
+            app.manage(CancelFlag::default());
             app.manage(app.app_handle().clone());
+
+            tauri::WebviewWindowBuilder::from_config(app.handle(), &app.config().app.windows[0])?
+                .build()?;
+
             Ok(())
         })
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }
@@ -0,0 +1,16 @@
+use unicode_normalization::UnicodeNormalization;
+
+// Folds the ways the same Japanese text gets typed into one form for matching:
+// NFKC (half-width katakana, full-width alphanumerics), lower case, hiragana as
+// katakana, and no whitespace at all, so "やまだ　太郎" matches "ヤマダ太郎".
+// Applied to both the stored copies used for search and the query strings.
+pub fn normalize(text: &str) -> String {
+    text.nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            'ぁ'..='ゖ' | 'ゝ' | 'ゞ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
@@ -0,0 +1,504 @@
+use crate::database::{SearchQuery, TimetableMode, TimetableQuery};
+use crate::scrape::{Day, Period, Semester};
+use serde::Serialize;
+use std::fmt;
+
+// Parses the one-line search syntax into a SearchQuery, e.g.
+//
+//   lecturer:山田 quarter:3Q-4Q day:月 -title:演習 credit>=2
+//   (department:数学系 OR department:物理学系) slot:月1-2 行列
+//   free:月1-2,月3-4,木5-6 grade:200
+//
+// Terms are ANDed; `-` negates a term or a group, `OR` separates alternatives and
+// parentheses group them. Commas list alternatives of one field (`quarter:1Q,3Q`),
+// quoted values may contain spaces and words without a field search the syllabus text.
+// `free` lists slots that every session of a course has to fit in.
+pub fn parse(input: &str) -> Result<SearchQuery, SyntaxError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+    };
+
+    let query = parser.group()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error("unexpected `)`"));
+    }
+
+    Ok(query)
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyntaxError {
+    // in characters from the start of the input
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "syntax error at {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    University,
+    Department,
+    Year,
+    Title,
+    Lecturer,
+    Grade,
+    Quarter,
+    Day,
+    Slot,
+    Free,
+    Credit,
+    Language,
+    LectureType,
+    Keyword,
+    Competency,
+    Text,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name.to_lowercase().as_str() {
+            "university" => Some(Field::University),
+            "department" | "dept" => Some(Field::Department),
+            "year" => Some(Field::Year),
+            "title" => Some(Field::Title),
+            "lecturer" => Some(Field::Lecturer),
+            "grade" => Some(Field::Grade),
+            "quarter" | "q" => Some(Field::Quarter),
+            "day" => Some(Field::Day),
+            "slot" => Some(Field::Slot),
+            "free" => Some(Field::Free),
+            "credit" => Some(Field::Credit),
+            "language" | "lang" => Some(Field::Language),
+            "type" => Some(Field::LectureType),
+            "keyword" => Some(Field::Keyword),
+            "competency" => Some(Field::Competency),
+            "text" => Some(Field::Text),
+            _ => None,
+        }
+    }
+
+    // Whether `query` already filters on this field, in which case another term
+    // for it has to be ANDed through any_of instead of widening the list.
+    fn is_set(self, query: &SearchQuery) -> bool {
+        match self {
+            Field::University => !query.university.is_empty(),
+            Field::Department => !query.department.is_empty(),
+            Field::Year => !query.year.is_empty(),
+            Field::Title => !query.title.is_empty(),
+            Field::Lecturer => !query.lecturer.is_empty(),
+            Field::Grade => !query.grade.is_empty(),
+            Field::Quarter => !query.quarter.is_empty(),
+            Field::Day | Field::Slot | Field::Free => !query.timetable.is_empty(),
+            Field::Language => !query.language.is_empty(),
+            Field::LectureType => !query.lecture_type.is_empty(),
+            Field::Keyword => !query.keyword.is_empty(),
+            Field::Competency => !query.competency.is_empty(),
+            // ranges narrow and text terms are ANDed already
+            Field::Credit | Field::Text => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Is,
+    AtLeast,
+    AtMost,
+    Above,
+    Below,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> SyntaxError {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> SyntaxError {
+        SyntaxError {
+            position,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // `OR` as a word of its own, not the start of e.g. `ORGANIC`
+    fn at_or(&self) -> bool {
+        self.chars[self.pos..].starts_with(&['O', 'R'])
+            && self
+                .chars
+                .get(self.pos + 2)
+                .is_none_or(|c| c.is_whitespace() || *c == '(')
+    }
+
+    // sequence ("OR" sequence)*
+    fn group(&mut self) -> Result<SearchQuery, SyntaxError> {
+        let mut alternatives = vec![self.sequence()?];
+
+        loop {
+            self.skip_whitespace();
+            if !self.at_or() {
+                break;
+            }
+            self.pos += 2;
+            alternatives.push(self.sequence()?);
+        }
+
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            SearchQuery {
+                any_of: alternatives,
+                ..Default::default()
+            }
+        })
+    }
+
+    // item+, up to the next `OR`, `)` or the end
+    fn sequence(&mut self) -> Result<SearchQuery, SyntaxError> {
+        let mut query = SearchQuery::default();
+        let mut empty = true;
+
+        loop {
+            self.skip_whitespace();
+            if self.peek().is_none() || self.peek() == Some(')') || self.at_or() {
+                break;
+            }
+            self.item(&mut query)?;
+            empty = false;
+        }
+
+        if empty {
+            return Err(self.error("expected a search term"));
+        }
+
+        Ok(query)
+    }
+
+    // "-"? ("(" group ")" | term)
+    fn item(&mut self, query: &mut SearchQuery) -> Result<(), SyntaxError> {
+        let negated = self.eat('-');
+
+        if self.peek() == Some('(') {
+            let open = self.pos;
+            self.pos += 1;
+            let group = self.group()?;
+            self.skip_whitespace();
+            if !self.eat(')') {
+                return Err(self.error_at(open, "unclosed `(`"));
+            }
+
+            if negated {
+                query.none_of.push(group);
+            } else {
+                // a single alternative, so the group simply has to match
+                query.any_of.push(group);
+            }
+            return Ok(());
+        }
+
+        let (field, operator, value, value_start) = self.term()?;
+
+        if negated {
+            let mut term = SearchQuery::default();
+            apply(&mut term, field, operator, &value, value_start)?;
+            query.none_of.push(term);
+        } else if field.is_set(query) {
+            let mut term = SearchQuery::default();
+            apply(&mut term, field, operator, &value, value_start)?;
+            query.any_of.push(term);
+        } else {
+            apply(query, field, operator, &value, value_start)?;
+        }
+
+        Ok(())
+    }
+
+    // name (":" | "=" | ">=" | "<=" | ">" | "<") value, or a bare word
+    fn term(&mut self) -> Result<(Field, Operator, String, usize), SyntaxError> {
+        let start = self.pos;
+
+        if self.peek() == Some('"') {
+            let value = self.quoted()?;
+            return Ok((Field::Text, Operator::Is, value, start));
+        }
+
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !"():=<>\"".contains(c))
+        {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+
+        let operator = match self.peek() {
+            Some(':') | Some('=') => Operator::Is,
+            Some('>') if self.chars.get(self.pos + 1) == Some(&'=') => Operator::AtLeast,
+            Some('<') if self.chars.get(self.pos + 1) == Some(&'=') => Operator::AtMost,
+            Some('>') => Operator::Above,
+            Some('<') => Operator::Below,
+            _ => {
+                if name.is_empty() {
+                    return Err(self.error("expected a search term"));
+                }
+                return Ok((Field::Text, Operator::Is, name, start));
+            }
+        };
+        if name.is_empty() {
+            return Err(self.error("expected a field name before the operator"));
+        }
+        let field = Field::from_name(&name)
+            .ok_or_else(|| self.error_at(start, &format!("unknown field `{}`", name)))?;
+        self.pos += match operator {
+            Operator::AtLeast | Operator::AtMost => 2,
+            _ => 1,
+        };
+
+        let value_start = self.pos;
+        let value = if self.peek() == Some('"') {
+            self.quoted()?
+        } else {
+            while self
+                .peek()
+                .is_some_and(|c| !c.is_whitespace() && c != '(' && c != ')')
+            {
+                self.pos += 1;
+            }
+            self.chars[value_start..self.pos].iter().collect()
+        };
+        if value.is_empty() {
+            return Err(self.error_at(value_start, &format!("missing value for `{}`", name)));
+        }
+
+        Ok((field, operator, value, value_start))
+    }
+
+    fn quoted(&mut self) -> Result<String, SyntaxError> {
+        let open = self.pos;
+        self.pos += 1;
+        let start = self.pos;
+
+        while self.peek().is_some_and(|c| c != '"') {
+            self.pos += 1;
+        }
+        if !self.eat('"') {
+            return Err(self.error_at(open, "unclosed `\"`"));
+        }
+
+        Ok(self.chars[start..self.pos - 1].iter().collect())
+    }
+}
+
+// Adds one term to `query`. Comma-separated values are alternatives of the field.
+fn apply(
+    query: &mut SearchQuery,
+    field: Field,
+    operator: Operator,
+    value: &str,
+    position: usize,
+) -> Result<(), SyntaxError> {
+    let error = |message: String| SyntaxError { position, message };
+
+    if field != Field::Credit && operator != Operator::Is {
+        return Err(error("only `credit` can be compared".to_string()));
+    }
+
+    let values = || value.split(',').map(str::trim).filter(|v| !v.is_empty());
+
+    match field {
+        Field::University => query.university.extend(values().map(String::from)),
+        Field::Department => query.department.extend(values().map(String::from)),
+        Field::Title => query.title.extend(values().map(String::from)),
+        Field::Lecturer => query.lecturer.extend(values().map(String::from)),
+        Field::Language => query.language.extend(values().map(String::from)),
+        Field::LectureType => query.lecture_type.extend(values().map(String::from)),
+        Field::Keyword => query.keyword.extend(values().map(String::from)),
+        Field::Competency => query.competency.extend(values().map(String::from)),
+        Field::Year => {
+            for year in values() {
+                if year.parse::<i32>().is_err() {
+                    return Err(error(format!("`{}` is not a year", year)));
+                }
+                query.year.push(year.to_string());
+            }
+        }
+        Field::Grade => {
+            for grade in values() {
+                let level = match grade {
+                    "1" | "100" => "100",
+                    "2" | "200" => "200",
+                    "3" | "300" => "300",
+                    "4" | "400" => "400",
+                    "5" | "500" => "500",
+                    "6" | "600" => "600",
+                    _ => return Err(error(format!("`{}` is not a grade, use 100 to 600", grade))),
+                };
+                query.grade.push(level.to_string());
+            }
+        }
+        Field::Quarter => {
+            for quarters in values() {
+                let (first, last) = quarters.split_once('-').unwrap_or((quarters, quarters));
+                let (Some(first), Some(last)) = (quarter_number(first), quarter_number(last))
+                else {
+                    return Err(error(format!(
+                        "`{}` is not a quarter, use 1Q to 4Q",
+                        quarters
+                    )));
+                };
+                if first > last {
+                    return Err(error(format!(
+                        "`{}` runs backwards, write the earlier quarter first",
+                        quarters
+                    )));
+                }
+                for number in first..=last {
+                    query.quarter.push(SEMESTERS[number - 1].clone());
+                }
+            }
+        }
+        Field::Day => {
+            for day in values() {
+                let day = parse_day(day)
+                    .ok_or_else(|| error(format!("`{}` is not a day of the week", day)))?;
+                for period in PERIODS {
+                    query.timetable.push(TimetableQuery {
+                        day: day.clone(),
+                        period: period.clone(),
+                    });
+                }
+            }
+        }
+        Field::Slot | Field::Free => {
+            if field == Field::Free {
+                query.timetable_mode = TimetableMode::Within;
+            }
+            for slot in values() {
+                query.timetable.push(
+                    parse_slot(slot)
+                        .ok_or_else(|| error(format!("`{}` is not a slot like 月1-2", slot)))?,
+                );
+            }
+        }
+        Field::Credit => {
+            let credit = value
+                .parse::<i32>()
+                .map_err(|_| error(format!("`{}` is not a number of credits", value)))?;
+            let out_of_range = || error(format!("`{}` is out of range", value));
+            let (min, max) = match operator {
+                Operator::Is => (Some(credit), Some(credit)),
+                Operator::AtLeast => (Some(credit), None),
+                Operator::Above => (Some(credit.checked_add(1).ok_or_else(out_of_range)?), None),
+                Operator::AtMost => (None, Some(credit)),
+                Operator::Below => (None, Some(credit.checked_sub(1).ok_or_else(out_of_range)?)),
+            };
+            if let Some(min) = min {
+                query.credit_min = Some(query.credit_min.map_or(min, |current| current.max(min)));
+            }
+            if let Some(max) = max {
+                query.credit_max = Some(query.credit_max.map_or(max, |current| current.min(max)));
+            }
+        }
+        Field::Text => {
+            if !query.text.is_empty() {
+                query.text.push(' ');
+            }
+            // a quoted phrase stays one term of the text search
+            if value.contains(char::is_whitespace) {
+                query.text.push_str(&format!("\"{}\"", value));
+            } else {
+                query.text.push_str(value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+const SEMESTERS: [Semester; 4] = [
+    Semester::First,
+    Semester::Second,
+    Semester::Third,
+    Semester::Fourth,
+];
+
+const PERIODS: [Period; 6] = [
+    Period::First,
+    Period::Second,
+    Period::Third,
+    Period::Fourth,
+    Period::Fifth,
+    Period::Sixth,
+];
+
+// "3Q", "3q" or "3"
+fn quarter_number(quarter: &str) -> Option<usize> {
+    let number = quarter.trim_end_matches(['Q', 'q']).parse().ok()?;
+    (1..=4).contains(&number).then_some(number)
+}
+
+// "3Q" as Semester::Third
+pub fn parse_quarter(quarter: &str) -> Option<Semester> {
+    quarter_number(quarter).map(|number| SEMESTERS[number - 1].clone())
+}
+
+// "月", "月曜", "月曜日", "mon" or "monday"
+fn parse_day(day: &str) -> Option<Day> {
+    let day = day.trim_end_matches("曜日").trim_end_matches('曜');
+    match day.to_lowercase().as_str() {
+        "月" | "mon" | "monday" => Some(Day::Monday),
+        "火" | "tue" | "tuesday" => Some(Day::Tuesday),
+        "水" | "wed" | "wednesday" => Some(Day::Wednesday),
+        "木" | "thu" | "thursday" => Some(Day::Thursday),
+        "金" | "fri" | "friday" => Some(Day::Friday),
+        "土" | "sat" | "saturday" => Some(Day::Saturday),
+        "日" | "sun" | "sunday" => Some(Day::Sunday),
+        _ => None,
+    }
+}
+
+// "月1-2", "月3" or "mon5-6"; a period number is folded into its two-period slot
+pub fn parse_slot(slot: &str) -> Option<TimetableQuery> {
+    let split = slot.find(|c: char| c.is_ascii_digit())?;
+    let (day, periods) = slot.split_at(split);
+    let first = periods.split('-').next()?.parse::<usize>().ok()?;
+    if !(1..=12).contains(&first) {
+        return None;
+    }
+
+    Some(TimetableQuery {
+        day: parse_day(day)?,
+        period: PERIODS[(first - 1) / 2].clone(),
+    })
+}
@@ -1,8 +1,12 @@
+use std::fmt;
+
 mod list;
 mod sub;
 
-pub use list::html_to_course_abstracts;
-pub use sub::{html_to_course, Course, Day, Period, Semester};
+pub use list::{html_to_course_abstracts, CourseAbstract};
+pub use sub::{
+    html_to_course, Course, CourseDetail, Day, LecturePlan, Period, Semester, TimeTable,
+};
 
 #[derive(Debug)]
 pub struct CourseTitle {
@@ -15,3 +19,14 @@ pub struct Lecturer {
     pub name: String,
     pub url: String,
 }
+
+#[derive(Debug)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parse error: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -1,4 +1,4 @@
-use crate::scrape::{CourseTitle, Lecturer};
+use crate::scrape::{CourseTitle, Lecturer, ParseError};
 use scraper::{ElementRef, Html, Selector};
 
 #[derive(Debug)]
@@ -7,11 +7,20 @@ pub struct CourseAbstract {
     pub title: CourseTitle,
     lecturer: Vec<Lecturer>,
     pub department: String,
+    // from the department link, e.g. GakubuCD=1&GakkaCD=311100 for 数学系 of 理学院
+    pub gakubu_cd: Option<u32>,
+    pub gakka_cd: Option<u32>,
+    // the first four digits of the JWC in the detail link, e.g. 2024 of JWC=202402001
+    pub year: Option<i32>,
     start: String,
     pub sylbs_update: String,
 }
 
-pub fn html_to_course_abstracts(html: &str) -> Vec<CourseAbstract> {
+// `base_url` is prepended to the relative links on the page, e.g. https://www.ocw.titech.ac.jp/
+pub fn html_to_course_abstracts(
+    html: &str,
+    base_url: &str,
+) -> Result<Vec<CourseAbstract>, ParseError> {
     let mut courses = Vec::new();
 
     let document = Html::parse_document(html);
@@ -19,8 +28,14 @@ pub fn html_to_course_abstracts(html: &str) -> Vec<CourseAbstract> {
     let tbody_selector = Selector::parse("tbody").unwrap();
     let row_selector = Selector::parse("tr").unwrap();
 
-    let rankings = document.select(&rankings_selector).next().unwrap();
-    let tbody = rankings.select(&tbody_selector).next().unwrap();
+    let rankings = document
+        .select(&rankings_selector)
+        .next()
+        .ok_or_else(|| ParseError("missing .ranking-list".to_string()))?;
+    let tbody = rankings
+        .select(&tbody_selector)
+        .next()
+        .ok_or_else(|| ParseError("missing .ranking-list tbody".to_string()))?;
     let rows = tbody.select(&row_selector);
 
     let code_selector = Selector::parse(".code").unwrap();
@@ -31,31 +46,41 @@ pub fn html_to_course_abstracts(html: &str) -> Vec<CourseAbstract> {
     let sylbs_update_selector = Selector::parse(".sylbs").unwrap();
 
     for row in rows {
-        let code = row.select(&code_selector).next().unwrap();
-        let course_title = row.select(&course_title_selector).next().unwrap();
-        let lecturer = row.select(&lecturer_selector).next().unwrap();
-        let opening_department = row.select(&opening_department_selector).next().unwrap();
-        let start = row.select(&start_selector).next().unwrap();
-        let sylbs_update = row.select(&sylbs_update_selector).next().unwrap();
+        let cell = |selector: &Selector, name: &str| {
+            row.select(selector)
+                .next()
+                .ok_or_else(|| ParseError(format!("missing {} in .ranking-list row", name)))
+        };
+        let code = cell(&code_selector, ".code")?;
+        let course_title = cell(&course_title_selector, ".course_title")?;
+        let lecturer = cell(&lecturer_selector, ".lecturer")?;
+        let opening_department = cell(&opening_department_selector, ".opening_department")?;
+        let start = cell(&start_selector, ".start")?;
+        let sylbs_update = cell(&sylbs_update_selector, ".sylbs")?;
 
+        let (gakubu_cd, gakka_cd) = get_department_codes(opening_department);
+        let title = get_course_title(course_title, base_url);
         courses.push(CourseAbstract {
             code: get_code(code),
-            title: get_course_title(course_title),
-            lecturer: get_lecturer(lecturer),
+            year: get_year(&title.url),
+            title,
+            lecturer: get_lecturer(lecturer, base_url),
             department: get_opening_department(opening_department),
+            gakubu_cd,
+            gakka_cd,
             start: get_start(start),
             sylbs_update: get_sylbs_update(sylbs_update),
         });
     }
 
-    courses
+    Ok(courses)
 }
 
 fn get_code(td: ElementRef) -> String {
     td.inner_html().trim().to_string()
 }
 
-fn get_course_title(td: ElementRef) -> CourseTitle {
+fn get_course_title(td: ElementRef, base_url: &str) -> CourseTitle {
     let a_err = td.select(&Selector::parse("a").unwrap()).next();
     let a = match a_err {
         Some(a) => a,
@@ -68,22 +93,16 @@ fn get_course_title(td: ElementRef) -> CourseTitle {
     };
     CourseTitle {
         title: a.inner_html().trim().to_string(),
-        url: format!(
-            "https://www.ocw.titech.ac.jp/{}",
-            a.value().attr("href").unwrap()
-        ),
+        url: format!("{}{}", base_url, a.value().attr("href").unwrap_or_default()),
     }
 }
 
-fn get_lecturer(td: ElementRef) -> Vec<Lecturer> {
+fn get_lecturer(td: ElementRef, base_url: &str) -> Vec<Lecturer> {
     let mut ret = Vec::new();
     for a in td.select(&Selector::parse("a").unwrap()) {
         ret.push(Lecturer {
             name: a.inner_html().trim().to_string(),
-            url: format!(
-                "https://www.ocw.titech.ac.jp/{}",
-                a.value().attr("href").unwrap()
-            ),
+            url: format!("{}{}", base_url, a.value().attr("href").unwrap_or_default()),
         });
     }
 
@@ -101,6 +120,29 @@ fn get_opening_department(td: ElementRef) -> String {
     a.inner_html().trim().to_string()
 }
 
+fn get_department_codes(td: ElementRef) -> (Option<u32>, Option<u32>) {
+    let href = td
+        .select(&Selector::parse("a").unwrap())
+        .next()
+        .and_then(|a| a.value().attr("href"))
+        .unwrap_or_default();
+    let param = |name: &str| {
+        href.split(['?', '&'])
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+            .and_then(|value| value.parse().ok())
+    };
+
+    (param("GakubuCD"), param("GakkaCD"))
+}
+
+fn get_year(url: &str) -> Option<i32> {
+    let jwc = url
+        .split(['?', '&'])
+        .find_map(|pair| pair.strip_prefix("JWC="))?;
+
+    jwc.get(..4)?.parse().ok()
+}
+
 fn get_start(td: ElementRef) -> String {
     td.inner_html().trim().to_string()
 }
@@ -1,10 +1,11 @@
-use crate::scrape::Lecturer;
+use crate::scrape::{Lecturer, ParseError};
 use regex::Regex;
 use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::Formatter;
 
+#[derive(Default)]
 pub struct Course {
     pub university: String,
     pub title: String,
@@ -23,6 +24,7 @@ pub struct Course {
     pub course_detail: CourseDetail,
 }
 
+#[derive(Default)]
 pub struct CourseDetail {
     pub abst: String,
     pub goal: String,
@@ -87,7 +89,7 @@ pub enum Day {
     Sunday,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Period {
     First,
     Second,
@@ -97,7 +99,7 @@ pub enum Period {
     Sixth,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Semester {
     First,
     Second,
@@ -105,7 +107,7 @@ pub enum Semester {
     Fourth,
 }
 
-pub fn html_to_course(html: &str) -> Course {
+pub fn html_to_course(html: &str, base_url: &str) -> Result<Course, ParseError> {
     let document = Html::parse_document(html);
 
     let university = String::from("東京工業大学");
@@ -113,94 +115,40 @@ pub fn html_to_course(html: &str) -> Course {
     let title = document
         .select(&Selector::parse(".page-title-area").unwrap())
         .next()
-        .unwrap();
+        .ok_or_else(|| ParseError("missing .page-title-area".to_string()))?;
     let (title, english_title) = get_title(
         title
             .select(&Selector::parse("h3").unwrap())
             .next()
-            .unwrap(),
-    );
+            .ok_or_else(|| ParseError("missing .page-title-area h3".to_string()))?,
+    )?;
 
     let abst_data = document
         .select(&Selector::parse(".gaiyo-data").unwrap())
         .next()
-        .unwrap();
+        .ok_or_else(|| ParseError("missing .gaiyo-data".to_string()))?;
     let dl_selector = Selector::parse("dl").unwrap();
-    let dls = abst_data.select(&dl_selector);
     let dd_selector = Selector::parse("dd").unwrap();
+    let dds = abst_data
+        .select(&dl_selector)
+        .map(|dl| dl.select(&dd_selector).next())
+        .collect::<Vec<_>>();
+    let dd = |n: usize| {
+        dds.get(n)
+            .copied()
+            .flatten()
+            .ok_or_else(|| ParseError(format!("missing .gaiyo-data dd #{}", n)))
+    };
 
-    let department = get_department(
-        dls.clone()
-            .next()
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let lecturer = get_lecturer(
-        dls.clone()
-            .nth(1)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let lecture_type = get_lecture_type(
-        dls.clone()
-            .nth(2)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let time_table = get_timetable(
-        dls.clone()
-            .nth(4)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let code = get_code(
-        dls.clone()
-            .nth(6)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let credit = get_credit(
-        dls.clone()
-            .nth(7)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let year = get_year(
-        dls.clone()
-            .nth(8)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let semester = get_semester(
-        dls.clone()
-            .nth(9)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
-    let language = get_language(
-        dls.clone()
-            .nth(12)
-            .unwrap()
-            .select(&dd_selector)
-            .next()
-            .unwrap(),
-    );
+    let department = get_department(dd(0)?);
+    let lecturer = get_lecturer(dd(1)?, base_url);
+    let lecture_type = get_lecture_type(dd(2)?);
+    let time_table = get_timetable(dd(4)?);
+    let code = get_code(dd(6)?);
+    let credit = get_credit(dd(7)?)?;
+    let year = get_year(dd(8)?)?;
+    let semester = get_semester(dd(9)?);
+    let language = get_language(dd(12)?);
 
     let url = "".to_string();
 
@@ -208,10 +156,10 @@ pub fn html_to_course(html: &str) -> Course {
         document
             .select(&Selector::parse("#overview").unwrap())
             .next()
-            .unwrap(),
-    );
+            .ok_or_else(|| ParseError("missing #overview".to_string()))?,
+    )?;
 
-    Course {
+    Ok(Course {
         university,
         title,
         english_title,
@@ -227,34 +175,34 @@ pub fn html_to_course(html: &str) -> Course {
         url,
         sylbs_update: "".to_string(),
         course_detail,
-    }
+    })
 }
 
 // (title, english_title)
-fn get_title(h3: ElementRef) -> (String, String) {
+fn get_title(h3: ElementRef) -> Result<(String, String), ParseError> {
     let all = h3.inner_html().trim().to_string();
 
     let titles = all.split("&nbsp;&nbsp;&nbsp;").collect::<Vec<&str>>();
+    if titles.len() < 2 {
+        return Err(ParseError(format!("unexpected title: {}", all)));
+    }
     let title = titles[0].to_string().chars().skip(7).collect::<String>();
     let english_title = titles[1].to_string();
 
-    (title, english_title)
+    Ok((title, english_title))
 }
 
 fn get_department(dd: ElementRef) -> String {
     dd.inner_html().trim().to_string()
 }
 
-fn get_lecturer(dd: ElementRef) -> Vec<Lecturer> {
+fn get_lecturer(dd: ElementRef, base_url: &str) -> Vec<Lecturer> {
     let mut lecturers = Vec::new();
 
     for a in dd.select(&Selector::parse("a").unwrap()) {
         lecturers.push(Lecturer {
             name: a.inner_html().trim().to_string(),
-            url: format!(
-                "https://www.ocw.titech.ac.jp/{}",
-                a.value().attr("href").unwrap()
-            ),
+            url: format!("{}{}", base_url, a.value().attr("href").unwrap_or_default()),
         });
     }
 
@@ -472,17 +420,17 @@ fn get_code(dd: ElementRef) -> String {
     dd.inner_html().trim().to_string()
 }
 
-fn get_credit(dd: ElementRef) -> i32 {
-    dd.inner_html().trim().to_string().parse().unwrap()
+fn get_credit(dd: ElementRef) -> Result<i32, ParseError> {
+    let credit = dd.inner_html().trim().to_string();
+    credit
+        .parse()
+        .map_err(|_| ParseError(format!("unexpected credit: {}", credit)))
 }
 
-fn get_year(dd: ElementRef) -> i32 {
-    dd.inner_html()
-        .trim()
-        .to_string()
-        .replace("年度", "")
-        .parse()
-        .unwrap()
+fn get_year(dd: ElementRef) -> Result<i32, ParseError> {
+    let year = dd.inner_html().trim().to_string().replace("年度", "");
+    year.parse()
+        .map_err(|_| ParseError(format!("unexpected year: {}", year)))
 }
 
 fn get_semester(dd: ElementRef) -> Vec<Semester> {
@@ -532,7 +480,7 @@ fn get_language(dd: ElementRef) -> String {
     dd.inner_html().trim().to_string()
 }
 
-fn get_details(overview: ElementRef) -> CourseDetail {
+fn get_details(overview: ElementRef) -> Result<CourseDetail, ParseError> {
     let mut details = CourseDetail {
         abst: "".to_string(),
         goal: "".to_string(),
@@ -553,7 +501,7 @@ fn get_details(overview: ElementRef) -> CourseDetail {
     };
 
     let div_selector = Selector::parse("div").unwrap();
-    let mut divs = overview.select(&div_selector);
+    let divs = overview.select(&div_selector);
     let p_selector = Selector::parse("p").unwrap();
     let ul_selector = Selector::parse("ul").unwrap();
     let tbody_selector = Selector::parse("tbody").unwrap();
@@ -606,7 +554,7 @@ fn get_details(overview: ElementRef) -> CourseDetail {
 
         if h3.inner_html().trim() == "授業計画・課題" {
             details.schedule = match div.select(&tbody_selector).next() {
-                Some(tbody) => get_schedule(tbody),
+                Some(tbody) => get_schedule(tbody)?,
                 None => Vec::new(),
             };
             continue;
@@ -685,7 +633,7 @@ fn get_details(overview: ElementRef) -> CourseDetail {
         }
     }
 
-    details
+    Ok(details)
 }
 
 fn get_abstract(p: ElementRef) -> String {
@@ -718,28 +666,27 @@ fn get_flow(p: ElementRef) -> String {
     p.inner_html().trim().to_string()
 }
 
-fn get_schedule(tbody: ElementRef) -> Vec<LecturePlan> {
+fn get_schedule(tbody: ElementRef) -> Result<Vec<LecturePlan>, ParseError> {
     let mut lecture_plans = Vec::new();
 
     let count_re = Regex::new(r"\d+").unwrap();
 
     let tr_selector = Selector::parse("tr").unwrap();
     for tr in tbody.select(&tr_selector) {
-        let count = tr
-            .select(&Selector::parse(".number_of_times").unwrap())
-            .next()
-            .unwrap();
-        let plan = tr
-            .select(&Selector::parse(".plan").unwrap())
-            .next()
-            .unwrap();
-        let assignment = tr
-            .select(&Selector::parse(".assignment").unwrap())
-            .next()
-            .unwrap();
+        let cell = |selector: &str| {
+            tr.select(&Selector::parse(selector).unwrap())
+                .next()
+                .ok_or_else(|| ParseError(format!("missing {} in schedule row", selector)))
+        };
+        let count = cell(".number_of_times")?;
+        let plan = cell(".plan")?;
+        let assignment = cell(".assignment")?;
 
         let count = count.inner_html().trim().to_string();
-        let count = count_re.find(&count).unwrap().as_str().parse().unwrap();
+        let count = count_re
+            .find(&count)
+            .and_then(|m| m.as_str().parse().ok())
+            .ok_or_else(|| ParseError(format!("unexpected schedule count: {}", count)))?;
         lecture_plans.push(LecturePlan {
             count,
             plan: plan.inner_html().trim().to_string(),
@@ -747,7 +694,7 @@ fn get_schedule(tbody: ElementRef) -> Vec<LecturePlan> {
         });
     }
 
-    lecture_plans
+    Ok(lecture_plans)
 }
 
 fn get_out_of_class(p: ElementRef) -> String {
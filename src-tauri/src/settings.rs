@@ -0,0 +1,126 @@
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time;
+
+// settings.toml in the app config dir; missing keys fall back to the defaults below
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub base_url: String,
+    pub departments: Vec<CrawlTarget>,
+    pub delay_secs: u64,
+    pub user_agent: String,
+    // database.db in the app data dir when unset; takes effect on the next launch
+    pub database_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlTarget {
+    pub gakubu_cd: u32,
+    pub name: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let departments = [
+            (1, "理学院"),
+            (4, "情報理工学院"),
+            (2, "工学院"),
+            (3, "物質理工学院"),
+            (5, "生命理工学院"),
+            (6, "環境・社会理工学院"),
+            (10, "初年次専門科目"),
+            (11, "工学院，物質理工学院，環境・社会理工学院共通科目"),
+            (7, "教養科目群"),
+        ];
+
+        Settings {
+            base_url: "https://www.ocw.titech.ac.jp/".to_string(),
+            departments: departments
+                .iter()
+                .map(|(gakubu_cd, name)| CrawlTarget {
+                    gakubu_cd: *gakubu_cd,
+                    name: name.to_string(),
+                })
+                .collect(),
+            delay_secs: 10,
+            user_agent: format!("pocket-syllabus/{}", env!("CARGO_PKG_VERSION")),
+            database_path: None,
+        }
+    }
+}
+
+impl Settings {
+    // Reads the settings file, writing the defaults first if it does not exist yet
+    pub fn load(path: &Path) -> AppResult<Settings> {
+        if !path.exists() {
+            let mut settings = Settings::default();
+            settings.save(path)?;
+            return Ok(settings);
+        }
+
+        let mut settings: Settings =
+            toml::from_str(&std::fs::read_to_string(path).map_err(config_error)?)?;
+        settings.normalize()?;
+
+        Ok(settings)
+    }
+
+    pub fn save(&mut self, path: &Path) -> AppResult<()> {
+        self.normalize()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(config_error)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?).map_err(config_error)?;
+
+        Ok(())
+    }
+
+    // Page paths are appended to base_url as they are, so it has to end in a slash.
+    fn normalize(&mut self) -> AppResult<()> {
+        if !self.base_url.starts_with("https://") && !self.base_url.starts_with("http://") {
+            return Err(AppError::Config(format!(
+                "base_url is not an http(s) URL: {}",
+                self.base_url
+            )));
+        }
+        if !self.base_url.ends_with('/') {
+            self.base_url.push('/');
+        }
+
+        Ok(())
+    }
+
+    pub fn delay(&self) -> time::Duration {
+        time::Duration::from_secs(self.delay_secs)
+    }
+
+    pub fn client(&self) -> AppResult<reqwest::Client> {
+        let client = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .build()
+            .map_err(|e| AppError::Config(e.to_string()))?;
+
+        Ok(client)
+    }
+
+    pub fn department_url(&self, gakubu_cd: u32) -> String {
+        format!(
+            "{}index.php?module=General&action=T0100&GakubuCD={}&lang=JA",
+            self.base_url, gakubu_cd
+        )
+    }
+
+    pub fn gakubu_cds(&self) -> Vec<u32> {
+        self.departments
+            .iter()
+            .map(|department| department.gakubu_cd)
+            .collect()
+    }
+}
+
+// The settings file is configuration, not data, so its io errors are Config errors.
+fn config_error(e: std::io::Error) -> AppError {
+    AppError::Config(e.to_string())
+}
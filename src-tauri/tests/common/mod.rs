@@ -0,0 +1,227 @@
+#![allow(dead_code)]
+
+use pocket_syllabus_lib::database;
+use pocket_syllabus_lib::scrape::{Course, Lecturer};
+use pocket_syllabus_lib::settings::{CrawlTarget, Settings};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+pub const LIST_PATH: &str = "/index.php?module=General&action=T0100&GakubuCD=1&lang=JA";
+pub const DETAIL_1_PATH: &str =
+    "/index.php?module=General&action=T0300&JWC=202402001&lang=JA&vid=03";
+pub const DETAIL_2_PATH: &str =
+    "/index.php?module=General&action=T0300&JWC=202402002&lang=JA&vid=03";
+
+pub fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[derive(Clone)]
+pub struct Route {
+    pub body: String,
+    pub etag: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+}
+
+// Serves fixture pages over HTTP/1.1 on a random local port. Routes can be
+// replaced while a test runs; unknown paths answer 404, and a matching
+// If-None-Match answers 304.
+#[derive(Clone)]
+pub struct MockServer {
+    pub base_url: String,
+    routes: Arc<Mutex<HashMap<String, Route>>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    pub async fn start() -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = MockServer {
+            base_url: format!("http://{}/", listener.local_addr().unwrap()),
+            routes: Arc::default(),
+            requests: Arc::default(),
+        };
+
+        let handle = server.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(handle.clone().serve(stream));
+            }
+        });
+
+        server
+    }
+
+    // the three pages of the fixture department GakubuCD=1
+    pub async fn start_with_fixtures() -> MockServer {
+        let server = MockServer::start().await;
+        server.route(LIST_PATH, &fixture("list.html"), Some("\"list-v1\""));
+        server.route(
+            DETAIL_1_PATH,
+            &fixture("detail_202402001.html"),
+            Some("\"detail-1-v1\""),
+        );
+        server.route(
+            DETAIL_2_PATH,
+            &fixture("detail_202402002.html"),
+            Some("\"detail-2-v1\""),
+        );
+        server
+    }
+
+    pub fn route(&self, path: &str, body: &str, etag: Option<&str>) {
+        self.routes.lock().unwrap().insert(
+            path.to_string(),
+            Route {
+                body: body.to_string(),
+                etag: etag.map(|etag| etag.to_string()),
+            },
+        );
+    }
+
+    pub fn remove(&self, path: &str) {
+        self.routes.lock().unwrap().remove(path);
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn clear_requests(&self) {
+        self.requests.lock().unwrap().clear();
+    }
+
+    async fn serve(self, mut stream: tokio::net::TcpStream) {
+        let mut buffer = Vec::new();
+        let mut chunk = [0; 4096];
+        while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            }
+        }
+
+        let head = String::from_utf8_lossy(&buffer).to_string();
+        let mut lines = head.split("\r\n");
+        let path = lines
+            .next()
+            .and_then(|line| line.split(' ').nth(1))
+            .unwrap_or("/")
+            .to_string();
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect::<HashMap<_, _>>();
+
+        let route = self.routes.lock().unwrap().get(&path).cloned();
+        let not_modified = match (&route, headers.get("if-none-match")) {
+            (
+                Some(Route {
+                    etag: Some(etag), ..
+                }),
+                Some(if_none_match),
+            ) => etag == if_none_match,
+            _ => false,
+        };
+        self.requests
+            .lock()
+            .unwrap()
+            .push(Request { path, headers });
+
+        let response = match route {
+            _ if not_modified => "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+            Some(route) => format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=UTF-8\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                route.body.len(),
+                route
+                    .etag
+                    .map(|etag| format!("ETag: {}\r\n", etag))
+                    .unwrap_or_default(),
+                route.body
+            ),
+            None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        };
+
+        let _ = stream.write_all(response.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+}
+
+pub async fn memory_pool() -> SqlitePool {
+    // a single connection, otherwise every connection opens its own empty database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    database::migrate(&pool).await.unwrap();
+    pool
+}
+
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pocket-syllabus-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+pub fn test_settings(base_url: &str) -> Settings {
+    Settings {
+        base_url: base_url.to_string(),
+        departments: vec![CrawlTarget {
+            gakubu_cd: 1,
+            name: "理学院".to_string(),
+        }],
+        delay_secs: 0,
+        ..Default::default()
+    }
+}
+
+// progress events collected in order, in place of the app's "fetch_status" events
+#[derive(Default)]
+pub struct Events(Mutex<Vec<String>>);
+
+impl Events {
+    pub fn push(&self, status: String) {
+        self.0.lock().unwrap().push(status);
+    }
+
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+// a course with only the fields search looks at filled in
+pub fn course(code: &str, title: &str, department: &str, year: i32) -> Course {
+    Course {
+        university: "東京工業大学".to_string(),
+        title: title.to_string(),
+        department: department.to_string(),
+        code: code.to_string(),
+        credit: 2,
+        year,
+        ..Default::default()
+    }
+}
+
+pub fn lecturer(name: &str) -> Lecturer {
+    Lecturer {
+        name: name.to_string(),
+        url: "".to_string(),
+    }
+}
@@ -0,0 +1,302 @@
+mod common;
+
+use common::{Events, MockServer, DETAIL_1_PATH, DETAIL_2_PATH, LIST_PATH};
+use pocket_syllabus_lib::archive::Archive;
+use pocket_syllabus_lib::crawl::{Crawler, RefreshTarget};
+use pocket_syllabus_lib::database;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+async fn course_json(pool: &SqlitePool, code: &str) -> Option<Value> {
+    match database::get_course_id_by_code(pool, code).await.unwrap() {
+        Some(id) => {
+            Some(serde_json::to_value(database::get_course(pool, id).await.unwrap()).unwrap())
+        }
+        None => None,
+    }
+}
+
+async fn course_count(pool: &SqlitePool) -> usize {
+    database::get_course_ids(pool).await.unwrap().len()
+}
+
+fn detail_requests(server: &MockServer) -> usize {
+    server
+        .requests()
+        .iter()
+        .filter(|request| request.path.contains("action=T0300"))
+        .count()
+}
+
+#[tokio::test]
+async fn crawl_inserts_every_course_of_a_department() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-inserts"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+
+    assert_eq!(
+        events.take(),
+        [
+            "Fetching Main",
+            "Left: 2 courses",
+            "Scraping: 1/2 courses: 数学系 線形代数第一",
+            "Finished: 1/2 courses: 線形代数第一",
+            "Scraping: 2/2 courses: 物理学系 量子力学基礎",
+            "Finished: 2/2 courses: 量子力学基礎",
+            "finish insert",
+        ]
+    );
+    assert_eq!(course_count(&pool).await, 2);
+
+    let course = course_json(&pool, "MTH.A201").await.unwrap();
+    assert_eq!(course["title"], "線形代数第一");
+    assert_eq!(course["credit"], 2);
+    assert_eq!(course["sylbsUpdate"], "2024/03/14");
+    assert_eq!(
+        course["url"],
+        format!("{}{}", server.base_url, &DETAIL_1_PATH[1..])
+    );
+    assert_eq!(course["timetable"].as_array().unwrap().len(), 2);
+    assert_eq!(course["timetable"][0]["room"], "W521");
+    assert_eq!(
+        course["courseDetail"]["keyword"],
+        serde_json::json!(["行列", "線形写像", "固有値"])
+    );
+
+    let course = course_json(&pool, "PHY.Q207").await.unwrap();
+    assert_eq!(course["lecturer"].as_array().unwrap().len(), 2);
+    assert_eq!(course["credit"], 4);
+}
+
+#[tokio::test]
+async fn second_crawl_sends_conditional_requests_and_skips_unchanged_courses() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-conditional"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+    events.take();
+    server.clear_requests();
+
+    crawler.crawl_departments(&[1]).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, LIST_PATH);
+    assert_eq!(requests[0].headers["if-none-match"], "\"list-v1\"");
+
+    let events = events.take();
+    assert!(events.contains(&"Main not modified".to_string()));
+    assert!(!events.iter().any(|event| event.starts_with("Finished")));
+    assert_eq!(course_count(&pool).await, 2);
+}
+
+#[tokio::test]
+async fn changed_sylbs_update_replaces_the_course() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-changed"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+    events.take();
+    server.clear_requests();
+
+    server.route(
+        LIST_PATH,
+        &common::fixture("list.html").replace("2024/03/14", "2024/04/01"),
+        Some("\"list-v2\""),
+    );
+    server.route(
+        DETAIL_1_PATH,
+        &common::fixture("detail_202402001.html").replace("W521", "W631"),
+        Some("\"detail-1-v2\""),
+    );
+
+    crawler.crawl_departments(&[1]).await.unwrap();
+
+    assert_eq!(detail_requests(&server), 1);
+    assert!(events
+        .take()
+        .contains(&"Finished: 1/2 courses: 線形代数第一".to_string()));
+    assert_eq!(course_count(&pool).await, 2);
+
+    let course = course_json(&pool, "MTH.A201").await.unwrap();
+    assert_eq!(course["sylbsUpdate"], "2024/04/01");
+    assert_eq!(course["timetable"][0]["room"], "W631");
+}
+
+#[tokio::test]
+async fn other_years_of_a_course_stay_when_it_is_crawled() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-years"));
+    let settings = common::test_settings(&server.base_url);
+
+    // last year's copy, updated on the same day as this year's list row
+    let mut last_year = common::course("MTH.A201", "線形代数第一", "数学系", 2023);
+    last_year.sylbs_update = "2024/03/14".to_string();
+    database::insert_course(&pool, &last_year).await.unwrap();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {}).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+    assert_eq!(detail_requests(&server), 2);
+    assert_eq!(course_count(&pool).await, 3);
+
+    server.route(
+        LIST_PATH,
+        &common::fixture("list.html").replace("2024/03/14", "2024/04/01"),
+        Some("\"list-v2\""),
+    );
+    crawler.crawl_departments(&[1]).await.unwrap();
+    // PHY.Q207 of the same year is up to date
+    assert_eq!(detail_requests(&server), 3);
+
+    let years: Vec<(i32, String)> = sqlx::query_as(
+        "SELECT year, sylbs_update FROM courses WHERE code = 'MTH.A201' ORDER BY year",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        years,
+        [
+            (2023, "2024/03/14".to_string()),
+            (2024, "2024/04/01".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn missing_detail_page_is_skipped() {
+    let server = MockServer::start_with_fixtures().await;
+    server.remove(DETAIL_2_PATH);
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-missing-detail"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+
+    let events = events.take();
+    assert!(events
+        .iter()
+        .any(|event| event.starts_with("Skipped: 2/2 courses:") && event.contains("404")));
+    assert_eq!(events.last().unwrap(), "finish insert");
+    assert_eq!(course_count(&pool).await, 1);
+    assert!(course_json(&pool, "PHY.Q207").await.is_none());
+}
+
+#[tokio::test]
+async fn malformed_detail_page_is_skipped() {
+    let server = MockServer::start_with_fixtures().await;
+    server.route(DETAIL_1_PATH, &common::fixture("malformed.html"), None);
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-malformed-detail"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+
+    let events = events.take();
+    assert!(events
+        .iter()
+        .any(|event| event.starts_with("Skipped: 1/2 courses: parse error")));
+    assert!(events.contains(&"Finished: 2/2 courses: 量子力学基礎".to_string()));
+    assert_eq!(course_count(&pool).await, 1);
+    assert!(course_json(&pool, "MTH.A201").await.is_none());
+}
+
+#[tokio::test]
+async fn malformed_list_page_is_skipped() {
+    let server = MockServer::start_with_fixtures().await;
+    server.route(LIST_PATH, &common::fixture("malformed.html"), None);
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-malformed-list"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+
+    let events = events.take();
+    assert_eq!(events.len(), 2);
+    assert!(events[1].starts_with(&format!("Skipped {}", settings.department_url(1))));
+    assert_eq!(detail_requests(&server), 0);
+    assert_eq!(course_count(&pool).await, 0);
+}
+
+#[tokio::test]
+async fn missing_list_page_does_not_stop_other_departments() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-missing-list"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[99, 1]).await.unwrap();
+
+    let events = events.take();
+    assert!(events[1].starts_with(&format!("Skipped {}", settings.department_url(99))));
+    assert!(events[1].contains("404"));
+    assert_eq!(events.last().unwrap(), "finish insert");
+    assert_eq!(course_count(&pool).await, 2);
+}
+
+#[tokio::test]
+async fn refresh_course_fetches_the_detail_page_again() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("crawl-refresh"));
+    let settings = common::test_settings(&server.base_url);
+    let events = Events::default();
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |status| events.push(status)).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+    events.take();
+
+    server.route(
+        DETAIL_2_PATH,
+        &common::fixture("detail_202402002.html").replace("S221", "S423"),
+        Some("\"detail-2-v2\""),
+    );
+
+    let id = crawler
+        .refresh_course(&RefreshTarget::Code("PHY.Q207".to_string()))
+        .await
+        .unwrap();
+
+    let url = format!("{}{}", server.base_url, &DETAIL_2_PATH[1..]);
+    assert_eq!(
+        events.take(),
+        [
+            format!("Refreshing: {}", url),
+            format!("Refreshed: {}", url)
+        ]
+    );
+    assert_eq!(course_count(&pool).await, 2);
+
+    let course =
+        serde_json::to_value(database::get_course(&pool, id as i32).await.unwrap()).unwrap();
+    assert_eq!(course["code"], "PHY.Q207");
+    assert_eq!(course["sylbsUpdate"], "2024/03/20");
+    assert_eq!(course["timetable"][0]["room"], "S423");
+
+    let missing = crawler
+        .refresh_course(&RefreshTarget::Code("XXX.X999".to_string()))
+        .await;
+    assert!(missing.is_err());
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use common::MockServer;
+use pocket_syllabus_lib::archive::Archive;
+use pocket_syllabus_lib::crawl::Crawler;
+use pocket_syllabus_lib::database::{self, DepartmentNode, SearchQuery};
+use pocket_syllabus_lib::scrape::html_to_course_abstracts;
+use pocket_syllabus_lib::settings::CrawlTarget;
+use sqlx::SqlitePool;
+
+fn find<'a>(tree: &'a [DepartmentNode], path: &[&str]) -> Option<&'a DepartmentNode> {
+    let node = tree.iter().find(|node| node.name == path[0])?;
+    match path.len() {
+        1 => Some(node),
+        _ => find(&node.children, &path[1..]),
+    }
+}
+
+async fn insert(pool: &SqlitePool, code: &str, department: &str) {
+    let course = common::course(code, code, department, 2024);
+    database::insert_course(pool, &course).await.unwrap();
+}
+
+async fn search_codes(pool: &SqlitePool, departments: &[&str]) -> Vec<String> {
+    let query = SearchQuery {
+        department: departments.iter().map(|name| name.to_string()).collect(),
+        ..Default::default()
+    };
+    database::search_courses(pool, query)
+        .await
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|course| course.code)
+        .collect()
+}
+
+#[tokio::test]
+async fn the_tree_starts_with_the_known_schools() {
+    let pool = common::memory_pool().await;
+    let tree = database::get_department_tree(&pool).await.unwrap();
+
+    assert_eq!(tree.len(), 9);
+    assert_eq!(
+        (tree[0].name.as_str(), tree[0].ocw_code),
+        ("理学院", Some(1))
+    );
+    assert_eq!(tree[0].children[0].name, "数学系");
+    // graduate courses run by several schools are under each of them
+    for school in ["理学院", "工学院", "物質理工学院", "環境・社会理工学院"] {
+        assert!(
+            find(&tree, &[school, "エネルギーコース"]).is_some(),
+            "{}",
+            school
+        );
+    }
+    assert!(find(&tree, &["生命理工学院", "エネルギーコース"]).is_none());
+}
+
+#[tokio::test]
+async fn list_pages_place_departments_by_gakubu_cd() {
+    let html = common::fixture("list.html");
+    let courses = html_to_course_abstracts(&html, "https://example.com/").unwrap();
+    assert_eq!(
+        (courses[0].gakubu_cd, courses[0].gakka_cd),
+        (Some(1), Some(311100))
+    );
+
+    let pool = common::memory_pool().await;
+    database::record_schools(
+        &pool,
+        &[CrawlTarget {
+            gakubu_cd: 12,
+            name: "新設学院".to_string(),
+        }],
+    )
+    .await
+    .unwrap();
+    let html = html
+        .replace(
+            "GakubuCD=1&amp;GakkaCD=311200",
+            "GakubuCD=12&amp;GakkaCD=312100",
+        )
+        .replace("物理学系", "新設系");
+    let courses = html_to_course_abstracts(&html, "https://example.com/").unwrap();
+    database::record_departments(&pool, &courses).await.unwrap();
+
+    let tree = database::get_department_tree(&pool).await.unwrap();
+    let math = find(&tree, &["理学院", "数学系"]).unwrap();
+    assert_eq!((math.ocw_code, math.guessed), (Some(311100), false));
+    let school = find(&tree, &["新設学院"]).unwrap();
+    assert_eq!(school.ocw_code, Some(12));
+    assert_eq!(school.children.len(), 1);
+    assert_eq!(school.children[0].name, "新設系");
+    assert_eq!(school.children[0].ocw_code, Some(312100));
+}
+
+#[tokio::test]
+async fn crawling_records_the_departments_of_the_list_page() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("departments-crawl"));
+    let settings = common::test_settings(&server.base_url);
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {}).unwrap();
+    crawler.crawl_departments(&[1]).await.unwrap();
+
+    let tree = database::get_department_tree(&pool).await.unwrap();
+    let physics = find(&tree, &["理学院", "物理学系"]).unwrap();
+    assert_eq!((physics.ocw_code, physics.guessed), (Some(311200), false));
+}
+
+#[tokio::test]
+async fn unknown_departments_are_placed_by_course_codes() {
+    let pool = common::memory_pool().await;
+    insert(&pool, "MTH.A201", "数学系").await;
+    insert(&pool, "MTH.C301", "数学系").await;
+    insert(&pool, "MTH.E501", "数学特別コース").await;
+    insert(&pool, "XYZ.A101", "謎の系").await;
+
+    let tree = database::get_department_tree(&pool).await.unwrap();
+    let special = find(&tree, &["理学院", "数学特別コース"]).unwrap();
+    assert!(special.guessed);
+    let unknown = find(&tree, &["謎の系"]).unwrap();
+    assert!(unknown.guessed);
+    assert_eq!(tree.len(), 10);
+
+    // a list page overrules the guess
+    let html = common::fixture("list.html")
+        .replace("数学系", "数学特別コース")
+        .replace(
+            "GakubuCD=1&amp;GakkaCD=311100",
+            "GakubuCD=2&amp;GakkaCD=322100",
+        );
+    let courses = html_to_course_abstracts(&html, "https://example.com/").unwrap();
+    database::record_departments(&pool, &courses).await.unwrap();
+    let tree = database::get_department_tree(&pool).await.unwrap();
+    assert!(find(&tree, &["理学院", "数学特別コース"]).is_none());
+    let special = find(&tree, &["工学院", "数学特別コース"]).unwrap();
+    assert_eq!((special.ocw_code, special.guessed), (Some(322100), false));
+
+    // courses stored before the departments table are placed when it is opened
+    sqlx::query("DELETE FROM departments WHERE guessed = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+    database::migrate(&pool).await.unwrap();
+    let tree = database::get_department_tree(&pool).await.unwrap();
+    assert!(find(&tree, &["謎の系"]).unwrap().guessed);
+}
+
+#[tokio::test]
+async fn a_school_search_takes_in_its_departments() {
+    let pool = common::memory_pool().await;
+    insert(&pool, "MTH.A201", "数学系").await;
+    insert(&pool, "MTH.E501", "数学コース").await;
+    insert(&pool, "ENR.H410", "エネルギーコース").await;
+    insert(&pool, "MEC.A201", "機械系").await;
+    insert(&pool, "XYZ.A101", "謎の系").await;
+
+    assert_eq!(
+        search_codes(&pool, &["理学院"]).await,
+        ["MTH.A201", "MTH.E501", "ENR.H410"]
+    );
+    assert_eq!(
+        search_codes(&pool, &["工学院"]).await,
+        ["ENR.H410", "MEC.A201"]
+    );
+    assert_eq!(search_codes(&pool, &["数学系"]).await, ["MTH.A201"]);
+    assert_eq!(
+        search_codes(&pool, &["数学系", "機械系"]).await,
+        ["MTH.A201", "MEC.A201"]
+    );
+    assert_eq!(search_codes(&pool, &["謎の系"]).await, ["XYZ.A101"]);
+    assert!(search_codes(&pool, &["存在しない系"]).await.is_empty());
+}
@@ -0,0 +1,260 @@
+mod common;
+
+use common::{MockServer, DETAIL_1_PATH};
+use pocket_syllabus_lib::archive::{Archive, DETAIL_PAGE};
+use pocket_syllabus_lib::crawl::{CancelFlag, Crawler, RefreshTarget};
+use pocket_syllabus_lib::database::{
+    self, RuleAction, RuleField, RuleMatch, SearchQuery, SearchRule,
+};
+use pocket_syllabus_lib::error::AppError;
+use pocket_syllabus_lib::query;
+use pocket_syllabus_lib::settings::Settings;
+use serde_json::json;
+
+#[test]
+fn errors_serialize_as_kind_and_message() {
+    for (error, expected) in [
+        (
+            AppError::NotFound("course 1".to_string()),
+            json!({ "kind": "NotFound", "message": "not found: course 1" }),
+        ),
+        (
+            AppError::Database("disk I/O error".to_string()),
+            json!({ "kind": "Database", "message": "database error: disk I/O error" }),
+        ),
+        (
+            AppError::Network("timed out".to_string()),
+            json!({ "kind": "Network", "message": "network error: timed out" }),
+        ),
+        (
+            AppError::File("permission denied".to_string()),
+            json!({ "kind": "File", "message": "file error: permission denied" }),
+        ),
+        (
+            AppError::Parse("missing #overview".to_string()),
+            json!({ "kind": "Parse", "message": "parse error: missing #overview" }),
+        ),
+        (
+            AppError::Cancelled,
+            json!({ "kind": "Cancelled", "message": "cancelled" }),
+        ),
+        (
+            AppError::Config("expected `=`".to_string()),
+            json!({ "kind": "Config", "message": "config error: expected `=`" }),
+        ),
+    ] {
+        assert_eq!(serde_json::to_value(&error).unwrap(), expected);
+    }
+}
+
+#[tokio::test]
+async fn missing_rows_are_not_found() {
+    let pool = common::memory_pool().await;
+
+    let error = database::get_course(&pool, 999).await.unwrap_err();
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+
+    let rule = SearchRule {
+        id: 999,
+        name: "".to_string(),
+        action: RuleAction::Exclude,
+        field: RuleField::Code,
+        match_kind: RuleMatch::Glob,
+        pattern: "LAH.*".to_string(),
+        enabled: true,
+    };
+    let error = database::update_search_rule(&pool, &rule)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+
+    let archive = Archive::new(common::temp_dir("errors-not-found"));
+    let settings = common::test_settings("http://127.0.0.1:1/");
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {}).unwrap();
+    let error = crawler
+        .refresh_course(&RefreshTarget::Code("XXX.X999".to_string()))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn a_failed_replace_keeps_the_stored_course() {
+    let pool = common::memory_pool().await;
+    let mut course = common::course("MTH.A201", "線形代数第一", "数学系", 2024);
+    course.lecturer = vec![common::lecturer("山田 太郎")];
+    let id = database::insert_course(&pool, &course).await.unwrap();
+
+    sqlx::query(
+        "CREATE TEMP TRIGGER no_lecturers BEFORE INSERT ON lecturers
+        BEGIN SELECT RAISE(ABORT, 'no lecturers'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let error = database::replace_course(&pool, &course).await.unwrap_err();
+    assert!(matches!(error, AppError::Database(_)), "{:?}", error);
+
+    let stored =
+        serde_json::to_value(database::get_course(&pool, id as i32).await.unwrap()).unwrap();
+    assert_eq!(stored["lecturer"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn closed_pool_is_a_database_error() {
+    let pool = common::memory_pool().await;
+    pool.close().await;
+
+    let error = database::search_courses(&pool, SearchQuery::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::Database(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn unwritable_files_are_file_errors() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let settings = common::test_settings(&server.base_url);
+
+    // the archive directory is taken by a plain file
+    let dir = common::temp_dir("errors-file");
+    std::fs::create_dir_all(&dir).unwrap();
+    let taken = dir.join("archive");
+    std::fs::write(&taken, "").unwrap();
+    let archive = Archive::new(taken);
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {}).unwrap();
+    let error = crawler
+        .refresh_course(&RefreshTarget::Url(format!(
+            "{}{}",
+            server.base_url,
+            &DETAIL_1_PATH[1..]
+        )))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::File(_)), "{:?}", error);
+
+    let error = AppError::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+    assert_eq!(error.kind(), "File");
+}
+
+#[tokio::test]
+async fn unreachable_and_missing_pages_are_network_errors() {
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("errors-network"));
+
+    // nothing listens on port 1
+    let settings = common::test_settings("http://127.0.0.1:1/");
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {}).unwrap();
+    let error = crawler
+        .refresh_course(&RefreshTarget::Url(
+            "http://127.0.0.1:1/index.php".to_string(),
+        ))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::Network(_)), "{:?}", error);
+
+    let server = MockServer::start().await;
+    let settings = common::test_settings(&server.base_url);
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {}).unwrap();
+    let error = crawler
+        .refresh_course(&RefreshTarget::Url(format!(
+            "{}{}",
+            server.base_url,
+            &DETAIL_1_PATH[1..]
+        )))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::Network(_)), "{:?}", error);
+
+    // a 304 with no archived copy to fall back on
+    server.route(DETAIL_1_PATH, "", Some("\"detail-1-v1\""));
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        reqwest::header::IF_NONE_MATCH,
+        "\"detail-1-v1\"".parse().unwrap(),
+    );
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap();
+    let url = format!("{}{}", server.base_url, &DETAIL_1_PATH[1..]);
+    let error = archive
+        .fetch(&pool, &client, &url, DETAIL_PAGE)
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, AppError::Network(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn unexpected_pages_and_queries_are_parse_errors() {
+    let server = MockServer::start().await;
+    server.route(DETAIL_1_PATH, "<html><body></body></html>", None);
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("errors-parse"));
+    let settings = common::test_settings(&server.base_url);
+
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {}).unwrap();
+    let error = crawler
+        .refresh_course(&RefreshTarget::Url(format!(
+            "{}{}",
+            server.base_url,
+            &DETAIL_1_PATH[1..]
+        )))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::Parse(_)), "{:?}", error);
+
+    let error = AppError::from(query::parse("foo:bar").unwrap_err());
+    assert_eq!(
+        error.to_string(),
+        "parse error: syntax error at 0: unknown field `foo`"
+    );
+}
+
+#[tokio::test]
+async fn cancelled_crawls_stop_before_the_next_page() {
+    let server = MockServer::start_with_fixtures().await;
+    let pool = common::memory_pool().await;
+    let archive = Archive::new(common::temp_dir("errors-cancelled"));
+    let settings = common::test_settings(&server.base_url);
+    let cancel = CancelFlag::default();
+
+    // cancelled once the list page is read, before any course is fetched
+    let crawler = Crawler::new(&pool, &archive, &settings, |status: String| {
+        if status.starts_with("Left:") {
+            cancel.cancel();
+        }
+    })
+    .unwrap()
+    .with_cancel(&cancel);
+    let error = crawler.crawl_departments(&[1]).await.unwrap_err();
+    assert!(matches!(error, AppError::Cancelled), "{:?}", error);
+    assert_eq!(server.requests().len(), 1);
+    assert!(database::get_course_ids(&pool).await.unwrap().is_empty());
+
+    // a new fetch starts after a reset
+    cancel.reset();
+    let crawler = Crawler::new(&pool, &archive, &settings, |_| {})
+        .unwrap()
+        .with_cancel(&cancel);
+    crawler.crawl_departments(&[1]).await.unwrap();
+    assert_eq!(database::get_course_ids(&pool).await.unwrap().len(), 2);
+}
+
+#[test]
+fn broken_settings_files_are_config_errors() {
+    let dir = common::temp_dir("errors-config");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("settings.toml");
+    std::fs::write(&path, "base_url = ").unwrap();
+
+    let error = Settings::load(&path).unwrap_err();
+    assert!(matches!(error, AppError::Config(_)), "{:?}", error);
+
+    // a directory where the file should be
+    let error = Settings::load(&dir).unwrap_err();
+    assert!(matches!(error, AppError::Config(_)), "{:?}", error);
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use pocket_syllabus_lib::database::{self, ExportFormat, SearchQuery, SortKey};
+use pocket_syllabus_lib::error::AppError;
+use pocket_syllabus_lib::scrape::{Day, LecturePlan, Period, Semester, TimeTable};
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    let mut course = common::course("MTH.A201", "線形代数第一", "数学系", 2024);
+    course.english_title = "Linear Algebra I".to_string();
+    course.lecturer = vec![common::lecturer("山田 太郎"), common::lecturer("佐藤 花子")];
+    course.semester = vec![Semester::Third, Semester::Fourth];
+    course.time_table = vec![
+        TimeTable {
+            day: Day::Monday,
+            period: Period::First,
+            room: "W521".to_string(),
+        },
+        TimeTable {
+            day: Day::Thursday,
+            period: Period::First,
+            room: "W521".to_string(),
+        },
+    ];
+    course.course_detail.abst = "行列, ベクトル空間と \"線形写像\" を学ぶ。".to_string();
+    course.course_detail.keyword = vec!["行列".to_string(), "固有値".to_string()];
+    course.course_detail.schedule = vec![
+        LecturePlan {
+            count: 1,
+            plan: "行列".to_string(),
+            assignment: "演習1".to_string(),
+        },
+        LecturePlan {
+            count: 2,
+            plan: "行列式".to_string(),
+            assignment: "演習2".to_string(),
+        },
+    ];
+    course.course_detail.related_course = vec!["MTH.A202".to_string()];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("PHY.Q207", "量子力学基礎", "物理学系", 2024);
+    course.credit = 4;
+    course.lecturer = vec![common::lecturer("鈴木 一郎")];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    pool
+}
+
+async fn export(pool: &SqlitePool, query: Option<SearchQuery>, format: ExportFormat) -> String {
+    database::export_courses(pool, query, format).await.unwrap()
+}
+
+#[tokio::test]
+async fn json_export_round_trips_through_import() {
+    let pool = seeded_pool().await;
+    let json = export(&pool, None, ExportFormat::Json).await;
+
+    let courses: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(courses.as_array().unwrap().len(), 2);
+    assert_eq!(courses[0]["englishTitle"], "Linear Algebra I");
+    assert_eq!(courses[0]["timetable"][1]["dayOfWeek"], 4);
+    assert_eq!(courses[0]["courseDetail"]["schedule"][1]["plan"], "行列式");
+
+    let copy = common::memory_pool().await;
+    assert_eq!(database::import_courses(&copy, &json).await.unwrap(), 2);
+    assert_eq!(export(&copy, None, ExportFormat::Json).await, json);
+
+    // importing again replaces the courses instead of adding them twice
+    assert_eq!(database::import_courses(&copy, &json).await.unwrap(), 2);
+    assert_eq!(database::get_course_ids(&copy).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn ndjson_has_one_course_per_line() {
+    let pool = seeded_pool().await;
+    let json: Vec<Value> =
+        serde_json::from_str(&export(&pool, None, ExportFormat::Json).await).unwrap();
+    let ndjson = export(&pool, None, ExportFormat::Ndjson).await;
+
+    let lines: Vec<Value> = ndjson
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines, json);
+}
+
+#[tokio::test]
+async fn csv_flattens_each_course_into_one_row() {
+    let pool = seeded_pool().await;
+    let csv = export(&pool, None, ExportFormat::Csv).await;
+
+    assert!(csv.starts_with("\u{feff}id,university,code,title,englishTitle,department,lecturer,"));
+    assert!(csv.contains(
+        "1,東京工業大学,MTH.A201,線形代数第一,Linear Algebra I,数学系,山田 太郎; 佐藤 花子,,月1-2 W521; 木1-2 W521,2,2024,3Q; 4Q,"
+    ));
+    assert!(csv.contains(",\"行列, ベクトル空間と \"\"線形写像\"\" を学ぶ。\","));
+    assert!(csv.contains(",\"1. 行列 / 演習1\n2. 行列式 / 演習2\","));
+    assert!(csv.contains("2,東京工業大学,PHY.Q207,量子力学基礎,,物理学系,鈴木 一郎,,,4,2024,,"));
+    // header, two rows; the schedule cell holds the only line break inside quotes
+    assert_eq!(csv.matches("\r\n").count(), 3);
+}
+
+#[tokio::test]
+async fn search_results_export_in_their_sort_order() {
+    let pool = seeded_pool().await;
+
+    let query = SearchQuery {
+        sort: Some(SortKey::Credit),
+        order: database::SortOrder::Desc,
+        limit: Some(1),
+        ignore_rules: true,
+        ..Default::default()
+    };
+    let courses: Vec<Value> =
+        serde_json::from_str(&export(&pool, Some(query), ExportFormat::Json).await).unwrap();
+    // the page size of the search does not cut the export short
+    assert_eq!(courses.len(), 2);
+    assert_eq!(courses[0]["code"], "PHY.Q207");
+
+    let query = SearchQuery {
+        lecturer: vec!["山田".to_string()],
+        ..Default::default()
+    };
+    let courses: Vec<Value> =
+        serde_json::from_str(&export(&pool, Some(query), ExportFormat::Json).await).unwrap();
+    assert_eq!(courses.len(), 1);
+    assert_eq!(courses[0]["code"], "MTH.A201");
+}
+
+#[tokio::test]
+async fn broken_imports_are_parse_errors() {
+    let pool = common::memory_pool().await;
+
+    let error = database::import_courses(&pool, "[{\"code\": ")
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::Parse(_)), "{:?}", error);
+
+    let seeded = seeded_pool().await;
+    let json = export(&seeded, None, ExportFormat::Json).await;
+    let json = json.replacen("\"dayOfWeek\": 1", "\"dayOfWeek\": 9", 1);
+    let error = database::import_courses(&pool, &json).await.unwrap_err();
+    assert!(matches!(error, AppError::Parse(_)), "{:?}", error);
+    assert!(database::get_course_ids(&pool).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn imports_replace_by_code_year_and_title() {
+    let pool = seeded_pool().await;
+    let json = export(&pool, None, ExportFormat::Json).await;
+
+    let last_year = common::course("MTH.A201", "線形代数第一", "数学系", 2023);
+    database::insert_course(&pool, &last_year).await.unwrap();
+
+    assert_eq!(database::import_courses(&pool, &json).await.unwrap(), 2);
+    let years: Vec<i32> =
+        sqlx::query_scalar("SELECT year FROM courses WHERE code = 'MTH.A201' ORDER BY year")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(years, [2023, 2024]);
+}
+
+#[tokio::test]
+async fn a_failed_import_stores_nothing() {
+    let seeded = seeded_pool().await;
+    let json = export(&seeded, None, ExportFormat::Json).await;
+
+    let pool = common::memory_pool().await;
+    // the second course fails after the first one has gone in
+    sqlx::query(
+        "CREATE TEMP TRIGGER no_suzuki BEFORE INSERT ON lecturers
+        WHEN NEW.name = '鈴木 一郎'
+        BEGIN SELECT RAISE(ABORT, 'no lecturers'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let error = database::import_courses(&pool, &json).await.unwrap_err();
+    assert!(matches!(error, AppError::Database(_)), "{:?}", error);
+    assert!(database::get_course_ids(&pool).await.unwrap().is_empty());
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use pocket_syllabus_lib::database::{self, CourseGraph, Direction, GraphFormat, Relation};
+use pocket_syllabus_lib::error::AppError;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+async fn insert(
+    pool: &SqlitePool,
+    code: &str,
+    title: &str,
+    year: i32,
+    prerequisite: &str,
+    related: &[&str],
+) {
+    let mut course = common::course(code, title, "数学系", year);
+    course.course_detail.prerequisite = prerequisite.to_string();
+    course.course_detail.related_course = related.iter().map(|entry| entry.to_string()).collect();
+    database::insert_course(pool, &course).await.unwrap();
+}
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+    insert(
+        &pool,
+        "MTH.A201",
+        "線形代数第一",
+        2024,
+        "特になし",
+        &["MTH.A202 ： 線形代数第二"],
+    )
+    .await;
+    insert(
+        &pool,
+        "MTH.A202",
+        "線形代数第二",
+        2024,
+        "ＭＴＨ．Ａ２０１ を履修済みであること",
+        &["MTH.A201 ： 線形代数第一"],
+    )
+    .await;
+    insert(
+        &pool,
+        "MTH.B301",
+        "代数学第一",
+        2024,
+        "MTH.A202 と MTH.C211 を履修していること",
+        &["MTH.B302 ： 代数学第二"],
+    )
+    .await;
+    // only the latest year of a code counts
+    insert(&pool, "MTH.B301", "代数学第一", 2023, "PHY.Q207", &[]).await;
+    insert(&pool, "MTH.C101", "微分積分", 2024, "", &[]).await;
+    pool
+}
+
+fn nodes(graph: &CourseGraph) -> Vec<(&str, Option<u32>)> {
+    graph
+        .nodes
+        .iter()
+        .map(|node| (node.code.as_str(), node.depth))
+        .collect()
+}
+
+fn edges(graph: &CourseGraph) -> Vec<(&str, &str, Relation)> {
+    graph
+        .edges
+        .iter()
+        .map(|edge| (edge.from.as_str(), edge.to.as_str(), edge.relation))
+        .collect()
+}
+
+#[test]
+fn course_codes_are_found_in_any_width() {
+    assert_eq!(
+        database::course_codes("ＭＴＨ．Ａ２０１，MTH.A202 または MTH.A201 の単位"),
+        ["MTH.A201", "MTH.A202"]
+    );
+    assert!(database::course_codes("MTH.A20 や LAS.M1011 は違う").is_empty());
+    assert_eq!(
+        database::course_codes("MTH.A201を履修済み、ＭＴＨ．Ａ２０２も"),
+        ["MTH.A201", "MTH.A202"]
+    );
+}
+
+#[tokio::test]
+async fn prerequisites_and_related_courses_link_codes() {
+    let pool = seeded_pool().await;
+    let graph = database::get_course_graph(&pool).await.unwrap();
+
+    assert_eq!(
+        nodes(&graph),
+        [
+            ("MTH.A201", None),
+            ("MTH.A202", None),
+            ("MTH.B301", None),
+            ("MTH.B302", None),
+            ("MTH.C101", None),
+            ("MTH.C211", None),
+        ]
+    );
+    assert_eq!(
+        edges(&graph),
+        [
+            ("MTH.A201", "MTH.A202", Relation::Prerequisite),
+            ("MTH.A201", "MTH.A202", Relation::Related),
+            ("MTH.A202", "MTH.B301", Relation::Prerequisite),
+            ("MTH.B301", "MTH.B302", Relation::Related),
+            ("MTH.C211", "MTH.B301", Relation::Prerequisite),
+        ]
+    );
+
+    assert_eq!(graph.nodes[0].title, "線形代数第一");
+    // codes only mentioned by others have no course
+    let b302 = &graph.nodes[3];
+    assert_eq!((b302.id, b302.title.as_str()), (None, ""));
+}
+
+#[tokio::test]
+async fn relations_follow_links_to_the_chosen_depth() {
+    let pool = seeded_pool().await;
+    let relations = |code: &'static str, direction, depth| {
+        let pool = pool.clone();
+        async move {
+            database::get_course_relations(&pool, code, direction, depth)
+                .await
+                .unwrap()
+        }
+    };
+
+    let graph = relations("MTH.A201", Direction::Descendants, 1).await;
+    assert_eq!(
+        nodes(&graph),
+        [("MTH.A201", Some(0)), ("MTH.A202", Some(1))]
+    );
+    assert_eq!(edges(&graph).len(), 2);
+
+    let graph = relations("MTH.A201", Direction::Descendants, 3).await;
+    assert_eq!(
+        nodes(&graph),
+        [
+            ("MTH.A201", Some(0)),
+            ("MTH.A202", Some(1)),
+            ("MTH.B301", Some(2)),
+            ("MTH.B302", Some(3)),
+        ]
+    );
+
+    let graph = relations("MTH.B301", Direction::Ancestors, 5).await;
+    assert_eq!(
+        nodes(&graph),
+        [
+            ("MTH.A201", Some(2)),
+            ("MTH.A202", Some(1)),
+            ("MTH.B301", Some(0)),
+            ("MTH.C211", Some(1)),
+        ]
+    );
+
+    let graph = relations("MTH.A202", Direction::Both, 1).await;
+    assert_eq!(
+        nodes(&graph),
+        [
+            ("MTH.A201", Some(1)),
+            ("MTH.A202", Some(0)),
+            ("MTH.B301", Some(1)),
+        ]
+    );
+
+    let graph = relations("MTH.C101", Direction::Both, 2).await;
+    assert_eq!(nodes(&graph), [("MTH.C101", Some(0))]);
+    assert!(graph.edges.is_empty());
+
+    let error = database::get_course_relations(&pool, "PHY.Q207", Direction::Both, 1)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn graphs_export_as_dot_and_json() {
+    let pool = seeded_pool().await;
+
+    let dot = database::export_course_graph(&pool, None, Direction::Both, 0, GraphFormat::Dot)
+        .await
+        .unwrap();
+    assert!(dot.starts_with("digraph courses {\n"));
+    assert!(dot.contains("    \"MTH.A201\" [label=\"MTH.A201\\n線形代数第一\"];\n"));
+    assert!(dot.contains("    \"MTH.C211\" [label=\"MTH.C211\", color=grey];\n"));
+    assert!(dot.contains("    \"MTH.A201\" -> \"MTH.A202\";\n"));
+    assert!(dot.contains("    \"MTH.A201\" -> \"MTH.A202\" [style=dashed];\n"));
+    assert!(dot.ends_with("}\n"));
+
+    let json = database::export_course_graph(
+        &pool,
+        Some("MTH.B301"),
+        Direction::Ancestors,
+        1,
+        GraphFormat::Json,
+    )
+    .await
+    .unwrap();
+    let graph: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(graph["nodes"].as_array().unwrap().len(), 3);
+    assert_eq!(graph["nodes"][1]["code"], "MTH.B301");
+    assert_eq!(graph["nodes"][1]["depth"], 0);
+    assert_eq!(graph["edges"][0]["relation"], "Prerequisite");
+}
@@ -0,0 +1,226 @@
+mod common;
+
+use pocket_syllabus_lib::database::{self, GridAxis, GridGroup, SearchQuery, TimetableGrid};
+use pocket_syllabus_lib::scrape::{Day, Period, Semester, TimeTable};
+use sqlx::SqlitePool;
+
+struct Seed {
+    code: &'static str,
+    department: &'static str,
+    lecturers: &'static [&'static str],
+    quarters: Vec<Semester>,
+    sessions: Vec<(Day, Period, &'static str)>,
+}
+
+async fn insert(pool: &SqlitePool, seed: Seed) -> i32 {
+    let mut course = common::course(seed.code, seed.code, seed.department, 2024);
+    course.lecturer = seed
+        .lecturers
+        .iter()
+        .map(|name| common::lecturer(name))
+        .collect();
+    course.semester = seed.quarters;
+    course.time_table = seed
+        .sessions
+        .into_iter()
+        .map(|(day, period, room)| TimeTable {
+            day,
+            period,
+            room: room.to_string(),
+        })
+        .collect();
+    database::insert_course(pool, &course).await.unwrap() as i32
+}
+
+// ids of MTH.A201, MTH.A202, MTH.B301, PHY.Q207, MTH.Z491
+async fn seeded_pool() -> (SqlitePool, Vec<i32>) {
+    let pool = common::memory_pool().await;
+    let ids = vec![
+        insert(
+            &pool,
+            Seed {
+                code: "MTH.A201",
+                department: "数学系",
+                lecturers: &["山田 太郎"],
+                quarters: vec![Semester::Third],
+                sessions: vec![
+                    (Day::Monday, Period::First, "W8E-101"),
+                    (Day::Thursday, Period::First, "W8E-101"),
+                ],
+            },
+        )
+        .await,
+        insert(
+            &pool,
+            Seed {
+                code: "MTH.A202",
+                department: "数学系",
+                lecturers: &["山田 太郎", "佐藤 花子"],
+                quarters: vec![Semester::Third, Semester::Fourth],
+                sessions: vec![(Day::Monday, Period::First, "W8E-102")],
+            },
+        )
+        .await,
+        insert(
+            &pool,
+            Seed {
+                code: "MTH.B301",
+                department: "数学系",
+                lecturers: &["佐藤 花子"],
+                quarters: vec![Semester::Fourth],
+                sessions: vec![(Day::Thursday, Period::First, "W8E-101")],
+            },
+        )
+        .await,
+        insert(
+            &pool,
+            Seed {
+                code: "PHY.Q207",
+                department: "物理学系",
+                lecturers: &["鈴木 一郎"],
+                quarters: vec![Semester::Third],
+                sessions: vec![(Day::Friday, Period::Third, "")],
+            },
+        )
+        .await,
+        insert(
+            &pool,
+            Seed {
+                code: "MTH.Z491",
+                department: "数学系",
+                lecturers: &[],
+                quarters: vec![],
+                sessions: vec![],
+            },
+        )
+        .await,
+    ];
+    (pool, ids)
+}
+
+async fn timetable_grid(
+    pool: &SqlitePool,
+    query: SearchQuery,
+    axis: GridAxis,
+    selected: &[i32],
+) -> TimetableGrid {
+    database::get_timetable_grid(pool, &query, axis, selected)
+        .await
+        .unwrap()
+}
+
+fn names(grid: &TimetableGrid) -> Vec<&str> {
+    grid.groups
+        .iter()
+        .map(|group| group.name.as_str())
+        .collect()
+}
+
+fn codes(group: &GridGroup, day: usize, period: usize) -> Vec<&str> {
+    group.cells[day][period]
+        .courses
+        .iter()
+        .map(|course| course.code.as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn courses_sit_in_their_day_and_period() {
+    let (pool, _) = seeded_pool().await;
+    let grid = timetable_grid(&pool, SearchQuery::default(), GridAxis::Department, &[]).await;
+
+    assert_eq!(names(&grid), ["数学系", "物理学系"]);
+    let math = &grid.groups[0];
+    assert_eq!(math.cells.len(), 7);
+    assert!(math.cells.iter().all(|periods| periods.len() == 6));
+    // Monday is day 1, 1-2 is period 0
+    assert_eq!(codes(math, 1, 0), ["MTH.A201", "MTH.A202"]);
+    assert_eq!(codes(math, 4, 0), ["MTH.A201", "MTH.B301"]);
+    assert_eq!(codes(&grid.groups[1], 5, 2), ["PHY.Q207"]);
+    let placed: usize = math
+        .cells
+        .iter()
+        .flatten()
+        .map(|cell| cell.courses.len())
+        .sum();
+    assert_eq!(placed, 4);
+    assert_eq!(math.unscheduled.len(), 1);
+    assert_eq!(math.unscheduled[0].code, "MTH.Z491");
+}
+
+#[tokio::test]
+async fn any_search_filters_the_grid() {
+    let (pool, _) = seeded_pool().await;
+    let query = SearchQuery {
+        department: vec!["理学院".to_string()],
+        quarter: vec![Semester::Fourth],
+        ..Default::default()
+    };
+    let grid = timetable_grid(&pool, query, GridAxis::Department, &[]).await;
+
+    assert_eq!(names(&grid), ["数学系"]);
+    let math = &grid.groups[0];
+    assert_eq!(codes(math, 1, 0), ["MTH.A202"]);
+    assert_eq!(codes(math, 4, 0), ["MTH.B301"]);
+    assert!(math.unscheduled.is_empty());
+}
+
+#[tokio::test]
+async fn chosen_courses_mark_their_clashes() {
+    let (pool, ids) = seeded_pool().await;
+    let (a201, a202, b301) = (ids[0], ids[1], ids[2]);
+
+    let grid = timetable_grid(&pool, SearchQuery::default(), GridAxis::Department, &[a201]).await;
+    let math = &grid.groups[0];
+    // MTH.A202 shares 3Q with MTH.A201 on Monday; MTH.B301 is only in 4Q
+    assert_eq!(math.cells[1][0].conflicts, [a202]);
+    assert!(math.cells[4][0].conflicts.is_empty());
+
+    // two chosen courses clash with each other
+    let grid = timetable_grid(
+        &pool,
+        SearchQuery::default(),
+        GridAxis::Department,
+        &[a201, a202],
+    )
+    .await;
+    assert_eq!(grid.groups[0].cells[1][0].conflicts, [a201, a202]);
+
+    // a chosen course the search leaves out still counts
+    let query = SearchQuery {
+        title: vec!["MTH.A202".to_string()],
+        ..Default::default()
+    };
+    let grid = timetable_grid(&pool, query, GridAxis::Department, &[a201, b301]).await;
+    assert_eq!(grid.groups[0].cells[1][0].conflicts, [a202]);
+}
+
+#[tokio::test]
+async fn lecturers_and_rooms_group_the_grid_too() {
+    let (pool, _) = seeded_pool().await;
+
+    let grid_by_lecturer =
+        timetable_grid(&pool, SearchQuery::default(), GridAxis::Lecturer, &[]).await;
+    assert_eq!(
+        names(&grid_by_lecturer),
+        ["佐藤 花子", "山田 太郎", "鈴木 一郎", ""]
+    );
+    let sato = &grid_by_lecturer.groups[0];
+    assert_eq!(codes(sato, 1, 0), ["MTH.A202"]);
+    assert_eq!(codes(sato, 4, 0), ["MTH.B301"]);
+    assert_eq!(
+        codes(&grid_by_lecturer.groups[1], 1, 0),
+        ["MTH.A201", "MTH.A202"]
+    );
+    assert_eq!(grid_by_lecturer.groups[3].unscheduled[0].code, "MTH.Z491");
+
+    let grid_by_room = timetable_grid(&pool, SearchQuery::default(), GridAxis::Room, &[]).await;
+    assert_eq!(names(&grid_by_room), ["W8E-101", "W8E-102", ""]);
+    let w8e_101 = &grid_by_room.groups[0];
+    assert_eq!(codes(w8e_101, 1, 0), ["MTH.A201"]);
+    assert_eq!(codes(w8e_101, 4, 0), ["MTH.A201", "MTH.B301"]);
+    assert_eq!(codes(&grid_by_room.groups[1], 1, 0), ["MTH.A202"]);
+    let unnamed = &grid_by_room.groups[2];
+    assert_eq!(codes(unnamed, 5, 2), ["PHY.Q207"]);
+    assert_eq!(unnamed.unscheduled[0].code, "MTH.Z491");
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::{Events, DETAIL_1_PATH};
+use pocket_syllabus_lib::database;
+use pocket_syllabus_lib::import::{import_html, ImportReport};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+const BASE_URL: &str = "https://example.com/";
+
+fn write(dir: &Path, name: &str, html: &str) {
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, html).unwrap();
+}
+
+// Pages as a browser saves them:
+// - list.html, the fixture list page
+// - pages/linear.html, MTH.A201 renamed to MTH.A299 and saved with its url
+// - pages/quantum.html, PHY.Q207 without the url
+// - more/list.html, a second PHY.Q207 row titled 量子力学応用
+// - more/applied.html, its detail page, also without the url
+// - orphan.html, a detail page no list row has
+// - notes.html and notes.txt, neither a list nor a detail page
+fn saved_pages(name: &str) -> PathBuf {
+    let dir = common::temp_dir(name);
+    let list = common::fixture("list.html");
+    let linear = common::fixture("detail_202402001.html");
+    let quantum = common::fixture("detail_202402002.html");
+
+    write(&dir, "list.html", &list);
+    write(
+        &dir,
+        "pages/linear.html",
+        &format!(
+            "<!-- saved from url=(0083){}{} -->\n{}",
+            BASE_URL,
+            DETAIL_1_PATH[1..].replace('&', "&amp;"),
+            linear.replace("MTH.A201", "MTH.A299")
+        ),
+    );
+    write(&dir, "pages/quantum.html", &quantum);
+    write(
+        &dir,
+        "more/list.html",
+        &list
+            .replace("MTH.A201", "MTH.Z999")
+            .replace("量子力学基礎", "量子力学応用")
+            .replace("JWC=202402002", "JWC=202402102")
+            .replace("2024/03/20", "2024/04/01"),
+    );
+    write(
+        &dir,
+        "more/applied.html",
+        &quantum.replace("量子力学基礎", "量子力学応用"),
+    );
+    write(&dir, "orphan.html", &linear.replace("MTH.A201", "LAH.T101"));
+    write(&dir, "notes.html", "<html><body>メモ</body></html>");
+    write(&dir, "notes.txt", "メモ");
+
+    dir
+}
+
+async fn import(pool: &SqlitePool, dir: &Path, events: &Events) -> ImportReport {
+    import_html(pool, dir, BASE_URL, |status| events.push(status))
+        .await
+        .unwrap()
+}
+
+fn name(dir: &Path, file: &str) -> String {
+    dir.join(file).to_string_lossy().to_string()
+}
+
+// (title, url, sylbs_update) of the courses with `code`, by title
+async fn stored(pool: &SqlitePool, code: &str) -> Vec<(String, String, String)> {
+    sqlx::query_as("SELECT title, url, sylbs_update FROM courses WHERE code = ? ORDER BY title")
+        .bind(code)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn saved_pages_are_matched_to_their_list_rows() {
+    let dir = saved_pages("import-match");
+    let pool = common::memory_pool().await;
+    let events = Events::default();
+
+    let report = import(&pool, &dir, &events).await;
+
+    assert_eq!(report.list_pages, 2);
+    assert_eq!(report.detail_pages, 4);
+    assert_eq!(report.imported, 4);
+    assert_eq!(report.up_to_date, 0);
+    assert_eq!(report.unmatched, [name(&dir, "orphan.html")]);
+    assert_eq!(report.skipped, [name(&dir, "notes.html")]);
+    assert!(report.failed.is_empty());
+    assert_eq!(events.take().len(), 4);
+
+    // by the saved url, although its code is on no list row
+    assert_eq!(
+        stored(&pool, "MTH.A299").await,
+        [(
+            "線形代数第一".to_string(),
+            format!("{}{}", BASE_URL, &DETAIL_1_PATH[1..]),
+            "2024/03/14".to_string()
+        )]
+    );
+    // by code, then by title where two rows share the code
+    let quantum = stored(&pool, "PHY.Q207").await;
+    assert_eq!(quantum.len(), 2);
+    assert_eq!(quantum[0].0, "量子力学基礎");
+    assert_eq!(quantum[0].2, "2024/03/20");
+    assert_eq!(quantum[1].0, "量子力学応用");
+    assert!(quantum[1].1.contains("JWC=202402102"));
+    assert_eq!(quantum[1].2, "2024/04/01");
+    // without a row there is no update date to compare later crawls with
+    let orphan = stored(&pool, "LAH.T101").await;
+    assert_eq!((orphan[0].1.as_str(), orphan[0].2.as_str()), ("", ""));
+}
+
+#[tokio::test]
+async fn importing_again_leaves_matched_courses_alone() {
+    let dir = saved_pages("import-again");
+    let pool = common::memory_pool().await;
+    let events = Events::default();
+
+    import(&pool, &dir, &events).await;
+    let report = import(&pool, &dir, &events).await;
+
+    assert_eq!(report.up_to_date, 3);
+    // an unmatched page has nothing to tell it is up to date by
+    assert_eq!(report.imported, 1);
+    assert_eq!(database::get_course_ids(&pool).await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn a_list_row_records_its_department() {
+    let dir = saved_pages("import-departments");
+    let pool = common::memory_pool().await;
+
+    import(&pool, &dir, &Events::default()).await;
+
+    let tree = database::get_department_tree(&pool).await.unwrap();
+    let physics = tree[0]
+        .children
+        .iter()
+        .find(|node| node.name == "物理学系")
+        .unwrap();
+    assert_eq!(physics.ocw_code, Some(311200));
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use pocket_syllabus_lib::archive::Archive;
+use pocket_syllabus_lib::database::{self, ArchivedPageRow, SearchQuery};
+use sqlx::SqlitePool;
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+    for (code, department, year) in [
+        ("MTH.A201", "数学系", 2024),
+        ("MTH.A202", "数学系", 2024),
+        ("MTH.A201", "数学系", 2023),
+        ("PHY.Q207", "物理学系", 2024),
+    ] {
+        let mut course = common::course(code, "講義", department, year);
+        course.lecturer = vec![common::lecturer("山田 太郎")];
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+    pool
+}
+
+fn count(tables: &[database::TableCount], table: &str) -> i64 {
+    tables
+        .iter()
+        .find(|count| count.table == table)
+        .map(|count| count.count)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn stats_count_rows_courses_and_bytes() {
+    let pool = seeded_pool().await;
+
+    let stats = database::get_database_stats(&pool).await.unwrap();
+    assert_eq!(count(&stats.tables, "courses"), 4);
+    assert_eq!(count(&stats.tables, "lecturers"), 4);
+    assert_eq!(count(&stats.tables, "search_rules"), 3);
+    assert_eq!(
+        stats
+            .courses
+            .iter()
+            .map(|courses| (courses.department.as_str(), courses.year, courses.count))
+            .collect::<Vec<_>>(),
+        [
+            ("数学系", 2023, 1),
+            ("数学系", 2024, 2),
+            ("物理学系", 2024, 1)
+        ]
+    );
+    assert!(stats.size > 0);
+    assert!(stats.free <= stats.size);
+}
+
+#[tokio::test]
+async fn check_finds_orphaned_rows() {
+    let pool = seeded_pool().await;
+
+    let report = database::check_database(&pool).await.unwrap();
+    assert!(report.is_ok(), "{:?}", report);
+
+    // what a client without foreign keys could leave behind
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM courses WHERE code = 'PHY.Q207'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO keywords (course_id, keyword) VALUES (99, '行列')")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let report = database::check_database(&pool).await.unwrap();
+    assert!(!report.is_ok());
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+    assert_eq!(
+        report
+            .orphans
+            .iter()
+            .map(|orphans| (orphans.table.as_str(), orphans.count))
+            .collect::<Vec<_>>(),
+        [("lecturers", 1), ("keywords", 1)]
+    );
+}
+
+#[tokio::test]
+async fn compact_empties_the_log_and_frees_pages() {
+    let dir = common::temp_dir("compact");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("database.db");
+    let pool = database::create_sqlite_pool(path.to_str().unwrap())
+        .await
+        .unwrap();
+    database::migrate(&pool).await.unwrap();
+
+    for i in 0..200 {
+        let mut course = common::course(&format!("MTH.A{}", i), "講義", "数学系", 2024);
+        course.course_detail.abst = "行列".repeat(500);
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+    sqlx::query("DELETE FROM courses")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let wal = dir.join("database.db-wal");
+    assert!(std::fs::metadata(&wal).unwrap().len() > 0);
+
+    let report = database::compact_database(&pool).await.unwrap();
+    assert!(
+        report.size_after < report.size_before,
+        "{} -> {}",
+        report.size_before,
+        report.size_after
+    );
+    assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+    assert_eq!(database::get_database_stats(&pool).await.unwrap().free, 0);
+}
+
+#[tokio::test]
+async fn reset_keeps_search_rules_and_optionally_the_archive() {
+    let pool = seeded_pool().await;
+    database::insert_archived_page(
+        &pool,
+        &ArchivedPageRow {
+            id: 0,
+            url: "http://www.ocw.titech.ac.jp/".to_string(),
+            kind: "list".to_string(),
+            fetched_at: 0,
+            etag: None,
+            last_modified: None,
+            path: "list.html.gz".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+
+    let report = database::reset_crawl_data(&pool, false).await.unwrap();
+    assert_eq!((report.courses, report.archived_pages), (4, 0));
+
+    let stats = database::get_database_stats(&pool).await.unwrap();
+    assert_eq!(count(&stats.tables, "courses"), 0);
+    assert_eq!(count(&stats.tables, "lecturers"), 0);
+    assert_eq!(count(&stats.tables, "course_texts"), 0);
+    assert_eq!(count(&stats.tables, "archived_pages"), 1);
+    assert_eq!(count(&stats.tables, "search_rules"), 3);
+    let response = database::search_courses(&pool, SearchQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(response.total, 0);
+
+    let report = database::reset_crawl_data(&pool, true).await.unwrap();
+    assert_eq!((report.courses, report.archived_pages), (0, 1));
+
+    let dir = common::temp_dir("reset-archive");
+    std::fs::create_dir_all(dir.join("www_ocw_titech_ac_jp")).unwrap();
+    Archive::new(dir.clone()).clear().unwrap();
+    assert!(!dir.exists());
+    // clearing an archive that was never written is fine
+    Archive::new(dir).clear().unwrap();
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use pocket_syllabus_lib::database::{self, SearchQuery};
+use pocket_syllabus_lib::error::AppError;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+const CHILD_TABLES: [&str; 7] = [
+    "lecturers",
+    "timetables",
+    "semesters",
+    "keywords",
+    "competencies",
+    "schedules",
+    "related_courses",
+];
+
+// A database as the app left it before migration `version` existed.
+async fn old_pool(version: i64) -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    let mut migrator = Migrator::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("migrations"))
+        .await
+        .unwrap();
+    migrator.migrations = migrator
+        .migrations
+        .iter()
+        .filter(|migration| migration.version < version)
+        .cloned()
+        .collect::<Vec<_>>()
+        .into();
+    migrator.run(&pool).await.unwrap();
+
+    pool
+}
+
+async fn count(pool: &SqlitePool, sql: &str) -> i64 {
+    sqlx::query_scalar(sql).fetch_one(pool).await.unwrap()
+}
+
+async fn index_names(pool: &SqlitePool) -> Vec<String> {
+    sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'index' AND sql IS NOT NULL")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn empty_database_gets_cascades_constraints_and_indices() {
+    let pool = common::memory_pool().await;
+
+    for table in CHILD_TABLES {
+        let on_delete: String = sqlx::query_scalar(&format!(
+            "SELECT on_delete FROM pragma_foreign_key_list('{}')",
+            table
+        ))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(on_delete, "CASCADE", "{}", table);
+    }
+
+    let indices = index_names(&pool).await;
+    for index in [
+        "courses_code_year_title",
+        "courses_department",
+        "lecturers_course_id",
+        "timetables_day_periods",
+        "archived_pages_url",
+    ] {
+        assert!(indices.iter().any(|name| name == index), "{}", index);
+    }
+
+    let mut course = common::course("MTH.A201", "線形代数第一", "数学系", 2024);
+    course.lecturer = vec![common::lecturer("山田 太郎")];
+    course.course_detail.keyword = vec!["行列".to_string()];
+    let id = database::insert_course(&pool, &course).await.unwrap();
+
+    let error = database::insert_course(&pool, &course).await.unwrap_err();
+    assert!(matches!(error, AppError::Database(_)), "{:?}", error);
+
+    // the same course in another year is a separate row
+    course.year = 2025;
+    database::insert_course(&pool, &course).await.unwrap();
+
+    sqlx::query("DELETE FROM courses WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM lecturers").await, 1);
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM keywords").await, 1);
+}
+
+#[tokio::test]
+async fn old_database_keeps_its_courses_and_loses_duplicates_and_orphans() {
+    let pool = old_pool(5).await;
+
+    // older versions stored a course again whenever it was crawled twice, and
+    // deleted courses without their child rows
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for (id, code, title, year, update) in [
+        (1, "MTH.A201", "線形代数第一", 2024, "2024/03/01"),
+        (2, "MTH.A201", "線形代数第一", 2024, "2024/03/14"),
+        (3, "MTH.A201", "線形代数第一", 2023, "2023/03/01"),
+        (4, "PHY.Q207", "量子力学基礎", 2024, "2024/03/14"),
+    ] {
+        sqlx::query(
+            "INSERT INTO courses (id, university, title, department, code, credit, year, sylbs_update)
+            VALUES (?, '東京工業大学', ?, '数学系', ?, 2, ?, ?)",
+        )
+        .bind(id)
+        .bind(title)
+        .bind(code)
+        .bind(year)
+        .bind(update)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO lecturers (course_id, name) VALUES (?, '山田 太郎')")
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO course_texts (rowid, title, abstract, goal, keywords, schedule, textbook) VALUES (?, ?, '', '', '', '', '')")
+            .bind(id)
+            .bind(title)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    sqlx::query("INSERT INTO timetables (course_id, day, periods, room) VALUES (99, 1, 1, 'W521')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    database::migrate(&pool).await.unwrap();
+
+    assert_eq!(database::get_course_ids(&pool).await.unwrap(), [2, 3, 4]);
+    let course = serde_json::to_value(database::get_course(&pool, 2).await.unwrap()).unwrap();
+    assert_eq!(course["sylbsUpdate"], "2024/03/14");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM lecturers").await, 3);
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM timetables").await, 0);
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM course_texts").await, 3);
+    assert_eq!(
+        count(
+            &pool,
+            "SELECT COUNT(*) FROM lecturers WHERE name_normalized IS NULL"
+        )
+        .await,
+        0
+    );
+    assert!(index_names(&pool)
+        .await
+        .iter()
+        .any(|name| name == "courses_code_year_title"));
+
+    let query = SearchQuery {
+        lecturer: vec!["山田".to_string()],
+        ignore_rules: true,
+        ..Default::default()
+    };
+    let response = database::search_courses(&pool, query).await.unwrap();
+    assert_eq!(response.total, 3);
+
+    // deleting a course now takes its lecturers along
+    sqlx::query("DELETE FROM courses WHERE id = 4")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM lecturers").await, 2);
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use pocket_syllabus_lib::database::{self, SearchQuery};
+use pocket_syllabus_lib::normalize::normalize;
+use sqlx::SqlitePool;
+
+#[test]
+fn normalize_folds_width_case_kana_and_whitespace() {
+    assert_eq!(normalize("ﾌﾟﾛｸﾞﾗﾐﾝｸﾞ"), "プログラミング");
+    assert_eq!(normalize("Ｃ言語"), "c言語");
+    assert_eq!(normalize("C言語"), "c言語");
+    assert_eq!(normalize("山田　太郎"), "山田太郎");
+    assert_eq!(normalize(" 山田 \t太郎 "), "山田太郎");
+    assert_eq!(normalize("きかいがくしゅう"), "キカイガクシュウ");
+    assert_eq!(normalize("ゝゞ"), "ヽヾ");
+    assert_eq!(normalize("Machine  Learning"), "machinelearning");
+    assert_eq!(normalize("ＡＢＣ１２３"), "abc123");
+}
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    let mut course = common::course("CSC.T101", "プログラミング第一", "情報工学系", 2024);
+    course.lecturer = vec![common::lecturer("山田 太郎")];
+    course.course_detail.abst = "C言語によるプログラミングの基礎を学ぶ。".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("CSC.T343", "きかいがくしゅう入門", "情報工学系", 2024);
+    course.lecturer = vec![common::lecturer("佐藤花子")];
+    course.course_detail.abst = "Machine Learning の基礎".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    pool
+}
+
+async fn codes(pool: &SqlitePool, query: SearchQuery) -> Vec<String> {
+    database::search_courses(pool, query)
+        .await
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|course| course.code)
+        .collect()
+}
+
+fn title(title: &str) -> SearchQuery {
+    SearchQuery {
+        title: vec![title.to_string()],
+        ..Default::default()
+    }
+}
+
+fn lecturer(lecturer: &str) -> SearchQuery {
+    SearchQuery {
+        lecturer: vec![lecturer.to_string()],
+        ..Default::default()
+    }
+}
+
+fn text(text: &str) -> SearchQuery {
+    SearchQuery {
+        text: text.to_string(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn title_and_lecturer_match_however_they_are_typed() {
+    let pool = seeded_pool().await;
+
+    assert_eq!(codes(&pool, title("ﾌﾟﾛｸﾞﾗﾐﾝｸﾞ")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, title("プログラミング 第一")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, title("キカイガクシュウ")).await, ["CSC.T343"]);
+
+    assert_eq!(codes(&pool, lecturer("山田太郎")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, lecturer("山田　太郎")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, lecturer("佐藤 花子")).await, ["CSC.T343"]);
+}
+
+#[tokio::test]
+async fn text_search_matches_normalized_syllabus_text() {
+    let pool = seeded_pool().await;
+
+    assert_eq!(codes(&pool, text("Ｃ言語")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, text("c言語")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, text("MACHINE")).await, ["CSC.T343"]);
+    assert_eq!(codes(&pool, text("machinelearning")).await, ["CSC.T343"]);
+    assert_eq!(codes(&pool, text("ｷｶｲｶﾞｸｼｭｳ")).await, ["CSC.T343"]);
+
+    // the snippet shows the syllabus as written, marked where the query appears verbatim
+    let courses = database::search_courses(&pool, text("C言語"))
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(
+        courses[0].snippet,
+        "<mark>C言語</mark>によるプログラミングの基礎を学ぶ。"
+    );
+    let courses = database::search_courses(&pool, text("Ｃ言語"))
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(courses[0].snippet, "");
+}
+
+#[tokio::test]
+async fn migrate_fills_in_rows_stored_before_normalization() {
+    let pool = seeded_pool().await;
+
+    sqlx::query("UPDATE courses SET title_normalized = NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE lecturers SET name_normalized = NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM course_texts_normalized")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(codes(&pool, title("ﾌﾟﾛｸﾞﾗﾐﾝｸﾞ")).await.is_empty());
+
+    database::migrate(&pool).await.unwrap();
+
+    assert_eq!(codes(&pool, title("ﾌﾟﾛｸﾞﾗﾐﾝｸﾞ")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, lecturer("山田太郎")).await, ["CSC.T101"]);
+    assert_eq!(codes(&pool, text("ｷｶｲｶﾞｸｼｭｳ")).await, ["CSC.T343"]);
+}
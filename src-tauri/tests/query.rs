@@ -0,0 +1,300 @@
+mod common;
+
+use pocket_syllabus_lib::database;
+use pocket_syllabus_lib::query::parse;
+use pocket_syllabus_lib::scrape::{Day, Period, Semester, TimeTable};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+
+// The parsed query as JSON, without the fields left at their defaults.
+fn parsed(input: &str) -> Value {
+    let mut value = serde_json::to_value(parse(input).unwrap()).unwrap();
+    strip_defaults(&mut value);
+    value
+}
+
+fn strip_defaults(value: &mut Value) {
+    if let Value::Object(map) = value {
+        map.retain(|key, value| match value {
+            _ if key == "order" => value != "Asc",
+            _ if key == "timetable_mode" => value != "Any",
+            _ if key == "offset" => value != 0,
+            Value::Array(items) => !items.is_empty(),
+            Value::String(text) => !text.is_empty(),
+            Value::Bool(flag) => *flag,
+            value => !value.is_null(),
+        });
+        for value in map.values_mut() {
+            if let Value::Array(items) = value {
+                items.iter_mut().for_each(strip_defaults);
+            }
+        }
+    }
+}
+
+fn error(input: &str) -> (usize, String) {
+    let error = parse(input).unwrap_err();
+    (error.position, error.message)
+}
+
+#[test]
+fn fields_fill_the_search_query() {
+    assert_eq!(
+        parsed("lecturer:山田 quarter:3Q-4Q -title:演習 credit>=2"),
+        json!({
+            "lecturer": ["山田"],
+            "quarter": ["Third", "Fourth"],
+            "credit_min": 2,
+            "none_of": [{ "title": ["演習"] }],
+        })
+    );
+
+    assert_eq!(
+        parsed("department:数学系,物理学系 grade:2 year:2024 slot:月1-2 credit<3"),
+        json!({
+            "department": ["数学系", "物理学系"],
+            "year": ["2024"],
+            "grade": ["200"],
+            "timetable": [{ "day": "Monday", "period": "First" }],
+            "credit_max": 2,
+        })
+    );
+
+    assert_eq!(
+        parsed("title:\"線形 代数\" 行列 \"固有 値\""),
+        json!({ "title": ["線形 代数"], "text": "行列 \"固有 値\"" })
+    );
+
+    let day = parsed("day:月");
+    assert_eq!(day["timetable"].as_array().unwrap().len(), 6);
+    assert_eq!(
+        day["timetable"][5],
+        json!({ "day": "Monday", "period": "Sixth" })
+    );
+}
+
+#[test]
+fn or_groups_and_repeated_fields() {
+    assert_eq!(
+        parsed("department:数学系 OR lecturer:山田"),
+        json!({
+            "any_of": [{ "department": ["数学系"] }, { "lecturer": ["山田"] }],
+        })
+    );
+
+    assert_eq!(
+        parsed("(title:線形 OR title:微分) -(quarter:1Q lecturer:佐藤)"),
+        json!({
+            "any_of": [{ "any_of": [{ "title": ["線形"] }, { "title": ["微分"] }] }],
+            "none_of": [{ "quarter": ["First"], "lecturer": ["佐藤"] }],
+        })
+    );
+
+    // a second title has to match as well, not widen the first
+    assert_eq!(
+        parsed("title:線形 title:代数 credit>=1 credit<=2"),
+        json!({
+            "title": ["線形"],
+            "credit_min": 1,
+            "credit_max": 2,
+            "any_of": [{ "title": ["代数"] }],
+        })
+    );
+
+    // only a standalone OR is an operator
+    assert_eq!(parsed("ORGANIC"), json!({ "text": "ORGANIC" }));
+}
+
+#[test]
+fn syntax_errors_point_at_the_problem() {
+    assert_eq!(
+        error("lecturer:山田 foo:bar"),
+        (12, "unknown field `foo`".to_string())
+    );
+    assert_eq!(
+        error("title:"),
+        (6, "missing value for `title`".to_string())
+    );
+    assert_eq!(error("quarter:5Q").0, 8);
+    assert_eq!(
+        error("quarter:4Q-1Q"),
+        (
+            8,
+            "`4Q-1Q` runs backwards, write the earlier quarter first".to_string()
+        )
+    );
+    assert_eq!(error("day:月 slot:月13").0, 11);
+    assert_eq!(
+        error("(title:線形 OR title:微分"),
+        (0, "unclosed `(`".to_string())
+    );
+    assert_eq!(error("title:線形)"), (8, "unexpected `)`".to_string()));
+    assert_eq!(error("title:\"線形"), (6, "unclosed `\"`".to_string()));
+    assert_eq!(error("credit>=two").0, 8);
+    assert_eq!(
+        error("credit>2147483647"),
+        (7, "`2147483647` is out of range".to_string())
+    );
+    assert_eq!(
+        error("credit<-2147483648"),
+        (7, "`-2147483648` is out of range".to_string())
+    );
+    assert_eq!(
+        error("title>=2"),
+        (7, "only `credit` can be compared".to_string())
+    );
+    assert_eq!(
+        error("title:線形 OR"),
+        (11, "expected a search term".to_string())
+    );
+    assert_eq!(error(""), (0, "expected a search term".to_string()));
+    assert_eq!(
+        error("- title:線形"),
+        (1, "expected a search term".to_string())
+    );
+
+    assert_eq!(
+        parse("foo:bar").unwrap_err().to_string(),
+        "syntax error at 0: unknown field `foo`"
+    );
+}
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    for (code, title, lecturer, credit, semester, day) in [
+        (
+            "MTH.A201",
+            "線形代数第一",
+            "山田 太郎",
+            2,
+            Semester::Third,
+            Day::Monday,
+        ),
+        (
+            "MTH.A202",
+            "線形代数演習",
+            "山田 太郎",
+            1,
+            Semester::Third,
+            Day::Monday,
+        ),
+        (
+            "MTH.B301",
+            "微分積分",
+            "山田 太郎",
+            2,
+            Semester::First,
+            Day::Monday,
+        ),
+        (
+            "PHY.Q207",
+            "量子力学",
+            "佐藤 花子",
+            2,
+            Semester::Fourth,
+            Day::Wednesday,
+        ),
+    ] {
+        let mut course = common::course(code, title, "数学系", 2024);
+        course.lecturer = vec![common::lecturer(lecturer)];
+        course.credit = credit;
+        course.semester = vec![semester];
+        course.time_table = vec![TimeTable {
+            day,
+            period: Period::Second,
+            room: "W521".to_string(),
+        }];
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    pool
+}
+
+async fn search(pool: &SqlitePool, input: &str) -> Vec<String> {
+    database::search_courses(pool, parse(input).unwrap())
+        .await
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|course| course.code)
+        .collect()
+}
+
+#[tokio::test]
+async fn parsed_queries_select_courses() {
+    let pool = seeded_pool().await;
+
+    assert_eq!(
+        search(
+            &pool,
+            "lecturer:山田 quarter:3Q-4Q day:月 -title:演習 credit>=2"
+        )
+        .await,
+        ["MTH.A201"]
+    );
+    assert_eq!(
+        search(&pool, "lecturer:佐藤 OR title:微分").await,
+        ["MTH.B301", "PHY.Q207"]
+    );
+    assert_eq!(search(&pool, "title:線形 title:演習").await, ["MTH.A202"]);
+    assert_eq!(
+        search(&pool, "-(lecturer:山田 quarter:3Q)").await,
+        ["MTH.B301", "PHY.Q207"]
+    );
+    assert_eq!(
+        search(&pool, "day:水 OR credit<2").await,
+        ["MTH.A202", "PHY.Q207"]
+    );
+    assert_eq!(
+        search(&pool, "slot:月3-4 -title:微分").await,
+        ["MTH.A201", "MTH.A202"]
+    );
+}
+
+#[tokio::test]
+async fn quoted_phrases_match_as_one_term() {
+    let pool = common::memory_pool().await;
+    for (code, abst) in [
+        ("MTH.A201", "線形代数の基礎を学ぶ。"),
+        ("MTH.A202", "線形写像と代数構造を学ぶ。"),
+    ] {
+        let mut course = common::course(code, code, "数学系", 2024);
+        course.course_detail.abst = abst.to_string();
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    assert_eq!(search(&pool, "線形 代数").await, ["MTH.A201", "MTH.A202"]);
+    assert_eq!(search(&pool, "\"線形 代数\"").await, ["MTH.A201"]);
+    assert_eq!(search(&pool, "text:\"線形 代数\"").await, ["MTH.A201"]);
+    assert_eq!(search(&pool, "\"写像と 代数\"").await, ["MTH.A202"]);
+}
+
+#[test]
+fn free_slots_select_the_within_mode() {
+    assert_eq!(
+        parsed("free:月1-2,木3-4"),
+        json!({
+            "timetable": [
+                { "day": "Monday", "period": "First" },
+                { "day": "Thursday", "period": "Second" },
+            ],
+            "timetable_mode": "Within",
+        })
+    );
+}
+
+#[test]
+fn detail_fields() {
+    assert_eq!(
+        parsed("lang:英語 type:講義,演習 keyword:行列 competency:専門力 credit:2"),
+        json!({
+            "language": ["英語"],
+            "lecture_type": ["講義", "演習"],
+            "keyword": ["行列"],
+            "competency": ["専門力"],
+            "credit_min": 2,
+            "credit_max": 2,
+        })
+    );
+}
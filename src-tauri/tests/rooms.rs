@@ -0,0 +1,262 @@
+mod common;
+
+use pocket_syllabus_lib::database::{self, Campus, Room};
+use pocket_syllabus_lib::error::AppError;
+use pocket_syllabus_lib::scrape::{Day, Period, Semester, TimeTable};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+
+fn room(name: &str, building: Option<&str>, campus: Option<Campus>) -> Room {
+    Room {
+        name: name.to_string(),
+        building: building.map(String::from),
+        campus,
+    }
+}
+
+async fn insert(
+    pool: &SqlitePool,
+    code: &str,
+    year: i32,
+    semester: Vec<Semester>,
+    time_table: &[(Day, &str)],
+) {
+    let mut course = common::course(code, code, "数学系", year);
+    course.semester = semester;
+    course.time_table = time_table
+        .iter()
+        .map(|(day, room)| TimeTable {
+            day: day.clone(),
+            period: Period::First,
+            room: room.to_string(),
+        })
+        .collect();
+    database::insert_course(pool, &course).await.unwrap();
+}
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+    insert(
+        &pool,
+        "MTH.A201",
+        2024,
+        vec![Semester::Third, Semester::Fourth],
+        &[
+            (Day::Monday, "W8E-101"),
+            (Day::Thursday, "W8E-101，W8E-102"),
+        ],
+    )
+    .await;
+    insert(
+        &pool,
+        "MTH.A202",
+        2024,
+        vec![Semester::Third],
+        &[(Day::Monday, "W8E-101")],
+    )
+    .await;
+    insert(
+        &pool,
+        "MTH.A201",
+        2023,
+        vec![Semester::Third],
+        &[(Day::Friday, "W8E-101")],
+    )
+    .await;
+    insert(
+        &pool,
+        "MTH.C101",
+        2024,
+        vec![Semester::First],
+        &[(Day::Monday, "すずかけ台 J221")],
+    )
+    .await;
+    insert(
+        &pool,
+        "LAS.A101",
+        2024,
+        vec![Semester::Third],
+        &[(Day::Tuesday, "Zoom")],
+    )
+    .await;
+    pool
+}
+
+#[test]
+fn room_strings_split_into_rooms_with_building_and_campus() {
+    let ookayama = Some(Campus::Ookayama);
+
+    assert_eq!(
+        database::parse_rooms("W8E-101"),
+        [room("W8E-101", Some("W8E"), ookayama)]
+    );
+    assert_eq!(
+        database::parse_rooms("W631，W641"),
+        [
+            room("W631", Some("W6"), ookayama),
+            room("W641", Some("W6"), ookayama)
+        ]
+    );
+    assert_eq!(
+        database::parse_rooms("H121"),
+        [room("H121", Some("H"), ookayama)]
+    );
+    assert_eq!(
+        database::parse_rooms("すずかけ台 J221"),
+        [room("J221", Some("J2"), Some(Campus::Suzukakedai))]
+    );
+    assert_eq!(
+        database::parse_rooms("Zoom / W631"),
+        [room("W631", Some("W6"), ookayama)]
+    );
+    assert_eq!(
+        database::parse_rooms("Zoom"),
+        [room("Zoom", None, Some(Campus::Online))]
+    );
+    assert_eq!(
+        database::parse_rooms("石川台7号館 201、情報演習室"),
+        [
+            room("石川台7号館 201", None, ookayama),
+            room("情報演習室", None, ookayama)
+        ]
+    );
+    assert!(database::parse_rooms("").is_empty());
+}
+
+#[tokio::test]
+async fn rooms_are_listed_by_campus_and_building() {
+    let pool = seeded_pool().await;
+
+    let names = |rooms: Vec<Room>| rooms.into_iter().map(|room| room.name).collect::<Vec<_>>();
+    assert_eq!(
+        names(database::get_rooms(&pool, None).await.unwrap()),
+        ["Zoom", "W8E-101", "W8E-102", "J221"]
+    );
+    assert_eq!(
+        names(
+            database::get_rooms(&pool, Some(Campus::Suzukakedai))
+                .await
+                .unwrap()
+        ),
+        ["J221"]
+    );
+}
+
+#[tokio::test]
+async fn occupancy_lists_each_quarter_slot_of_the_year() {
+    let pool = seeded_pool().await;
+
+    let occupancy = database::get_room_occupancy(&pool, "W8E-101", None)
+        .await
+        .unwrap();
+    assert_eq!(occupancy.year, 2024);
+    assert_eq!(
+        occupancy.room,
+        room("W8E-101", Some("W8E"), Some(Campus::Ookayama))
+    );
+    let slots: Vec<(Value, Value, Vec<&str>)> = occupancy
+        .slots
+        .iter()
+        .map(|slot| {
+            (
+                serde_json::to_value(&slot.quarter).unwrap(),
+                serde_json::to_value(&slot.day).unwrap(),
+                slot.courses
+                    .iter()
+                    .map(|course| course.code.as_str())
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        slots,
+        [
+            (
+                json!("Third"),
+                json!("Monday"),
+                vec!["MTH.A201", "MTH.A202"]
+            ),
+            (json!("Third"), json!("Thursday"), vec!["MTH.A201"]),
+            (json!("Fourth"), json!("Monday"), vec!["MTH.A201"]),
+            (json!("Fourth"), json!("Thursday"), vec!["MTH.A201"]),
+        ]
+    );
+
+    let occupancy = database::get_room_occupancy(&pool, "W8E-101", Some(2023))
+        .await
+        .unwrap();
+    assert_eq!(occupancy.slots.len(), 1);
+
+    let error = database::get_room_occupancy(&pool, "W9-999", None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn empty_rooms_leave_out_used_and_online_ones() {
+    let pool = seeded_pool().await;
+    // a room written as free text is no place to go to
+    insert(
+        &pool,
+        "MTH.C102",
+        2024,
+        vec![Semester::Second],
+        &[(Day::Wednesday, "未定")],
+    )
+    .await;
+    let empty = |day, quarter, campus| {
+        let pool = pool.clone();
+        async move {
+            database::find_empty_rooms(&pool, &day, &Period::First, &quarter, None, campus)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|room| room.name)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        empty(Day::Monday, Semester::Third, None).await,
+        ["W8E-102", "J221"]
+    );
+    assert_eq!(empty(Day::Thursday, Semester::Fourth, None).await, ["J221"]);
+    assert_eq!(
+        empty(Day::Monday, Semester::First, None).await,
+        ["W8E-101", "W8E-102"]
+    );
+    assert_eq!(
+        empty(Day::Friday, Semester::Third, Some(Campus::Ookayama)).await,
+        ["W8E-101", "W8E-102"]
+    );
+}
+
+#[tokio::test]
+async fn rooms_follow_their_timetables() {
+    let pool = seeded_pool().await;
+    let links = |pool: SqlitePool| async move {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM timetable_rooms")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+    };
+    assert_eq!(links(pool.clone()).await, 7);
+
+    // rows from before the rooms table are linked when the database is opened
+    sqlx::query("DELETE FROM rooms")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(links(pool.clone()).await, 0);
+    database::migrate(&pool).await.unwrap();
+    assert_eq!(links(pool.clone()).await, 7);
+    assert_eq!(database::get_rooms(&pool, None).await.unwrap().len(), 4);
+
+    // replacing a course drops its old links, the rooms stay known
+    let mut course = common::course("MTH.A202", "MTH.A202", "数学系", 2024);
+    course.semester = vec![Semester::Third];
+    database::replace_course(&pool, &course).await.unwrap();
+    assert_eq!(links(pool.clone()).await, 6);
+    assert_eq!(database::get_rooms(&pool, None).await.unwrap().len(), 4);
+}
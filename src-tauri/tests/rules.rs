@@ -0,0 +1,261 @@
+mod common;
+
+use pocket_syllabus_lib::database::{
+    self, RuleAction, RuleField, RuleMatch, SearchQuery, SearchResponse, SearchRule,
+};
+use pocket_syllabus_lib::error::AppError;
+use pocket_syllabus_lib::scrape::{Day, Period, Semester, TimeTable};
+use sqlx::SqlitePool;
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    for (code, department, lecturer, semester) in [
+        ("MTH.A201", "数学系", "山田 太郎", Semester::First),
+        ("LAH.T101", "文系教養科目", "各教員", Semester::Second),
+        ("LAH.S102", "文系教養科目", "佐藤 花子", Semester::Third),
+        ("PHY.Q207", "物理学系", "教員未定", Semester::Fourth),
+    ] {
+        let mut course = common::course(code, code, department, 2024);
+        course.lecturer = vec![common::lecturer(lecturer)];
+        course.semester = vec![semester];
+        course.time_table = vec![TimeTable {
+            day: Day::Tuesday,
+            period: Period::Third,
+            room: "W521".to_string(),
+        }];
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    pool
+}
+
+fn rule(action: RuleAction, field: RuleField, match_kind: RuleMatch, pattern: &str) -> SearchRule {
+    SearchRule {
+        id: 0,
+        name: pattern.to_string(),
+        action,
+        field,
+        match_kind,
+        pattern: pattern.to_string(),
+        enabled: true,
+    }
+}
+
+async fn search(pool: &SqlitePool, query: SearchQuery) -> (Vec<String>, Vec<(String, i64)>) {
+    let SearchResponse { items, hidden, .. } = database::search_courses(pool, query).await.unwrap();
+    (
+        items.into_iter().map(|course| course.code).collect(),
+        hidden
+            .into_iter()
+            .map(|hidden| (hidden.name, hidden.count))
+            .collect(),
+    )
+}
+
+async fn remove_default_rule(pool: &SqlitePool) {
+    for rule in database::get_search_rules(pool).await.unwrap() {
+        database::delete_search_rule(pool, rule.id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn default_rules_hide_placeholder_lecturers_and_can_be_disabled() {
+    let pool = seeded_pool().await;
+
+    let (codes, hidden) = search(&pool, SearchQuery::default()).await;
+    assert_eq!(codes, ["MTH.A201", "LAH.S102"]);
+    assert_eq!(
+        hidden,
+        [
+            ("担当教員が各教員の講義".to_string(), 1),
+            ("担当教員が未定の講義".to_string(), 0),
+            ("担当教員が教員未定の講義".to_string(), 1)
+        ]
+    );
+
+    // hidden counts follow the other filters
+    let query = SearchQuery {
+        department: vec!["文系教養科目".to_string()],
+        ..Default::default()
+    };
+    let (codes, hidden) = search(&pool, query).await;
+    assert_eq!(codes, ["LAH.S102"]);
+    assert_eq!(
+        hidden,
+        [
+            ("担当教員が各教員の講義".to_string(), 1),
+            ("担当教員が未定の講義".to_string(), 0),
+            ("担当教員が教員未定の講義".to_string(), 0)
+        ]
+    );
+
+    let query = SearchQuery {
+        ignore_rules: true,
+        ..Default::default()
+    };
+    let (codes, hidden) = search(&pool, query).await;
+    assert_eq!(codes.len(), 4);
+    assert!(hidden.is_empty());
+
+    let rules = database::get_search_rules(&pool).await.unwrap();
+    assert_eq!(
+        rules
+            .iter()
+            .map(|rule| (rule.match_kind, rule.pattern.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (RuleMatch::Equals, "各教員"),
+            (RuleMatch::Equals, "未定"),
+            (RuleMatch::Equals, "教員未定")
+        ]
+    );
+    for mut rule in rules {
+        rule.enabled = false;
+        database::update_search_rule(&pool, &rule).await.unwrap();
+    }
+
+    let (codes, hidden) = search(&pool, SearchQuery::default()).await;
+    assert_eq!(codes.len(), 4);
+    assert!(hidden.is_empty());
+}
+
+#[tokio::test]
+async fn default_rules_leave_real_names_alone() {
+    let pool = common::memory_pool().await;
+
+    for (code, lecturers) in [
+        ("EDU.A101", vec!["教員免許センター"]),
+        ("MTH.C301", vec!["山田 太郎", "未定"]),
+    ] {
+        let mut course = common::course(code, code, "数学系", 2024);
+        course.lecturer = lecturers.into_iter().map(common::lecturer).collect();
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    let (codes, hidden) = search(&pool, SearchQuery::default()).await;
+    assert_eq!(codes, ["EDU.A101"]);
+    assert_eq!(
+        hidden,
+        [
+            ("担当教員が各教員の講義".to_string(), 0),
+            ("担当教員が未定の講義".to_string(), 1),
+            ("担当教員が教員未定の講義".to_string(), 0)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn include_rules_bring_back_excluded_courses() {
+    let pool = seeded_pool().await;
+
+    let include = rule(
+        RuleAction::Include,
+        RuleField::Department,
+        RuleMatch::Equals,
+        "物理学系",
+    );
+    database::add_search_rule(&pool, &include).await.unwrap();
+
+    let (codes, hidden) = search(&pool, SearchQuery::default()).await;
+    assert_eq!(codes, ["MTH.A201", "LAH.S102", "PHY.Q207"]);
+    assert_eq!(
+        hidden,
+        [
+            ("担当教員が各教員の講義".to_string(), 1),
+            ("担当教員が未定の講義".to_string(), 0),
+            ("担当教員が教員未定の講義".to_string(), 0)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rules_match_any_list_item_field() {
+    let pool = seeded_pool().await;
+    remove_default_rule(&pool).await;
+
+    for (field, match_kind, pattern, expected) in [
+        (
+            RuleField::Code,
+            RuleMatch::Glob,
+            "LAH.*",
+            vec!["MTH.A201", "PHY.Q207"],
+        ),
+        (
+            RuleField::Lecturer,
+            RuleMatch::Equals,
+            "各教員",
+            vec!["MTH.A201", "LAH.S102", "PHY.Q207"],
+        ),
+        (
+            RuleField::Semester,
+            RuleMatch::Equals,
+            "1Q",
+            vec!["LAH.T101", "LAH.S102", "PHY.Q207"],
+        ),
+        (RuleField::Timetable, RuleMatch::Contains, "火5-6", vec![]),
+        (
+            RuleField::University,
+            RuleMatch::Contains,
+            "一橋",
+            vec!["MTH.A201", "LAH.T101", "LAH.S102", "PHY.Q207"],
+        ),
+    ] {
+        let id = database::add_search_rule(
+            &pool,
+            &rule(RuleAction::Exclude, field, match_kind, pattern),
+        )
+        .await
+        .unwrap();
+
+        let (codes, hidden) = search(&pool, SearchQuery::default()).await;
+        assert_eq!(codes, expected, "{:?} {}", field, pattern);
+        assert_eq!(hidden, [(pattern.to_string(), 4 - expected.len() as i64)]);
+
+        database::delete_search_rule(&pool, id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn rules_are_stored_and_edited() {
+    let pool = seeded_pool().await;
+    remove_default_rule(&pool).await;
+
+    let mut code = rule(
+        RuleAction::Exclude,
+        RuleField::Code,
+        RuleMatch::Glob,
+        "LAH.*",
+    );
+    code.id = database::add_search_rule(&pool, &code).await.unwrap();
+    let credit = rule(
+        RuleAction::Exclude,
+        RuleField::Credit,
+        RuleMatch::Equals,
+        "2",
+    );
+    database::add_search_rule(&pool, &credit).await.unwrap();
+
+    let rules = database::get_search_rules(&pool).await.unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].id, code.id);
+    assert_eq!(rules[0].field, RuleField::Code);
+    assert_eq!(rules[0].match_kind, RuleMatch::Glob);
+    assert_eq!(rules[1].action, RuleAction::Exclude);
+    assert_eq!(rules[1].pattern, "2");
+
+    code.pattern = "MTH.*".to_string();
+    database::update_search_rule(&pool, &code).await.unwrap();
+    let rules = database::get_search_rules(&pool).await.unwrap();
+    assert_eq!(rules[0].pattern, "MTH.*");
+
+    code.id = 999;
+    assert!(matches!(
+        database::update_search_rule(&pool, &code).await,
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        database::delete_search_rule(&pool, 999).await,
+        Err(AppError::NotFound(_))
+    ));
+}
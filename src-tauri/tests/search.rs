@@ -0,0 +1,751 @@
+mod common;
+
+use pocket_syllabus_lib::database::{
+    self, CourseListItem, FacetCount, SearchQuery, SortKey, SortOrder, TimetableMode,
+    TimetableQuery,
+};
+use pocket_syllabus_lib::scrape::{Day, LecturePlan, Period, Semester, TimeTable};
+use sqlx::SqlitePool;
+
+async fn seeded_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    let mut course = common::course("MTH.A201", "線形代数第一", "数学系", 2024);
+    course.lecturer = vec![common::lecturer("山田 太郎")];
+    course.time_table = vec![TimeTable {
+        day: Day::Monday,
+        period: Period::First,
+        room: "W521".to_string(),
+    }];
+    course.semester = vec![Semester::First];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("LAH.S101", "Women's Studies", "O'Neill Institute", 2024);
+    course.lecturer = vec![common::lecturer("O'Brien")];
+    course.time_table = vec![TimeTable {
+        day: Day::Friday,
+        period: Period::Third,
+        room: "S221".to_string(),
+    }];
+    course.semester = vec![Semester::Third];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("PHY.Q207", "量子力学基礎", "物理学系", 2023);
+    course.lecturer = vec![common::lecturer("佐藤 花子")];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    pool
+}
+
+async fn search_codes(pool: &SqlitePool, query: SearchQuery) -> Vec<String> {
+    database::search_courses(pool, query)
+        .await
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|course| course.code)
+        .collect()
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+#[tokio::test]
+async fn apostrophes_are_matched_literally() {
+    let pool = seeded_pool().await;
+
+    let query = SearchQuery {
+        department: strings(&["O'Neill Institute"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["LAH.S101"]);
+
+    let query = SearchQuery {
+        title: strings(&["Women's"]),
+        lecturer: strings(&["O'Brien"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["LAH.S101"]);
+}
+
+#[tokio::test]
+async fn hostile_values_do_not_change_the_query() {
+    let pool = seeded_pool().await;
+
+    for hostile in [
+        "' OR '1'='1",
+        "x') OR ('1'='1",
+        "'); DROP TABLE courses; --",
+        "東京工業大学' --",
+        "\" OR 1=1 --",
+    ] {
+        for query in [
+            SearchQuery {
+                university: strings(&[hostile]),
+                ..Default::default()
+            },
+            SearchQuery {
+                department: strings(&[hostile]),
+                ..Default::default()
+            },
+            SearchQuery {
+                year: strings(&[hostile]),
+                ..Default::default()
+            },
+            SearchQuery {
+                title: strings(&[hostile]),
+                ..Default::default()
+            },
+            SearchQuery {
+                lecturer: strings(&[hostile]),
+                ..Default::default()
+            },
+        ] {
+            let query_text = format!("{:?}", query);
+            assert!(
+                search_codes(&pool, query).await.is_empty(),
+                "{} matched something",
+                query_text
+            );
+        }
+    }
+
+    assert_eq!(database::get_course_ids(&pool).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn hostile_value_next_to_a_valid_one_only_matches_the_valid_one() {
+    let pool = seeded_pool().await;
+
+    let query = SearchQuery {
+        university: strings(&["東京工業大学"]),
+        department: strings(&["数学系", "') OR 1=1 --"]),
+        year: strings(&["2024", "2023' OR '1'='1"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A201"]);
+}
+
+#[tokio::test]
+async fn bound_filters_still_select_courses() {
+    let pool = seeded_pool().await;
+
+    let query = SearchQuery {
+        year: strings(&["2023"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["PHY.Q207"]);
+
+    let query = SearchQuery {
+        timetable: vec![TimetableQuery {
+            day: Day::Friday,
+            period: Period::Third,
+        }],
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["LAH.S101"]);
+
+    let query = SearchQuery {
+        quarter: vec![Semester::First],
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A201"]);
+
+    let query = SearchQuery {
+        grade: strings(&["200"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A201", "PHY.Q207"]);
+}
+
+#[tokio::test]
+async fn list_items_join_lecturers_timetables_and_semesters() {
+    let pool = common::memory_pool().await;
+
+    let mut course = common::course("PHY.Q207", "量子力学基礎", "物理学系", 2024);
+    course.lecturer = vec![common::lecturer("佐藤 花子"), common::lecturer("鈴木 一郎")];
+    course.time_table = vec![
+        TimeTable {
+            day: Day::Tuesday,
+            period: Period::Third,
+            room: "S221".to_string(),
+        },
+        TimeTable {
+            day: Day::Friday,
+            period: Period::Sixth,
+            room: "S221".to_string(),
+        },
+    ];
+    course.semester = vec![Semester::Third, Semester::Fourth];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("LAH.T101", "文系エッセンス", "文系教養科目", 2024);
+    course.lecturer = vec![common::lecturer("各教員")];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let courses = database::search_courses(&pool, SearchQuery::default())
+        .await
+        .unwrap()
+        .items;
+
+    assert_eq!(courses.len(), 1);
+    assert_eq!(courses[0].lecturer, "佐藤 花子, 鈴木 一郎");
+    assert_eq!(courses[0].timetable, "火5-6, 金11-12");
+    assert_eq!(courses[0].semester, "3Q, 4Q");
+    assert_eq!(courses[0].credit, 2);
+}
+
+async fn text_search(pool: &SqlitePool, text: &str) -> Vec<CourseListItem> {
+    let query = SearchQuery {
+        text: text.to_string(),
+        ..Default::default()
+    };
+    database::search_courses(pool, query).await.unwrap().items
+}
+
+#[tokio::test]
+async fn text_search_looks_through_the_whole_syllabus() {
+    let pool = common::memory_pool().await;
+
+    let mut course = common::course("MTH.A201", "線形代数第一", "数学系", 2024);
+    course.course_detail.abst = "行列の基礎を学び、機械学習への応用にも触れる。".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("CSC.T343", "パターン認識", "情報工学系", 2024);
+    course.course_detail.keyword = vec!["機械学習".to_string(), "統計".to_string()];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("CSC.T352", "データ解析", "情報工学系", 2024);
+    course.course_detail.schedule = vec![LecturePlan {
+        count: 3,
+        plan: "機械学習の入門".to_string(),
+        assignment: "".to_string(),
+    }];
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("CSC.T101", "プログラミング", "情報工学系", 2024);
+    course.course_detail.textbook = "はじめての機械学習".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("PHY.Q207", "量子力学基礎", "物理学系", 2024);
+    course.course_detail.goal = "量子力学の基本を理解する。".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut codes = text_search(&pool, "機械学習")
+        .await
+        .into_iter()
+        .map(|course| course.code)
+        .collect::<Vec<_>>();
+    codes.sort();
+    assert_eq!(codes, ["CSC.T101", "CSC.T343", "CSC.T352", "MTH.A201"]);
+
+    let courses = text_search(&pool, "行列 機械学習").await;
+    assert_eq!(courses.len(), 1);
+    assert_eq!(
+        courses[0].snippet,
+        "行列の基礎を学び、<mark>機械学習</mark>への応用にも触れる。"
+    );
+
+    // shorter than a trigram
+    let courses = text_search(&pool, "量子").await;
+    assert_eq!(courses.len(), 1);
+    assert_eq!(courses[0].code, "PHY.Q207");
+    assert_eq!(courses[0].snippet, "");
+
+    assert!(text_search(&pool, "有機化学").await.is_empty());
+}
+
+#[tokio::test]
+async fn text_search_ranks_closer_matches_first() {
+    let pool = common::memory_pool().await;
+
+    let mut course = common::course("CSC.T101", "プログラミング", "情報工学系", 2024);
+    course.course_detail.textbook =
+        "計算機科学の教科書。アルゴリズムとデータ構造、オペレーティングシステム、ネットワーク、深層学習を扱う。"
+            .to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("CSC.T343", "深層学習", "情報工学系", 2024);
+    course.course_detail.abst = "深層学習の理論と深層学習の実装を学ぶ。".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let codes = text_search(&pool, "深層学習")
+        .await
+        .into_iter()
+        .map(|course| course.code)
+        .collect::<Vec<_>>();
+    assert_eq!(codes, ["CSC.T343", "CSC.T101"]);
+}
+
+#[tokio::test]
+async fn short_terms_do_not_match_across_fields() {
+    let pool = common::memory_pool().await;
+
+    // the title ends in 数 and the abstract starts with 学
+    let mut course = common::course("MTH.A201", "線形代数", "数学系", 2024);
+    course.course_detail.abst = "学習の基礎として行列を扱う。".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let mut course = common::course("MTH.B301", "集合と位相", "数学系", 2024);
+    course.course_detail.goal = "数学の言葉に慣れる。".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+
+    let courses = text_search(&pool, "数学").await;
+    assert_eq!(courses.len(), 1);
+    assert_eq!(courses[0].code, "MTH.B301");
+}
+
+#[tokio::test]
+async fn text_index_follows_replaced_courses() {
+    let pool = common::memory_pool().await;
+
+    let mut course = common::course("MTH.A201", "線形代数第一", "数学系", 2024);
+    course.course_detail.abst = "機械学習への応用".to_string();
+    database::insert_course(&pool, &course).await.unwrap();
+    assert_eq!(text_search(&pool, "機械学習").await.len(), 1);
+
+    course.course_detail.abst = "固有値と対角化".to_string();
+    database::replace_course(&pool, &course).await.unwrap();
+
+    assert!(text_search(&pool, "機械学習").await.is_empty());
+    assert_eq!(text_search(&pool, "対角化").await.len(), 1);
+}
+
+#[tokio::test]
+async fn text_search_syntax_is_not_interpreted() {
+    let pool = seeded_pool().await;
+
+    for hostile in [
+        "\"",
+        "機械学習\" OR \"線形",
+        "title: NEAR(a b)",
+        "*",
+        "'); DROP TABLE course_texts; --",
+    ] {
+        assert!(text_search(&pool, hostile).await.is_empty(), "{}", hostile);
+    }
+
+    assert_eq!(text_search(&pool, "線形代数").await.len(), 1);
+}
+
+async fn paging_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    for (code, title, department, credit, updated) in [
+        ("MTH.A201", "線形代数第一", "数学系", 2, "2024/03/01"),
+        ("CSC.T343", "パターン認識", "情報工学系", 1, "2024/04/15"),
+        ("PHY.Q207", "量子力学基礎", "物理学系", 4, "2023/12/20"),
+        ("ART.B101", "アート入門", "芸術系", 3, "2024/01/10"),
+    ] {
+        let mut course = common::course(code, title, department, 2024);
+        course.credit = credit;
+        course.sylbs_update = updated.to_string();
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    pool
+}
+
+fn sorted(sort: SortKey, order: SortOrder) -> SearchQuery {
+    SearchQuery {
+        sort: Some(sort),
+        order,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn results_sort_by_the_requested_key_and_direction() {
+    let pool = paging_pool().await;
+
+    assert_eq!(
+        search_codes(&pool, sorted(SortKey::Code, SortOrder::Asc)).await,
+        ["ART.B101", "CSC.T343", "MTH.A201", "PHY.Q207"]
+    );
+    assert_eq!(
+        search_codes(&pool, sorted(SortKey::Code, SortOrder::Desc)).await,
+        ["PHY.Q207", "MTH.A201", "CSC.T343", "ART.B101"]
+    );
+    assert_eq!(
+        search_codes(&pool, sorted(SortKey::Title, SortOrder::Asc)).await,
+        ["ART.B101", "CSC.T343", "MTH.A201", "PHY.Q207"]
+    );
+    assert_eq!(
+        search_codes(&pool, sorted(SortKey::Credit, SortOrder::Desc)).await,
+        ["PHY.Q207", "ART.B101", "MTH.A201", "CSC.T343"]
+    );
+    assert_eq!(
+        search_codes(&pool, sorted(SortKey::Department, SortOrder::Asc)).await,
+        ["CSC.T343", "MTH.A201", "PHY.Q207", "ART.B101"]
+    );
+    assert_eq!(
+        search_codes(&pool, sorted(SortKey::LastUpdated, SortOrder::Desc)).await,
+        ["CSC.T343", "MTH.A201", "ART.B101", "PHY.Q207"]
+    );
+}
+
+#[tokio::test]
+async fn pages_slice_the_results_and_total_counts_every_match() {
+    let pool = paging_pool().await;
+
+    let page = |offset, limit| SearchQuery {
+        offset,
+        limit,
+        sort: Some(SortKey::Code),
+        ..Default::default()
+    };
+
+    let response = database::search_courses(&pool, page(0, Some(3)))
+        .await
+        .unwrap();
+    assert_eq!(response.total, 4);
+    assert_eq!(response.items.len(), 3);
+    assert_eq!(response.items[0].code, "ART.B101");
+
+    let response = database::search_courses(&pool, page(3, Some(3)))
+        .await
+        .unwrap();
+    assert_eq!(response.total, 4);
+    let codes: Vec<String> = response.items.into_iter().map(|item| item.code).collect();
+    assert_eq!(codes, ["PHY.Q207"]);
+
+    assert_eq!(
+        search_codes(&pool, page(2, None)).await,
+        ["MTH.A201", "PHY.Q207"]
+    );
+
+    let response = database::search_courses(&pool, page(10, Some(3)))
+        .await
+        .unwrap();
+    assert_eq!(response.total, 4);
+    assert!(response.items.is_empty());
+
+    // filters narrow the total as well as the page
+    let query = SearchQuery {
+        department: strings(&["数学系", "物理学系"]),
+        limit: Some(1),
+        ..Default::default()
+    };
+    let response = database::search_courses(&pool, query).await.unwrap();
+    assert_eq!(response.total, 2);
+    assert_eq!(response.items.len(), 1);
+}
+
+#[tokio::test]
+async fn paging_fields_are_optional_in_the_request() {
+    let query: SearchQuery = serde_json::from_str(
+        r#"{"university": [], "department": [], "year": [], "title": [], "lecturer": [],
+            "grade": [], "quarter": [], "timetable": []}"#,
+    )
+    .unwrap();
+    assert_eq!(query.offset, 0);
+    assert_eq!(query.limit, None);
+    assert!(query.sort.is_none());
+
+    let query: SearchQuery = serde_json::from_str(
+        r#"{"university": [], "department": [], "year": [], "title": [], "lecturer": [],
+            "grade": [], "quarter": [], "timetable": [],
+            "offset": 50, "limit": 50, "sort": "LastUpdated", "order": "Desc"}"#,
+    )
+    .unwrap();
+    assert_eq!(query.offset, 50);
+    assert_eq!(query.limit, Some(50));
+    assert!(matches!(query.sort, Some(SortKey::LastUpdated)));
+    assert!(matches!(query.order, SortOrder::Desc));
+}
+
+fn counts(facet: &[FacetCount]) -> Vec<(&str, i64)> {
+    facet
+        .iter()
+        .map(|count| (count.value.as_str(), count.count))
+        .collect()
+}
+
+#[tokio::test]
+async fn facets_count_every_value_under_the_other_filters() {
+    let pool = common::memory_pool().await;
+
+    for (code, department, credit, language, semesters, slots) in [
+        (
+            "MTH.A201",
+            "数学系",
+            2,
+            "日本語",
+            vec![Semester::First],
+            vec![(Day::Monday, Period::First)],
+        ),
+        (
+            "MTH.A301",
+            "数学系",
+            1,
+            "英語",
+            vec![Semester::Third, Semester::Fourth],
+            vec![
+                (Day::Monday, Period::First),
+                (Day::Thursday, Period::Second),
+            ],
+        ),
+        (
+            "PHY.Q207",
+            "物理学系",
+            2,
+            "日本語",
+            vec![Semester::Third],
+            vec![(Day::Tuesday, Period::Third)],
+        ),
+    ] {
+        let mut course = common::course(code, code, department, 2024);
+        course.credit = credit;
+        course.language = language.to_string();
+        course.lecture_type = "講義".to_string();
+        course.semester = semesters;
+        course.time_table = slots
+            .into_iter()
+            .map(|(day, period)| TimeTable {
+                day,
+                period,
+                room: "W521".to_string(),
+            })
+            .collect();
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    let query = SearchQuery {
+        department: strings(&["数学系"]),
+        quarter: vec![Semester::Third],
+        ..Default::default()
+    };
+    let response = database::search_courses(&pool, query).await.unwrap();
+    assert_eq!(response.total, 1);
+
+    let facets = response.facets;
+    // the department facet ignores the department filter but keeps the quarter
+    assert_eq!(counts(&facets.department), [("数学系", 1), ("物理学系", 1)]);
+    // and the quarter facet the other way round
+    assert_eq!(counts(&facets.quarter), [("1Q", 1), ("3Q", 1), ("4Q", 1)]);
+    // facets without a filter of their own count only the matching course
+    assert_eq!(counts(&facets.slot), [("月1-2", 1), ("木3-4", 1)]);
+    assert_eq!(counts(&facets.grade), [("300", 1)]);
+    assert_eq!(counts(&facets.credit), [("1", 1)]);
+    assert_eq!(counts(&facets.language), [("英語", 1)]);
+    assert_eq!(counts(&facets.lecture_type), [("講義", 1)]);
+
+    let response = database::search_courses(&pool, SearchQuery::default())
+        .await
+        .unwrap();
+    let facets = response.facets;
+    assert_eq!(counts(&facets.department), [("数学系", 2), ("物理学系", 1)]);
+    assert_eq!(
+        counts(&facets.slot),
+        [("月1-2", 2), ("火5-6", 1), ("木3-4", 1)]
+    );
+    assert_eq!(counts(&facets.grade), [("200", 2), ("300", 1)]);
+    assert_eq!(counts(&facets.credit), [("1", 1), ("2", 2)]);
+}
+
+async fn timetable_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    for (code, slots) in [
+        ("MTH.A201", vec![(Day::Monday, Period::First)]),
+        ("MTH.A202", vec![(Day::Monday, Period::Second)]),
+        ("MTH.A203", vec![(Day::Wednesday, Period::First)]),
+        (
+            "MTH.A204",
+            vec![(Day::Monday, Period::First), (Day::Thursday, Period::Third)],
+        ),
+        ("MTH.A205", vec![]),
+    ] {
+        let mut course = common::course(code, code, "数学系", 2024);
+        course.time_table = slots
+            .into_iter()
+            .map(|(day, period)| TimeTable {
+                day,
+                period,
+                room: "H111".to_string(),
+            })
+            .collect();
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    pool
+}
+
+fn slots(mode: TimetableMode, slots: &[(Day, Period)]) -> SearchQuery {
+    SearchQuery {
+        timetable: slots
+            .iter()
+            .map(|(day, period)| TimetableQuery {
+                day: day.clone(),
+                period: period.clone(),
+            })
+            .collect(),
+        timetable_mode: mode,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn timetable_slots_match_as_day_period_pairs() {
+    let pool = timetable_pool().await;
+
+    // Mon-1 and Wed-2 must not match Mon-2 or Wed-1
+    let query = slots(
+        TimetableMode::Any,
+        &[
+            (Day::Monday, Period::First),
+            (Day::Wednesday, Period::Second),
+        ],
+    );
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A201", "MTH.A204"]);
+
+    let query = slots(
+        TimetableMode::Any,
+        &[
+            (Day::Monday, Period::Second),
+            (Day::Wednesday, Period::First),
+        ],
+    );
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A202", "MTH.A203"]);
+}
+
+#[tokio::test]
+async fn within_mode_keeps_courses_that_fit_in_the_free_slots() {
+    let pool = timetable_pool().await;
+
+    // MTH.A204 also meets on Thursday, and MTH.A205 has no sessions at all
+    let query = slots(
+        TimetableMode::Within,
+        &[
+            (Day::Monday, Period::First),
+            (Day::Wednesday, Period::First),
+        ],
+    );
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A201", "MTH.A203"]);
+
+    let query = slots(
+        TimetableMode::Within,
+        &[(Day::Monday, Period::First), (Day::Thursday, Period::Third)],
+    );
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A201", "MTH.A204"]);
+
+    let query: SearchQuery = serde_json::from_str(
+        r#"{"university": [], "department": [], "year": [], "title": [], "lecturer": [],
+            "grade": [], "quarter": [],
+            "timetable": [{"day": "Monday", "period": "Second"}],
+            "timetable_mode": "Within"}"#,
+    )
+    .unwrap();
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A202"]);
+}
+
+async fn detail_pool() -> SqlitePool {
+    let pool = common::memory_pool().await;
+
+    for (code, language, credit, lecture_type, keywords, competencies) in [
+        (
+            "MTH.A201",
+            "日本語",
+            2,
+            "講義",
+            vec!["行列"],
+            vec!["専門力"],
+        ),
+        (
+            "MTH.A202",
+            "英語",
+            1,
+            "演習",
+            vec!["行列", "固有値"],
+            vec![],
+        ),
+        (
+            "MTH.A203",
+            "英語",
+            2,
+            "講義",
+            vec![],
+            vec!["専門力", "展開力"],
+        ),
+        ("MTH.A204", "", 4, "", vec![], vec![]),
+    ] {
+        let mut course = common::course(code, code, "数学系", 2024);
+        course.language = language.to_string();
+        course.credit = credit;
+        course.lecture_type = lecture_type.to_string();
+        course.course_detail.keyword = strings(&keywords);
+        course.course_detail.competencies = strings(&competencies);
+        database::insert_course(&pool, &course).await.unwrap();
+    }
+
+    pool
+}
+
+#[tokio::test]
+async fn detail_filters_select_courses() {
+    let pool = detail_pool().await;
+
+    let query = SearchQuery {
+        language: strings(&["英語"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A202", "MTH.A203"]);
+
+    let query = SearchQuery {
+        credit: vec![1, 4],
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A202", "MTH.A204"]);
+
+    let query = SearchQuery {
+        lecture_type: strings(&["講義"]),
+        language: strings(&["英語"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A203"]);
+
+    let query = SearchQuery {
+        keyword: strings(&["行列"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A201", "MTH.A202"]);
+
+    let query = SearchQuery {
+        competency: strings(&["展開力", "存在しない"]),
+        ..Default::default()
+    };
+    assert_eq!(search_codes(&pool, query).await, ["MTH.A203"]);
+
+    // the language facet still counts the other languages
+    let query = SearchQuery {
+        language: strings(&["英語"]),
+        ..Default::default()
+    };
+    let facets = database::search_courses(&pool, query).await.unwrap().facets;
+    assert_eq!(
+        counts(&facets.language),
+        [("", 1), ("日本語", 1), ("英語", 2)]
+    );
+    assert_eq!(counts(&facets.credit), [("1", 1), ("2", 1)]);
+}
+
+#[tokio::test]
+async fn filter_options_list_the_stored_values() {
+    let pool = detail_pool().await;
+
+    let options = database::get_filter_options(&pool).await.unwrap();
+    assert_eq!(options.university, ["東京工業大学"]);
+    assert_eq!(options.department, ["数学系"]);
+    assert_eq!(options.year, [2024]);
+    assert_eq!(options.language, ["日本語", "英語"]);
+    assert_eq!(options.credit, [1, 2, 4]);
+    assert_eq!(options.lecture_type, ["演習", "講義"]);
+    assert_eq!(options.keyword, ["固有値", "行列"]);
+    assert_eq!(options.competency, ["専門力", "展開力"]);
+
+    let options = database::get_filter_options(&common::memory_pool().await)
+        .await
+        .unwrap();
+    assert!(options.department.is_empty());
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use pocket_syllabus_lib::error::AppError;
+use pocket_syllabus_lib::settings::Settings;
+
+#[test]
+fn a_missing_file_is_written_with_the_defaults() {
+    let dir = common::temp_dir("settings-defaults");
+    let path = dir.join("settings.toml");
+
+    let settings = Settings::load(&path).unwrap();
+    assert!(path.exists());
+    assert_eq!(settings.base_url, "https://www.ocw.titech.ac.jp/");
+    assert_eq!(Settings::load(&path).unwrap().departments.len(), 9);
+}
+
+#[test]
+fn saved_settings_load_back_with_a_trailing_slash() {
+    let dir = common::temp_dir("settings-reload");
+    let path = dir.join("settings.toml");
+
+    let mut settings = common::test_settings("http://localhost:8080/ocw");
+    settings.database_path = Some("/tmp/syllabus.db".to_string());
+    settings.save(&path).unwrap();
+    assert_eq!(settings.base_url, "http://localhost:8080/ocw/");
+
+    let loaded = Settings::load(&path).unwrap();
+    assert_eq!(loaded.base_url, "http://localhost:8080/ocw/");
+    assert_eq!(loaded.gakubu_cds(), [1]);
+    assert_eq!(loaded.delay_secs, 0);
+    assert_eq!(loaded.database_path.as_deref(), Some("/tmp/syllabus.db"));
+    assert_eq!(
+        loaded.department_url(4),
+        "http://localhost:8080/ocw/index.php?module=General&action=T0100&GakubuCD=4&lang=JA"
+    );
+
+    // a file edited by hand is fixed up as it is read
+    std::fs::write(&path, "base_url = \"https://ocw.example.com\"").unwrap();
+    let loaded = Settings::load(&path).unwrap();
+    assert_eq!(loaded.base_url, "https://ocw.example.com/");
+}
+
+#[test]
+fn a_base_url_that_is_not_http_is_a_config_error() {
+    let dir = common::temp_dir("settings-invalid");
+    let path = dir.join("settings.toml");
+
+    let mut settings = common::test_settings("www.ocw.titech.ac.jp");
+    let error = settings.save(&path).unwrap_err();
+    assert!(matches!(error, AppError::Config(_)), "{:?}", error);
+    assert!(!path.exists());
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&path, "base_url = \"ftp://ocw.example.com/\"").unwrap();
+    let error = Settings::load(&path).unwrap_err();
+    assert!(matches!(error, AppError::Config(_)), "{:?}", error);
+}
@@ -0,0 +1,268 @@
+mod common;
+
+use pocket_syllabus_lib::database::{
+    self, ArchivedPageRow, RuleAction, RuleField, RuleMatch, SearchRule,
+};
+use pocket_syllabus_lib::error::AppError;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+const SOURCE: &str = "http://www.ocw.titech.ac.jp/";
+
+async fn insert(pool: &SqlitePool, code: &str, title: &str, sylbs_update: &str) {
+    let mut course = common::course(code, title, "数学系", 2024);
+    course.sylbs_update = sylbs_update.to_string();
+    course.lecturer = vec![common::lecturer("山田 太郎")];
+    database::insert_course(pool, &course).await.unwrap();
+}
+
+async fn add_rule(pool: &SqlitePool) {
+    let rule = SearchRule {
+        id: 0,
+        name: "演習を隠す".to_string(),
+        action: RuleAction::Exclude,
+        field: RuleField::Title,
+        match_kind: RuleMatch::Contains,
+        pattern: "演習".to_string(),
+        enabled: true,
+    };
+    database::add_search_rule(pool, &rule).await.unwrap();
+}
+
+async fn sylbs_updates(pool: &SqlitePool) -> Vec<(String, String)> {
+    sqlx::query_as("SELECT code, sylbs_update FROM courses ORDER BY code")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+// A migrated database file like the app's. VACUUM INTO from an in-memory
+// database writes the copy to memory as well.
+async fn file_pool(dir: &Path, name: &str) -> SqlitePool {
+    std::fs::create_dir_all(dir).unwrap();
+    let pool = database::create_sqlite_pool(dir.join(name).to_str().unwrap())
+        .await
+        .unwrap();
+    database::migrate(&pool).await.unwrap();
+    pool
+}
+
+// A snapshot of MTH.A201 updated 2024/03/14 and MTH.A202.
+async fn snapshot(name: &str) -> PathBuf {
+    let dir = common::temp_dir(name);
+    let pool = file_pool(&dir, "database.db").await;
+    insert(&pool, "MTH.A201", "線形代数第一", "2024/03/14").await;
+    insert(&pool, "MTH.A202", "線形代数第二", "2024/03/14").await;
+    add_rule(&pool).await;
+    database::insert_archived_page(
+        &pool,
+        &ArchivedPageRow {
+            id: 0,
+            url: SOURCE.to_string(),
+            kind: "list".to_string(),
+            fetched_at: 0,
+            etag: None,
+            last_modified: None,
+            path: "list.html.gz".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+
+    let path = dir.join("snapshot.db");
+    database::create_snapshot(&pool, &path, SOURCE)
+        .await
+        .unwrap();
+    path
+}
+
+// A connection to a database file that leaves no WAL behind, as snapshots are read
+// as immutable and would not see it.
+async fn open(path: &Path) -> SqlitePool {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Delete);
+    SqlitePool::connect_with(options).await.unwrap()
+}
+
+#[tokio::test]
+async fn snapshots_describe_themselves_and_hold_only_courses() {
+    let path = snapshot("snapshot-create").await;
+
+    let meta = database::get_snapshot_meta(&path).await.unwrap();
+    assert_eq!(meta.source, SOURCE);
+    assert_eq!(meta.year, Some(2024));
+    assert!(meta.created_at > 0);
+    assert_eq!(meta.schema_version, 8);
+
+    let snapshot = open(&path).await;
+    let counts: Vec<(String, i64)> = database::count_rows(&snapshot)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|(table, _)| {
+            ["courses", "archived_pages", "search_rules", "snapshots"].contains(&table.as_str())
+        })
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            ("archived_pages".to_string(), 0),
+            ("courses".to_string(), 2),
+            ("search_rules".to_string(), 0),
+            ("snapshots".to_string(), 1),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn first_launch_seeds_an_empty_database_once() {
+    let path = snapshot("snapshot-seed").await;
+    let pool = common::memory_pool().await;
+
+    let report = database::seed_from_snapshot(&pool, &path)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(report.added, 2);
+    assert_eq!(report.snapshot.source, SOURCE);
+    assert_eq!(database::get_course_ids(&pool).await.unwrap().len(), 2);
+    // the snapshot's search rules are not copied, the default ones stay
+    assert_eq!(database::get_search_rules(&pool).await.unwrap().len(), 3);
+
+    assert!(database::seed_from_snapshot(&pool, &path)
+        .await
+        .unwrap()
+        .is_none());
+    let empty = common::memory_pool().await;
+    assert!(
+        database::seed_from_snapshot(&empty, Path::new("missing.db"))
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // the courses are searchable like crawled ones
+    let query = database::SearchQuery {
+        lecturer: vec!["山田".to_string()],
+        ..Default::default()
+    };
+    let response = database::search_courses(&pool, query).await.unwrap();
+    assert_eq!(response.total, 2);
+}
+
+#[tokio::test]
+async fn newer_snapshots_merge_without_touching_user_data() {
+    let path = snapshot("snapshot-merge").await;
+    let pool = common::memory_pool().await;
+    insert(&pool, "MTH.A201", "線形代数第一", "2024/03/01").await;
+    insert(&pool, "PHY.Q207", "量子力学基礎", "2024/03/20").await;
+    add_rule(&pool).await;
+
+    let report = database::import_snapshot(&pool, &path).await.unwrap();
+    assert_eq!((report.added, report.updated, report.unchanged), (1, 1, 0));
+    assert_eq!(
+        sylbs_updates(&pool).await,
+        [
+            ("MTH.A201".to_string(), "2024/03/14".to_string()),
+            ("MTH.A202".to_string(), "2024/03/14".to_string()),
+            ("PHY.Q207".to_string(), "2024/03/20".to_string()),
+        ]
+    );
+    assert_eq!(database::get_search_rules(&pool).await.unwrap().len(), 4);
+
+    let imported: Vec<(String, Option<i64>)> =
+        sqlx::query_as("SELECT source, imported_at FROM snapshots")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].0, SOURCE);
+    assert!(imported[0].1.is_some());
+
+    let report = database::import_snapshot(&pool, &path).await.unwrap();
+    assert_eq!((report.added, report.updated, report.unchanged), (0, 0, 2));
+
+    // a stored course updated after the snapshot was taken is kept
+    let pool = common::memory_pool().await;
+    insert(&pool, "MTH.A201", "線形代数第一", "2024/04/01").await;
+    let report = database::import_snapshot(&pool, &path).await.unwrap();
+    assert_eq!((report.added, report.updated, report.unchanged), (1, 0, 1));
+    assert_eq!(sylbs_updates(&pool).await[0].1, "2024/04/01");
+}
+
+#[tokio::test]
+async fn a_failed_merge_leaves_the_database_as_it_was() {
+    let path = snapshot("snapshot-rollback").await;
+    let pool = common::memory_pool().await;
+    insert(&pool, "MTH.A201", "線形代数第一", "2024/03/01").await;
+
+    // MTH.A201 is replaced before MTH.A202 fails to go in
+    sqlx::query(
+        "CREATE TEMP TRIGGER no_a202 BEFORE INSERT ON courses
+        WHEN NEW.code = 'MTH.A202'
+        BEGIN SELECT RAISE(ABORT, 'no MTH.A202'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let error = database::import_snapshot(&pool, &path).await.unwrap_err();
+    assert!(matches!(error, AppError::Database(_)), "{:?}", error);
+
+    assert_eq!(
+        sylbs_updates(&pool).await,
+        [("MTH.A201".to_string(), "2024/03/01".to_string())]
+    );
+    let imported: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM snapshots")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(imported, 0);
+}
+
+#[tokio::test]
+async fn unusable_snapshots_are_rejected() {
+    let pool = common::memory_pool().await;
+
+    let error = database::import_snapshot(&pool, Path::new("missing.db"))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+
+    // a database that was never made into a snapshot
+    let plain = snapshot("snapshot-plain").await;
+    let snapshot_pool = open(&plain).await;
+    sqlx::query("DELETE FROM snapshots")
+        .execute(&snapshot_pool)
+        .await
+        .unwrap();
+    snapshot_pool.close().await;
+    let error = database::import_snapshot(&pool, &plain).await.unwrap_err();
+    assert!(matches!(error, AppError::Parse(_)), "{:?}", error);
+
+    // a database without a snapshots table to read
+    let dir = common::temp_dir("snapshot-bare");
+    std::fs::create_dir_all(&dir).unwrap();
+    let bare = dir.join("bare.db");
+    let bare_pool = open(&bare).await;
+    sqlx::query("CREATE TABLE courses (id INTEGER PRIMARY KEY)")
+        .execute(&bare_pool)
+        .await
+        .unwrap();
+    bare_pool.close().await;
+    let error = database::import_snapshot(&pool, &bare).await.unwrap_err();
+    assert!(matches!(error, AppError::Database(_)), "{:?}", error);
+
+    let path = snapshot("snapshot-newer").await;
+    let snapshot = open(&path).await;
+    sqlx::query("UPDATE snapshots SET schema_version = 999")
+        .execute(&snapshot)
+        .await
+        .unwrap();
+    snapshot.close().await;
+    let error = database::import_snapshot(&pool, &path).await.unwrap_err();
+    assert!(matches!(error, AppError::Config(_)), "{:?}", error);
+    assert!(database::get_course_ids(&pool).await.unwrap().is_empty());
+}
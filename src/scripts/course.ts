@@ -65,8 +65,102 @@ export interface CourseListItem {
   semester: string;
   department: string;
   credit: number;
+  snippet: string;
 }
 
+export type SortKey = 'Code' | 'Title' | 'Credit' | 'Department' | 'LastUpdated';
+export type SortOrder = 'Asc' | 'Desc';
+
+export interface FacetCount {
+  value: string;
+  count: number;
+}
+
+// Matches per value, each counted without the facet's own filter.
+export interface Facets {
+  department: FacetCount[];
+  quarter: FacetCount[];
+  slot: FacetCount[];
+  grade: FacetCount[];
+  credit: FacetCount[];
+  language: FacetCount[];
+  lectureType: FacetCount[];
+}
+
+// Courses a search rule left out of the results.
+export interface HiddenCount {
+  rule_id: number;
+  name: string;
+  count: number;
+}
+
+// One page of search results; total counts every match, not just this page.
+export interface SearchResponse {
+  items: CourseListItem[];
+  total: number;
+  facets: Facets;
+  hidden: HiddenCount[];
+}
+
+export const facetCount = (
+  facet: FacetCount[] | undefined,
+  value: string,
+): number | undefined =>
+  facet === undefined
+    ? undefined
+    : (facet.find((count) => count.value === value)?.count ?? 0);
+
+export const PAGE_SIZE = 100;
+
+// The values stored in the database, for the filter menus.
+export interface FilterOptions {
+  university: string[];
+  department: string[];
+  year: number[];
+  language: string[];
+  credit: number[];
+  lectureType: string[];
+  keyword: string[];
+  competency: string[];
+}
+
+// A school, or a department under one; guessed when it was placed by its
+// course codes rather than a crawled list page.
+export interface DepartmentNode {
+  id: number;
+  name: string;
+  ocwCode: number | null;
+  guessed: boolean;
+  children: DepartmentNode[];
+}
+
+// What every failing command rejects with; message is readable as is.
+export interface AppError {
+  kind:
+    | 'NotFound'
+    | 'Database'
+    | 'Network'
+    | 'File'
+    | 'Parse'
+    | 'Cancelled'
+    | 'Config';
+  message: string;
+}
+
+// Splits a search snippet into plain and <mark>ed parts, so it can be rendered
+// without v-html.
+export const splitSnippet = (
+  snippet: string,
+): { text: string; mark: boolean }[] =>
+  snippet
+    .split(/(<mark>.*?<\/mark>)/)
+    .filter((part) => part !== '')
+    .map((part) =>
+      part.startsWith('<mark>')
+        ? { text: part.slice(6, -7), mark: true }
+        : { text: part, mark: false },
+    );
+
 export const getSampleItems = (numSamples: number): CourseListItem[] => {
   const items: CourseListItem[] = [];
   for (let i = 0; i < numSamples; i++) {
@@ -80,6 +174,7 @@ export const getSampleItems = (numSamples: number): CourseListItem[] => {
       semester: '2024前期',
       department: `学科${i}`,
       credit: 2,
+      snippet: '',
     });
   }
   return items;